@@ -1,13 +1,47 @@
-use crate::database::TrackItem;
-use crate::services::{get_active_window, get_idle_time, TrackingStatus, WindowInfo};
+use crate::database::days::parse_date;
+use crate::database::encryption;
+use crate::database::{
+    AttentionConfig, BreakStats, CompactionConfig, CompactionResult, Database, DatabaseKey,
+    DayAttention, DayBoundaries, Goal, GoalHistoryEntry, ItemLabels, LogTimer, PooledDatabase,
+    Project, RangeChanges, Rule, Tag, TrackItem, TrendStats,
+};
+use crate::services::{
+    capture_browser_url, default_browsers, default_exclusions, get_active_window, get_idle_backend,
+    get_idle_time, reapply_rules, redact_history, BreakConfig, BreakStatus, BrowserConfig,
+    ExclusionFilter, ExclusionRule, FocusConfig, FocusStatus, GoalProgress, RedactionConfig,
+    Redactor, RuleApplyResult, RuleEngine, TrackerConfig, TrackingStatus, WindowInfo,
+};
 use crate::AppState;
 use serde::{Deserialize, Serialize};
-use tauri::{Manager, State};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tauri::{Emitter, Manager, State};
 
 // ============================================================================
 // Database Commands
 // ============================================================================
 
+/// Fail while the database waits for its passphrase or is being re-keyed
+///
+/// The connections then point at an in-memory placeholder, and anything
+/// written there would be lost.
+fn ensure_unlocked(state: &AppState) -> Result<(), String> {
+    if state.locked.load(Ordering::SeqCst) {
+        return Err("Database is locked".to_string());
+    }
+    Ok(())
+}
+
+/// The writer connection, unless the database is locked
+///
+/// The flag is checked while holding the connection, so a re-key that
+/// locks the database cannot race a write into the old file.
+fn writer(state: &AppState) -> Result<PooledDatabase<'_>, String> {
+    let db = state.db.write()?;
+    ensure_unlocked(state)?;
+    Ok(db)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResult {
@@ -17,16 +51,23 @@ pub struct SearchResult {
 }
 
 /// Find all items for a specific day
+///
+/// With `clip`, items are cut to the range and split at day boundaries.
 #[tauri::command]
 pub fn find_all_day_items(
     state: State<AppState>,
     from: i64,
     to: i64,
     task_name: String,
+    clip: Option<bool>,
 ) -> Result<Vec<TrackItem>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.find_all_day_items(from, to, &task_name)
-        .map_err(|e| e.to_string())
+    let db = state.db.read()?;
+    if clip.unwrap_or(false) {
+        db.find_clipped_items(from, to, &task_name, &day_boundaries(&db)?)
+    } else {
+        db.find_all_day_items(from, to, &task_name)
+    }
+    .map_err(|e| e.to_string())
 }
 
 /// Create a new track item
@@ -35,8 +76,12 @@ pub fn create_track_item(
     state: State<AppState>,
     track_item: TrackItem,
 ) -> Result<TrackItem, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.create_track_item(&track_item).map_err(|e| e.to_string())
+    let db = writer(&state)?;
+    let item = db
+        .create_track_item(&track_item)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(item)
 }
 
 /// Update an existing track item
@@ -45,15 +90,90 @@ pub fn update_track_item(
     state: State<AppState>,
     track_item: TrackItem,
 ) -> Result<TrackItem, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_track_item(&track_item).map_err(|e| e.to_string())
+    let db = writer(&state)?;
+    let item = db
+        .update_track_item(&track_item)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(item)
 }
 
 /// Delete track items by IDs
 #[tauri::command]
 pub fn delete_by_ids(state: State<AppState>, ids: Vec<i64>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.delete_by_ids(&ids).map_err(|e| e.to_string())
+    let db = writer(&state)?;
+    db.delete_by_ids(&ids).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Split a track item in two at a timestamp
+#[tauri::command]
+pub fn split_track_item(
+    state: State<AppState>,
+    id: i64,
+    at: i64,
+) -> Result<(TrackItem, TrackItem), String> {
+    let db = writer(&state)?;
+    let parts = db.split_track_item(id, at).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(parts)
+}
+
+/// Merge track items of one type into a single item
+#[tauri::command]
+pub fn merge_track_items(state: State<AppState>, ids: Vec<i64>) -> Result<TrackItem, String> {
+    let db = writer(&state)?;
+    let item = db.merge_track_items(&ids).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(item)
+}
+
+/// Move track items by an offset in milliseconds
+#[tauri::command]
+pub fn shift_track_items(
+    state: State<AppState>,
+    ids: Vec<i64>,
+    offset: i64,
+) -> Result<Vec<TrackItem>, String> {
+    let db = writer(&state)?;
+    let items = db
+        .shift_track_items(&ids, offset)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(items)
+}
+
+/// Cut a time range out of the timeline
+#[tauri::command]
+pub fn trim_track_items(
+    state: State<AppState>,
+    from: i64,
+    to: i64,
+    task_name: Option<String>,
+) -> Result<usize, String> {
+    let db = writer(&state)?;
+    let changed = db
+        .trim_track_items(from, to, task_name.as_deref())
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(changed)
+}
+
+/// Change the app, title or project of the app items in a time range
+#[tauri::command]
+pub fn reassign_range(
+    state: State<AppState>,
+    from: i64,
+    to: i64,
+    changes: RangeChanges,
+) -> Result<Vec<TrackItem>, String> {
+    let db = writer(&state)?;
+    let items = db
+        .reassign_range(from, to, &changes)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(items)
 }
 
 /// Search track items
@@ -67,8 +187,8 @@ pub fn search_items(
     limit: Option<i64>,
     offset: Option<i64>,
 ) -> Result<SearchResult, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let (data, total) = db
+    let db = state.db.read()?;
+    let (data, total, total_duration) = db
         .search_items(
             from,
             to,
@@ -79,12 +199,6 @@ pub fn search_items(
         )
         .map_err(|e| e.to_string())?;
 
-    // Calculate total duration
-    let total_duration: i64 = data
-        .iter()
-        .map(|item| item.end_date - item.begin_date)
-        .sum();
-
     Ok(SearchResult {
         data,
         total,
@@ -99,7 +213,7 @@ pub fn update_track_item_color(
     app_name: String,
     color: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = writer(&state)?;
     db.update_track_item_color(&app_name, &color)
         .map_err(|e| e.to_string())
 }
@@ -115,9 +229,17 @@ pub fn get_app_version() -> String {
 // ============================================================================
 
 /// Get the currently active window
+///
+/// The URL is only filled in when URL tracking is enabled.
 #[tauri::command]
-pub fn get_current_window() -> Result<WindowInfo, String> {
-    get_active_window()
+pub fn get_current_window(state: State<AppState>) -> Result<WindowInfo, String> {
+    let mut window = get_active_window()?;
+
+    let config = state.tracker.config();
+    if config.track_urls {
+        capture_browser_url(&mut window, &config.browsers);
+    }
+    Ok(window)
 }
 
 /// Get the current tracking status
@@ -134,6 +256,7 @@ pub fn get_tracking_status(state: State<AppState>) -> TrackingStatus {
         is_running: tracker.is_running(),
         is_paused: tracker.is_paused(),
         current_state: tracker.get_system_state(),
+        idle_backend: get_idle_backend(),
         current_app,
         current_title,
     }
@@ -142,14 +265,15 @@ pub fn get_tracking_status(state: State<AppState>) -> TrackingStatus {
 /// Start tracking
 #[tauri::command]
 pub fn start_tracking(state: State<AppState>, app_handle: tauri::AppHandle) -> Result<(), String> {
+    ensure_unlocked(&state)?;
     state.tracker.start(app_handle);
     Ok(())
 }
 
 /// Stop tracking
 #[tauri::command]
-pub fn stop_tracking(state: State<AppState>) -> Result<(), String> {
-    state.tracker.stop();
+pub async fn stop_tracking(state: State<'_, AppState>) -> Result<(), String> {
+    state.tracker.stop().await;
     Ok(())
 }
 
@@ -173,6 +297,234 @@ pub fn get_idle_time_command() -> f64 {
     get_idle_time().as_secs_f64()
 }
 
+// ============================================================================
+// Log Timer Commands
+// ============================================================================
+
+/// Tell the frontend and the tray that the log timer changed
+///
+/// A logged item also counts toward the goals.
+fn log_timer_changed(
+    state: &AppState,
+    app: &tauri::AppHandle,
+    timer: Option<&LogTimer>,
+    saved: Option<&TrackItem>,
+    days: &DayBoundaries,
+) {
+    if let Some(item) = saved {
+        state.goals.record_item(app, item);
+        let _ = app.emit("track-item-saved", item);
+    }
+    let _ = app.emit("log-timer-changed", timer);
+    crate::tray::show_log_timer(app, timer, days);
+}
+
+/// Start a manual timer for time no window tracker sees
+///
+/// A timer that is already running is stopped and logged first.
+#[tauri::command]
+pub fn start_log_timer(
+    state: State<AppState>,
+    app: tauri::AppHandle,
+    title: String,
+    project_id: Option<i64>,
+) -> Result<LogTimer, String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("Timer title is empty".to_string());
+    }
+
+    let db = writer(&state)?;
+    let (timer, stopped) = db
+        .start_log_timer(title, project_id, chrono::Utc::now().timestamp_millis())
+        .map_err(|e| e.to_string())?;
+    let days = day_boundaries(&db)?;
+    drop(db);
+
+    log_timer_changed(&state, &app, Some(&timer), stopped.as_ref(), &days);
+    Ok(timer)
+}
+
+/// Stop the running timer and save it as a `LogTrackItem`
+///
+/// Returns the saved item; `None` if no timer ran or it was too short.
+#[tauri::command]
+pub fn stop_log_timer(
+    state: State<AppState>,
+    app: tauri::AppHandle,
+) -> Result<Option<TrackItem>, String> {
+    let db = writer(&state)?;
+    let item = db
+        .stop_log_timer(chrono::Utc::now().timestamp_millis())
+        .map_err(|e| e.to_string())?;
+    let days = day_boundaries(&db)?;
+    drop(db);
+
+    log_timer_changed(&state, &app, None, item.as_ref(), &days);
+    Ok(item)
+}
+
+/// Get the running log timer, if any
+#[tauri::command]
+pub fn get_running_log_timer(state: State<AppState>) -> Result<Option<LogTimer>, String> {
+    let db = state.db.read()?;
+    db.get_log_timer().map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Focus Session Commands
+// ============================================================================
+
+/// Start a focus session with a focus block
+#[tauri::command]
+pub fn start_focus_session(
+    state: State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<FocusStatus, String> {
+    ensure_unlocked(&state)?;
+    state.focus.start(app_handle)
+}
+
+/// Pause the focus countdown
+///
+/// Like skipping and stopping, this records the finished stretch, so it
+/// waits while the database is locked.
+#[tauri::command]
+pub fn pause_focus_session(
+    state: State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<FocusStatus, String> {
+    ensure_unlocked(&state)?;
+    Ok(state.focus.pause(&app_handle))
+}
+
+/// Resume the focus countdown
+#[tauri::command]
+pub fn resume_focus_session(state: State<AppState>, app_handle: tauri::AppHandle) -> FocusStatus {
+    state.focus.resume(&app_handle)
+}
+
+/// End the current phase and move on to the next
+#[tauri::command]
+pub fn skip_focus_phase(
+    state: State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<FocusStatus, String> {
+    ensure_unlocked(&state)?;
+    Ok(state.focus.skip(&app_handle))
+}
+
+/// End the focus session
+#[tauri::command]
+pub async fn stop_focus_session(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<FocusStatus, String> {
+    ensure_unlocked(&state)?;
+    Ok(state.focus.stop(&app_handle).await)
+}
+
+/// Get the focus session status
+#[tauri::command]
+pub fn get_focus_status(state: State<AppState>) -> FocusStatus {
+    state.focus.status()
+}
+
+// ============================================================================
+// Break Reminder Commands
+// ============================================================================
+
+/// Get continuous active time and whether a break reminder is pending
+#[tauri::command]
+pub fn get_break_status(state: State<AppState>) -> BreakStatus {
+    state.breaks.status()
+}
+
+/// Snooze the pending break reminder
+#[tauri::command]
+pub fn snooze_break_reminder(state: State<AppState>) -> BreakStatus {
+    state.breaks.snooze()
+}
+
+/// Dismiss the pending break reminder
+#[tauri::command]
+pub fn dismiss_break_reminder(state: State<AppState>) -> BreakStatus {
+    state.breaks.dismiss()
+}
+
+/// Get how break reminders shown on local dates `from_date` to `to_date`
+/// went
+#[tauri::command]
+pub fn get_break_stats(
+    state: State<AppState>,
+    from_date: String,
+    to_date: String,
+) -> Result<BreakStats, String> {
+    let db = state.db.read()?;
+    let (from, to) = local_date_range(&day_boundaries(&db)?, &from_date, &to_date)?;
+    db.get_break_stats(from, to).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Goal Commands
+// ============================================================================
+
+/// List goals and limits
+#[tauri::command]
+pub fn get_goals(state: State<AppState>) -> Result<Vec<Goal>, String> {
+    let db = state.db.read()?;
+    db.get_goals().map_err(|e| e.to_string())
+}
+
+/// Create or update a goal or limit
+#[tauri::command]
+pub fn save_goal(state: State<AppState>, goal: Goal) -> Result<Goal, String> {
+    if goal.name.trim().is_empty() {
+        return Err("Goal name is empty".to_string());
+    }
+    if goal.duration <= 0 {
+        return Err("Goal duration must be positive".to_string());
+    }
+    if let Some(day) = goal.weekdays.iter().find(|&&day| day > 6) {
+        return Err(format!("Invalid weekday: {}", day));
+    }
+
+    let db = writer(&state)?;
+    let saved = db.save_goal(&goal).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(saved)
+}
+
+/// Delete a goal or limit and its history
+#[tauri::command]
+pub fn delete_goal(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.delete_goal(id).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Get the progress of the goals that apply right now
+#[tauri::command]
+pub fn get_goal_progress(state: State<AppState>) -> Result<Vec<GoalProgress>, String> {
+    state.goals.progress()
+}
+
+/// Get how a goal went in the periods starting on local dates
+/// `from_date` to `to_date`, for charting streaks
+#[tauri::command]
+pub fn get_goal_history(
+    state: State<AppState>,
+    goal_id: i64,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<GoalHistoryEntry>, String> {
+    let db = state.db.read()?;
+    let (from, to) = local_date_range(&day_boundaries(&db)?, &from_date, &to_date)?;
+    db.get_goal_history(goal_id, from, to)
+        .map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Statistics Commands (for Summary page)
 // ============================================================================
@@ -186,38 +538,61 @@ pub struct AppUsageStats {
     pub color: Option<String>,
 }
 
-/// Get app usage statistics for a time range
+/// Day boundaries from the timezone and start-of-day settings
+fn day_boundaries(db: &Database) -> Result<DayBoundaries, String> {
+    let settings = db.get_settings().map_err(|e| e.to_string())?;
+    DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)
+}
+
+/// `[from, to)` of the local dates `from_date` to `to_date`, inclusive
+fn local_date_range(
+    days: &DayBoundaries,
+    from_date: &str,
+    to_date: &str,
+) -> Result<(i64, i64), String> {
+    Ok(days.date_range(parse_date(from_date)?, parse_date(to_date)?))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRange {
+    pub from: i64,
+    pub to: i64,
+}
+
+/// Get the `[from, to)` range of local dates (`YYYY-MM-DD`, inclusive),
+/// using the timezone and start-of-day settings
+#[tauri::command]
+pub fn get_date_range(
+    state: State<AppState>,
+    from_date: String,
+    to_date: String,
+) -> Result<DateRange, String> {
+    let db = state.db.read()?;
+    let (from, to) = local_date_range(&day_boundaries(&db)?, &from_date, &to_date)?;
+    Ok(DateRange { from, to })
+}
+
+/// Get app usage statistics for local dates `from_date` to `to_date`
 #[tauri::command]
 pub fn get_app_usage_stats(
     state: State<AppState>,
-    from: i64,
-    to: i64,
+    from_date: String,
+    to_date: String,
 ) -> Result<Vec<AppUsageStats>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-
-    // Get all AppTrackItems for the time range
-    let items = db
-        .find_all_day_items(from, to, "AppTrackItem")
+    let db = state.db.read()?;
+    let days = day_boundaries(&db)?;
+    let (from, to) = local_date_range(&days, &from_date, &to_date)?;
+    let durations = db
+        .get_app_durations(from, to, &days)
         .map_err(|e| e.to_string())?;
 
-    // Aggregate by app name
-    let mut app_durations: std::collections::HashMap<String, (i64, Option<String>)> =
-        std::collections::HashMap::new();
-
-    for item in &items {
-        let duration = item.end_date - item.begin_date;
-        let entry = app_durations
-            .entry(item.app.clone())
-            .or_insert((0, item.color.clone()));
-        entry.0 += duration;
-    }
-
     // Calculate total and percentages
-    let total_duration: i64 = app_durations.values().map(|(d, _)| d).sum();
+    let total_duration: i64 = durations.iter().map(|(_, _, d)| d).sum();
 
-    let mut stats: Vec<AppUsageStats> = app_durations
+    let stats = durations
         .into_iter()
-        .map(|(app, (duration, color))| {
+        .map(|(app, color, duration)| {
             let percentage = if total_duration > 0 {
                 (duration as f64 / total_duration as f64) * 100.0
             } else {
@@ -232,12 +607,48 @@ pub fn get_app_usage_stats(
         })
         .collect();
 
-    // Sort by duration descending
-    stats.sort_by(|a, b| b.total_duration.cmp(&a.total_duration));
-
     Ok(stats)
 }
 
+/// Get daily totals with rolling averages, time per weekday and hour, and
+/// week-over-week and month-over-month deltas per app, domain and project
+/// for local dates `from_date` to `to_date`
+#[tauri::command]
+pub fn get_trend_stats(
+    state: State<AppState>,
+    from_date: String,
+    to_date: String,
+) -> Result<TrendStats, String> {
+    let (first, last) = (parse_date(&from_date)?, parse_date(&to_date)?);
+    if first > last {
+        return Err("Start date is after end date".to_string());
+    }
+
+    let db = state.db.read()?;
+    db.get_trend_stats(first, last, &day_boundaries(&db)?)
+        .map_err(|e| e.to_string())
+}
+
+/// Get app switches, stretch lengths per app and project, deep work
+/// blocks and fragmentation for each local date from `from_date` to
+/// `to_date`
+#[tauri::command]
+pub fn get_attention_stats(
+    state: State<AppState>,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<DayAttention>, String> {
+    let (first, last) = (parse_date(&from_date)?, parse_date(&to_date)?);
+    if first > last {
+        return Err("Start date is after end date".to_string());
+    }
+
+    let db = state.db.read()?;
+    let settings = db.get_settings().map_err(|e| e.to_string())?;
+    db.get_attention_stats(first, last, &day_boundaries(&db)?, &settings.attention)
+        .map_err(|e| e.to_string())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DomainUsageStats {
@@ -247,50 +658,35 @@ pub struct DomainUsageStats {
     pub page_count: i64,
 }
 
-/// Get domain usage statistics for a specific browser app
+/// Get domain usage statistics for a specific browser app for local dates
+/// `from_date` to `to_date`
 #[tauri::command]
 pub fn get_domain_usage_stats(
     state: State<AppState>,
-    from: i64,
-    to: i64,
+    from_date: String,
+    to_date: String,
     app_name: String,
 ) -> Result<Vec<DomainUsageStats>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-
-    // Get all AppTrackItems for the time range
-    let items = db
-        .find_all_day_items(from, to, "AppTrackItem")
+    let db = state.db.read()?;
+    let days = day_boundaries(&db)?;
+    let (from, to) = local_date_range(&days, &from_date, &to_date)?;
+    let durations = db
+        .get_domain_durations(from, to, &app_name, &days)
         .map_err(|e| e.to_string())?;
 
-    // Filter by app name and aggregate by domain
-    let mut domain_durations: std::collections::HashMap<String, (i64, i64)> =
-        std::collections::HashMap::new();
-
-    for item in &items {
-        if item.app != app_name {
-            continue;
-        }
-
-        let domain = item.domain.clone().unwrap_or_else(|| "Other".to_string());
-        let duration = item.end_date - item.begin_date;
-        let entry = domain_durations.entry(domain).or_insert((0, 0));
-        entry.0 += duration;
-        entry.1 += 1; // page count
-    }
-
     // Calculate total and percentages
-    let total_duration: i64 = domain_durations.values().map(|(d, _)| d).sum();
+    let total_duration: i64 = durations.iter().map(|(_, d, _)| d).sum();
 
-    let mut stats: Vec<DomainUsageStats> = domain_durations
+    let stats = durations
         .into_iter()
-        .map(|(domain, (duration, page_count))| {
+        .map(|(domain, duration, page_count)| {
             let percentage = if total_duration > 0 {
                 (duration as f64 / total_duration as f64) * 100.0
             } else {
                 0.0
             };
             DomainUsageStats {
-                domain,
+                domain: domain.unwrap_or_else(|| "Other".to_string()),
                 total_duration: duration,
                 percentage,
                 page_count,
@@ -298,12 +694,295 @@ pub fn get_domain_usage_stats(
         })
         .collect();
 
-    // Sort by duration descending
-    stats.sort_by(|a, b| b.total_duration.cmp(&a.total_duration));
+    Ok(stats)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectUsageStats {
+    /// `None` for time not assigned to any project
+    pub project_id: Option<i64>,
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub total_duration: i64,
+    pub percentage: f64,
+}
+
+/// Get app usage statistics grouped by project for local dates
+/// `from_date` to `to_date`
+#[tauri::command]
+pub fn get_project_usage_stats(
+    state: State<AppState>,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<ProjectUsageStats>, String> {
+    let db = state.db.read()?;
+    let (from, to) = local_date_range(&day_boundaries(&db)?, &from_date, &to_date)?;
+    let durations = db
+        .get_project_durations(from, to)
+        .map_err(|e| e.to_string())?;
+
+    // Calculate total and percentages
+    let total_duration: i64 = durations.iter().map(|(_, d)| d).sum();
+
+    let stats = durations
+        .into_iter()
+        .map(|(project, duration)| {
+            let percentage = if total_duration > 0 {
+                (duration as f64 / total_duration as f64) * 100.0
+            } else {
+                0.0
+            };
+            ProjectUsageStats {
+                project_id: project.as_ref().map(|p| p.id),
+                name: project.as_ref().map(|p| p.name.clone()),
+                color: project.and_then(|p| p.color),
+                total_duration: duration,
+                percentage,
+            }
+        })
+        .collect();
 
     Ok(stats)
 }
 
+// ============================================================================
+// Project & Tag Commands
+// ============================================================================
+
+/// List projects
+#[tauri::command]
+pub fn get_projects(
+    state: State<AppState>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Project>, String> {
+    let db = state.db.read()?;
+    db.get_projects(include_archived.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// Create a project
+#[tauri::command]
+pub fn create_project(
+    state: State<AppState>,
+    name: String,
+    color: Option<String>,
+) -> Result<Project, String> {
+    let db = writer(&state)?;
+    db.create_project(&name, color.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Rename a project
+#[tauri::command]
+pub fn rename_project(state: State<AppState>, id: i64, name: String) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.rename_project(id, &name).map_err(|e| e.to_string())
+}
+
+/// Archive or unarchive a project
+#[tauri::command]
+pub fn archive_project(state: State<AppState>, id: i64, archived: bool) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.set_project_archived(id, archived)
+        .map_err(|e| e.to_string())
+}
+
+/// Merge projects into a target project
+#[tauri::command]
+pub fn merge_projects(
+    state: State<AppState>,
+    source_ids: Vec<i64>,
+    target_id: i64,
+) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.merge_projects(&source_ids, target_id)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Assign a project to track items
+#[tauri::command]
+pub fn assign_project(
+    state: State<AppState>,
+    project_id: i64,
+    item_ids: Vec<i64>,
+) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.assign_project(project_id, &item_ids)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Remove a project from track items
+#[tauri::command]
+pub fn unassign_project(
+    state: State<AppState>,
+    project_id: i64,
+    item_ids: Vec<i64>,
+) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.unassign_project(project_id, &item_ids)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// List tags
+#[tauri::command]
+pub fn get_tags(state: State<AppState>) -> Result<Vec<Tag>, String> {
+    let db = state.db.read()?;
+    db.get_tags().map_err(|e| e.to_string())
+}
+
+/// Create a tag
+#[tauri::command]
+pub fn create_tag(
+    state: State<AppState>,
+    name: String,
+    color: Option<String>,
+) -> Result<Tag, String> {
+    let db = writer(&state)?;
+    db.create_tag(&name, color.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a tag
+#[tauri::command]
+pub fn delete_tag(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.delete_tag(id).map_err(|e| e.to_string())?;
+    reload_tracker_rules(&state, &db)?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Add a tag to track items
+#[tauri::command]
+pub fn tag_items(state: State<AppState>, tag_id: i64, item_ids: Vec<i64>) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.tag_items(tag_id, &item_ids).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Remove a tag from track items
+#[tauri::command]
+pub fn untag_items(state: State<AppState>, tag_id: i64, item_ids: Vec<i64>) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.untag_items(tag_id, &item_ids)
+        .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Get the projects and tags of track items
+#[tauri::command]
+pub fn get_item_labels(
+    state: State<AppState>,
+    item_ids: Vec<i64>,
+) -> Result<Vec<ItemLabels>, String> {
+    let db = state.db.read()?;
+    db.get_item_labels(&item_ids).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Rule Commands
+// ============================================================================
+
+/// Hand the current rules to the running tracker
+fn reload_tracker_rules(state: &AppState, db: &Database) -> Result<(), String> {
+    let rules = db.get_rules().map_err(|e| e.to_string())?;
+    state.tracker.set_rules(&rules, day_boundaries(db)?);
+    Ok(())
+}
+
+/// List classification rules in evaluation order
+#[tauri::command]
+pub fn get_rules(state: State<AppState>) -> Result<Vec<Rule>, String> {
+    let db = state.db.read()?;
+    db.get_rules().map_err(|e| e.to_string())
+}
+
+/// Create or update a classification rule
+#[tauri::command]
+pub fn save_rule(state: State<AppState>, rule: Rule) -> Result<Rule, String> {
+    RuleEngine::validate(&rule)?;
+
+    let db = writer(&state)?;
+    if !db
+        .rule_labels_exist(&rule.actions)
+        .map_err(|e| e.to_string())?
+    {
+        return Err("Rule assigns a project or tag that does not exist".to_string());
+    }
+    let saved = db.save_rule(&rule).map_err(|e| e.to_string())?;
+    reload_tracker_rules(&state, &db)?;
+    Ok(saved)
+}
+
+/// Delete a classification rule
+#[tauri::command]
+pub fn delete_rule(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.delete_rule(id).map_err(|e| e.to_string())?;
+    reload_tracker_rules(&state, &db)
+}
+
+/// Change the evaluation order of rules
+#[tauri::command]
+pub fn reorder_rules(state: State<AppState>, ids: Vec<i64>) -> Result<(), String> {
+    let db = writer(&state)?;
+    db.reorder_rules(&ids).map_err(|e| e.to_string())?;
+    reload_tracker_rules(&state, &db)
+}
+
+/// Re-apply rules to the app items in a time range
+///
+/// With `dry_run` only reports how many items would change.
+#[tauri::command]
+pub fn apply_rules_to_range(
+    state: State<AppState>,
+    from: i64,
+    to: i64,
+    dry_run: bool,
+) -> Result<RuleApplyResult, String> {
+    let db = writer(&state)?;
+    let rules = db.get_rules().map_err(|e| e.to_string())?;
+    let engine = RuleEngine::new(&rules, day_boundaries(&db)?);
+    let result = reapply_rules(&db, &engine, from, to, dry_run).map_err(|e| e.to_string())?;
+    state.goals.invalidate();
+    Ok(result)
+}
+
+// ============================================================================
+// Compaction Commands
+// ============================================================================
+
+/// Merge touching app items of the same kind in a time range and absorb
+/// blips into their neighbors, using the compaction settings
+///
+/// With `dry_run` only reports how many items would be merged.
+#[tauri::command]
+pub fn compact_timeline(
+    state: State<AppState>,
+    from: i64,
+    to: i64,
+    dry_run: bool,
+) -> Result<CompactionResult, String> {
+    let db = writer(&state)?;
+    let settings = db.get_settings().map_err(|e| e.to_string())?;
+    let result = db
+        .compact_track_items(from, to, &settings.compaction, dry_run)
+        .map_err(|e| e.to_string())?;
+    if result.applied {
+        state.goals.invalidate();
+    }
+    Ok(result)
+}
+
 // ============================================================================
 // Settings Commands
 // ============================================================================
@@ -317,7 +996,16 @@ pub struct AppSettings {
     pub polling_interval: u64, // seconds
     pub idle_threshold: u64,   // seconds
     pub track_urls: bool,
+    pub browsers: Vec<BrowserConfig>, // browsers URLs can be read from
+    pub exclusions: Vec<ExclusionRule>, // windows never recorded as-is
+    pub redaction: RedactionConfig,
     pub hide_dock: bool,
+    pub timezone: Option<String>, // IANA name; None for the system zone
+    pub day_start_hour: u32,      // local hour days start at, 0-23
+    pub focus: FocusConfig,       // focus session lengths and alerts
+    pub breaks: BreakConfig,      // reminders after long active stretches
+    pub attention: AttentionConfig, // flicker and deep work thresholds
+    pub compaction: CompactionConfig, // merging of adjacent and tiny items
 }
 
 impl Default for AppSettings {
@@ -329,7 +1017,16 @@ impl Default for AppSettings {
             polling_interval: 3,
             idle_threshold: 300,
             track_urls: false,
+            browsers: default_browsers(),
+            exclusions: default_exclusions(),
+            redaction: RedactionConfig::default(),
             hide_dock: false,
+            timezone: None,
+            day_start_hour: 0,
+            focus: FocusConfig::default(),
+            breaks: BreakConfig::default(),
+            attention: AttentionConfig::default(),
+            compaction: CompactionConfig::default(),
         }
     }
 }
@@ -337,21 +1034,60 @@ impl Default for AppSettings {
 /// Get application settings
 #[tauri::command]
 pub fn get_settings(state: State<AppState>) -> Result<AppSettings, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = state.db.read()?;
     db.get_settings().map_err(|e| e.to_string())
 }
 
 /// Save application settings
+///
+/// Tracker-related settings are applied to the running tracker right away.
 #[tauri::command]
 pub fn save_settings(state: State<AppState>, settings: AppSettings) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.save_settings(&settings).map_err(|e| e.to_string())
+    ExclusionFilter::validate(&settings.exclusions)?;
+    Redactor::validate(&settings.redaction)?;
+    DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)?;
+
+    let db = writer(&state)?;
+    db.save_settings(&settings).map_err(|e| e.to_string())?;
+
+    state.tracker.update_config(TrackerConfig::from(&settings));
+    state.focus.update_config(settings.focus);
+    state.breaks.update_config(settings.breaks);
+    state.compaction.update_config(settings.compaction);
+    state.goals.invalidate();
+    // Time-of-day conditions follow the time zone
+    reload_tracker_rules(&state, &db)
+}
+
+/// Re-run redaction over stored items in a time range
+///
+/// Uses the current redaction settings.
+#[tauri::command]
+pub fn redact_stored_items(
+    state: State<AppState>,
+    from: i64,
+    to: i64,
+) -> Result<RedactionResult, String> {
+    let redactor = Redactor::new(&state.tracker.config().redaction);
+
+    let db = writer(&state)?;
+    let updated = redact_history(&db, &redactor, from, to).map_err(|e| e.to_string())?;
+
+    Ok(RedactionResult {
+        items_updated: updated,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionResult {
+    pub items_updated: i64,
 }
 
 /// Get all tracked apps (for color management)
 #[tauri::command]
 pub fn get_tracked_apps(state: State<AppState>) -> Result<Vec<TrackedApp>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = state.db.read()?;
     db.get_tracked_apps().map_err(|e| e.to_string())
 }
 
@@ -363,17 +1099,27 @@ pub struct TrackedApp {
     pub total_time: i64,
 }
 
+/// Path of the database file
+fn database_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_dir.join("timlyzer.db"))
+}
+
 /// Get database info
 #[tauri::command]
-pub fn get_database_info(app: tauri::AppHandle) -> Result<DatabaseInfo, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("timlyzer.db");
+pub fn get_database_info(
+    state: State<AppState>,
+    app: tauri::AppHandle,
+) -> Result<DatabaseInfo, String> {
+    let db_path = database_path(&app)?;
 
     let size = std::fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
 
     Ok(DatabaseInfo {
         path: db_path.to_string_lossy().to_string(),
         size_bytes: size,
+        encrypted: encryption::is_encrypted_file(&db_path),
+        locked: state.locked.load(Ordering::SeqCst),
     })
 }
 
@@ -382,15 +1128,20 @@ pub fn get_database_info(app: tauri::AppHandle) -> Result<DatabaseInfo, String>
 pub struct DatabaseInfo {
     pub path: String,
     pub size_bytes: u64,
+    /// Whether the file is encrypted at rest
+    pub encrypted: bool,
+    /// Whether the database is waiting for its passphrase
+    pub locked: bool,
 }
 
 /// Clear data before a specific date
 #[tauri::command]
 pub fn clear_data_before(state: State<AppState>, before_date: i64) -> Result<ClearResult, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = writer(&state)?;
     let deleted = db
         .clear_data_before(before_date)
         .map_err(|e| e.to_string())?;
+    state.goals.invalidate();
 
     Ok(ClearResult {
         items_deleted: deleted,
@@ -403,6 +1154,241 @@ pub struct ClearResult {
     pub items_deleted: i64,
 }
 
+// ============================================================================
+// Encryption Commands
+// ============================================================================
+
+/// Stop everything that writes in the background
+///
+/// A focus session keeps going; only its countdown loop stops. Returns
+/// whether the tracker was running.
+async fn stop_services(state: &AppState) -> bool {
+    let was_tracking = state.tracker.is_running();
+    state.tracker.stop().await;
+    state.focus.suspend_loop().await;
+    state.breaks.stop().await;
+    state.compaction.stop().await;
+    state.rollups.stop().await;
+    was_tracking
+}
+
+/// Start the background services, and the tracker if `tracking`
+fn start_services(state: &AppState, app: &tauri::AppHandle, tracking: bool) {
+    if tracking {
+        state.tracker.start(app.clone());
+    }
+    state.focus.resume_loop(app.clone());
+    state.breaks.start(app.clone());
+    state.compaction.start(app.clone());
+    state.rollups.start();
+}
+
+/// Reopen the shared connections to the database file with a key
+///
+/// Unlocks the database once the file is open.
+fn reopen_database(state: &AppState, path: &Path, key: Option<&DatabaseKey>) -> Result<(), String> {
+    let path = path.to_str().ok_or("Invalid database path")?;
+    state.db.reopen(path, key)?;
+    *state.db_key.lock().map_err(|e| e.to_string())? = key.cloned();
+    state.locked.store(false, Ordering::SeqCst);
+    state.goals.invalidate();
+    Ok(())
+}
+
+/// Replace the database file with a copy encrypted with `key`
+///
+/// All connections are closed before the swap so the write-ahead log is
+/// folded into the old file, then reopened with the new key. The database
+/// stays locked from the copy until the file is reopened, so no write is
+/// lost with the old file.
+///
+/// The key must be staged in the keyring first; it replaces the current
+/// entry only once the file opens with it.
+fn encrypt_database_file(state: &AppState, path: &Path, key: &DatabaseKey) -> Result<(), String> {
+    let encrypted_path = path.with_extension("db.encrypting");
+    let _ = std::fs::remove_file(&encrypted_path);
+
+    {
+        let db = state.db.write()?;
+        state.locked.store(true, Ordering::SeqCst);
+        if let Err(e) = db.export_copy(&encrypted_path.to_string_lossy(), Some(key)) {
+            let _ = std::fs::remove_file(&encrypted_path);
+            state.locked.store(false, Ordering::SeqCst);
+            return Err(e.to_string());
+        }
+    }
+
+    let previous = state.db_key.lock().map_err(|e| e.to_string())?.clone();
+    state.db.reopen(":memory:", None)?;
+
+    if let Err(e) = std::fs::rename(&encrypted_path, path) {
+        let _ = std::fs::remove_file(&encrypted_path);
+        reopen_database(state, path, previous.as_ref())?;
+        return Err(e.to_string());
+    }
+    // From here on the file only opens with the new key, so the staged
+    // entry stays until it is remembered
+    reopen_database(state, path, Some(key))?;
+    if let Err(e) = encryption::remember_key(Some(key)) {
+        log::error!("Failed to remember database key: {}", e);
+    }
+    Ok(())
+}
+
+/// Encrypt with the given passphrase, or with a generated key kept in the
+/// OS secret store when none is given
+async fn set_database_key(
+    state: &AppState,
+    app: &tauri::AppHandle,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let key = match passphrase {
+        Some(passphrase) if !passphrase.is_empty() => DatabaseKey::Passphrase(passphrase),
+        _ => DatabaseKey::generate()?,
+    };
+    let path = database_path(app)?;
+
+    // Save the key before the data depends on it, next to the current one
+    encryption::stage_key(&key)?;
+
+    let was_tracking = stop_services(state).await;
+    let result = encrypt_database_file(state, &path, &key);
+    if let Err(e) = &result {
+        log::error!("Failed to encrypt database: {}", e);
+        // The old file is still in place unless it is locked now
+        if !state.locked.load(Ordering::SeqCst) {
+            if let Err(e) = encryption::discard_staged_key() {
+                log::error!("Failed to discard staged database key: {}", e);
+            }
+        }
+    }
+
+    // A swap that could not reopen the file leaves the database locked
+    if !state.locked.load(Ordering::SeqCst) {
+        start_services(state, app, was_tracking);
+    }
+    result
+}
+
+/// Encrypt an unencrypted database
+///
+/// Without a passphrase a random key is generated and kept in the OS
+/// secret store.
+#[tauri::command]
+pub async fn enable_encryption(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    if encryption::is_encrypted_file(&database_path(&app)?) {
+        return Err("Database is already encrypted".to_string());
+    }
+    set_database_key(&state, &app, passphrase).await?;
+    log::info!("Database encryption enabled");
+    Ok(())
+}
+
+/// Re-encrypt an encrypted database with a new passphrase or generated key
+#[tauri::command]
+pub async fn change_encryption_key(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let path = database_path(&app)?;
+    if !encryption::is_encrypted_file(&path) {
+        return Err("Database is not encrypted".to_string());
+    }
+    ensure_unlocked(&state)?;
+    set_database_key(&state, &app, passphrase).await?;
+    log::info!("Database encryption key changed");
+    Ok(())
+}
+
+/// Write an unencrypted copy of the database
+///
+/// The copy is written next to the target and only replaces it once
+/// complete, so a failed export leaves an existing file alone.
+#[tauri::command]
+pub fn export_decrypted_copy(state: State<AppState>, file_path: String) -> Result<(), String> {
+    let partial_path = PathBuf::from(format!("{}.exporting", file_path));
+    let _ = std::fs::remove_file(&partial_path);
+
+    let exported = writer(&state).and_then(|db| {
+        db.export_copy(&partial_path.to_string_lossy(), None)
+            .map_err(|e| e.to_string())
+    });
+    let renamed = exported
+        .and_then(|_| std::fs::rename(&partial_path, &file_path).map_err(|e| e.to_string()));
+    if renamed.is_err() {
+        let _ = std::fs::remove_file(&partial_path);
+    }
+    renamed
+}
+
+/// Open the database with the first keyring key that fits
+///
+/// Also promotes a staged key left behind by an interrupted re-key.
+fn unlock_with_keyring(state: &AppState, path: &Path) -> Result<(), String> {
+    let keys = encryption::load_keyring_keys()?;
+    for (index, key) in keys.iter().enumerate() {
+        if reopen_database(state, path, Some(key)).is_ok() {
+            if index > 0 {
+                encryption::remember_key(Some(key))?;
+            }
+            return Ok(());
+        }
+    }
+    Err("No key in the keyring opens the database".to_string())
+}
+
+/// Unlock an encrypted database and start tracking
+///
+/// Without a passphrase the keyring is read again, for when it was not
+/// available at launch.
+#[tauri::command]
+pub fn unlock_database(
+    state: State<AppState>,
+    app: tauri::AppHandle,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    if !state.locked.load(Ordering::SeqCst) {
+        return Err("Database is not locked".to_string());
+    }
+
+    let path = database_path(&app)?;
+    match passphrase {
+        Some(passphrase) => {
+            let key = DatabaseKey::Passphrase(passphrase);
+            reopen_database(&state, &path, Some(&key))
+                .map_err(|_| "Wrong passphrase".to_string())?;
+            // A generated key left in the keyring no longer fits
+            if let Err(e) = encryption::remember_key(Some(&key)) {
+                log::error!("Failed to clear database key: {}", e);
+            }
+        }
+        None => unlock_with_keyring(&state, &path)?,
+    }
+
+    // Settings and rules could not be read while locked
+    {
+        let db = state.db.read()?;
+        let settings = db.get_settings().map_err(|e| e.to_string())?;
+        state.tracker.update_config(TrackerConfig::from(&settings));
+        state.focus.update_config(settings.focus);
+        state.breaks.update_config(settings.breaks);
+        state.compaction.update_config(settings.compaction);
+        reload_tracker_rules(&state, &db)?;
+        let timer = db.get_log_timer().ok().flatten();
+        crate::tray::show_log_timer(&app, timer.as_ref(), &day_boundaries(&db)?);
+    }
+
+    start_services(&state, &app, true);
+    let _ = app.emit("database-unlocked", ());
+    log::info!("Database unlocked");
+    Ok(())
+}
+
 // ============================================================================
 // Export Commands
 // ============================================================================
@@ -415,33 +1401,39 @@ pub struct ExportResult {
     pub file_path: String,
 }
 
-/// Export data to CSV
+/// Export data for local dates `from_date` to `to_date` to CSV
 #[tauri::command]
 pub fn export_to_csv(
     state: State<AppState>,
-    from: i64,
-    to: i64,
+    from_date: String,
+    to_date: String,
     task_name: Option<String>,
     file_path: String,
 ) -> Result<ExportResult, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = state.db.read()?;
+    let days = day_boundaries(&db)?;
+    let (from, to) = local_date_range(&days, &from_date, &to_date)?;
 
-    // Get items
+    // Get items, cut to the range and split by day
     let items = if let Some(tn) = &task_name {
-        db.find_all_day_items(from, to, tn)
+        db.find_clipped_items(from, to, tn, &days)
             .map_err(|e| e.to_string())?
     } else {
         let mut all = Vec::new();
         all.extend(
-            db.find_all_day_items(from, to, "AppTrackItem")
+            db.find_clipped_items(from, to, "AppTrackItem", &days)
+                .map_err(|e| e.to_string())?,
+        );
+        all.extend(
+            db.find_clipped_items(from, to, "StatusTrackItem", &days)
                 .map_err(|e| e.to_string())?,
         );
         all.extend(
-            db.find_all_day_items(from, to, "StatusTrackItem")
+            db.find_clipped_items(from, to, "LogTrackItem", &days)
                 .map_err(|e| e.to_string())?,
         );
         all.extend(
-            db.find_all_day_items(from, to, "LogTrackItem")
+            db.find_clipped_items(from, to, "FocusTrackItem", &days)
                 .map_err(|e| e.to_string())?,
         );
         all.sort_by(|a, b| a.begin_date.cmp(&b.begin_date));
@@ -476,33 +1468,39 @@ pub fn export_to_csv(
     })
 }
 
-/// Export data to JSON
+/// Export data for local dates `from_date` to `to_date` to JSON
 #[tauri::command]
 pub fn export_to_json(
     state: State<AppState>,
-    from: i64,
-    to: i64,
+    from_date: String,
+    to_date: String,
     task_name: Option<String>,
     file_path: String,
 ) -> Result<ExportResult, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = state.db.read()?;
+    let days = day_boundaries(&db)?;
+    let (from, to) = local_date_range(&days, &from_date, &to_date)?;
 
-    // Get items
+    // Get items, cut to the range and split by day
     let items = if let Some(tn) = &task_name {
-        db.find_all_day_items(from, to, tn)
+        db.find_clipped_items(from, to, tn, &days)
             .map_err(|e| e.to_string())?
     } else {
         let mut all = Vec::new();
         all.extend(
-            db.find_all_day_items(from, to, "AppTrackItem")
+            db.find_clipped_items(from, to, "AppTrackItem", &days)
+                .map_err(|e| e.to_string())?,
+        );
+        all.extend(
+            db.find_clipped_items(from, to, "StatusTrackItem", &days)
                 .map_err(|e| e.to_string())?,
         );
         all.extend(
-            db.find_all_day_items(from, to, "StatusTrackItem")
+            db.find_clipped_items(from, to, "LogTrackItem", &days)
                 .map_err(|e| e.to_string())?,
         );
         all.extend(
-            db.find_all_day_items(from, to, "LogTrackItem")
+            db.find_clipped_items(from, to, "FocusTrackItem", &days)
                 .map_err(|e| e.to_string())?,
         );
         all.sort_by(|a, b| a.begin_date.cmp(&b.begin_date));
@@ -517,7 +1515,9 @@ pub fn export_to_json(
         "exportDate": chrono::Utc::now().to_rfc3339(),
         "dateRange": {
             "from": from,
-            "to": to
+            "to": to,
+            "fromDate": from_date,
+            "toDate": to_date
         },
         "totalItems": items.len(),
         "totalDuration": total_duration,
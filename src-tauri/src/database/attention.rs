@@ -0,0 +1,429 @@
+// Attention Metrics Module
+// 专注度与切换统计模块
+
+use super::days::DayBoundaries;
+use super::{Database, Productivity, TrackItem, UsageKey};
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Attention metric configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AttentionConfig {
+    /// Windows focused for less than this are folded into the surrounding
+    /// stretch (seconds)
+    pub flicker_seconds: u64,
+    /// Shortest productive stretch that counts as deep work (minutes)
+    pub deep_work_minutes: u64,
+}
+
+impl Default for AttentionConfig {
+    fn default() -> Self {
+        Self {
+            flicker_seconds: 10,
+            deep_work_minutes: 25,
+        }
+    }
+}
+
+/// Uninterrupted stretches of one app or project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StretchStats {
+    pub key: UsageKey,
+    pub count: usize,
+    pub median: i64,
+    pub longest: i64,
+}
+
+/// A stretch of productive apps only
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepWorkBlock {
+    pub begin_date: i64,
+    pub end_date: i64,
+}
+
+/// Attention metrics of one local date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayAttention {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    /// App time (milliseconds)
+    pub active_duration: i64,
+    /// Changes from one app to another
+    pub switches: usize,
+    pub switches_per_hour: f64,
+    /// 0 when the day is one stretch, approaching 1 as it breaks into many
+    /// short ones
+    pub fragmentation: f64,
+    /// By longest stretch, longest first
+    pub apps: Vec<StretchStats>,
+    pub projects: Vec<StretchStats>,
+    pub deep_work: Vec<DeepWorkBlock>,
+    pub deep_work_duration: i64,
+}
+
+/// One app item of a day
+struct Focused {
+    app: String,
+    project_id: Option<i64>,
+    productive: bool,
+    begin: i64,
+    end: i64,
+}
+
+/// Consecutive items with the same key, flickers folded in
+#[derive(Debug, Clone, PartialEq)]
+struct Stretch<K> {
+    key: K,
+    productive: bool,
+    begin: i64,
+    end: i64,
+    /// Began with a flicker; takes the key of the item after it
+    provisional: bool,
+}
+
+/// Join items into stretches of the same key
+///
+/// Items shorter than `flicker` extend the stretch before them, and so do
+/// returns to its key. Gaps longer than `flicker` end a stretch.
+fn stretches<K: Clone + PartialEq>(
+    items: impl IntoIterator<Item = (K, bool, i64, i64)>,
+    flicker: i64,
+) -> Vec<Stretch<K>> {
+    let mut stretches: Vec<Stretch<K>> = Vec::new();
+    for (key, productive, begin, end) in items {
+        let short = end - begin < flicker;
+        if let Some(last) = stretches.last_mut() {
+            if begin - last.end <= flicker {
+                if short || last.key == key {
+                    last.end = last.end.max(end);
+                    continue;
+                }
+                if last.provisional {
+                    *last = Stretch {
+                        key,
+                        productive,
+                        begin: last.begin,
+                        end,
+                        provisional: false,
+                    };
+                    continue;
+                }
+            }
+        }
+        stretches.push(Stretch {
+            key,
+            productive,
+            begin,
+            end,
+            provisional: short,
+        });
+    }
+    stretches
+}
+
+/// Stretch counts, medians and maxima per key, longest first
+fn stretch_stats<K: Clone + Ord>(
+    stretches: &[Stretch<Option<K>>],
+    usage_key: impl Fn(K) -> UsageKey,
+) -> Vec<StretchStats> {
+    let mut durations: BTreeMap<K, Vec<i64>> = BTreeMap::new();
+    for stretch in stretches {
+        if let Some(key) = &stretch.key {
+            durations
+                .entry(key.clone())
+                .or_default()
+                .push(stretch.end - stretch.begin);
+        }
+    }
+
+    let mut stats: Vec<StretchStats> = durations
+        .into_iter()
+        .map(|(key, mut durations)| {
+            durations.sort_unstable();
+            let mid = durations.len() / 2;
+            let median = if durations.len().is_multiple_of(2) {
+                (durations[mid - 1] + durations[mid]) / 2
+            } else {
+                durations[mid]
+            };
+            StretchStats {
+                key: usage_key(key),
+                count: durations.len(),
+                median,
+                longest: durations[durations.len() - 1],
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.longest.cmp(&a.longest));
+    stats
+}
+
+/// Metrics of one day's items, in order
+fn day_attention(date: NaiveDate, items: &[Focused], config: &AttentionConfig) -> DayAttention {
+    let flicker = config.flicker_seconds as i64 * 1000;
+    let deep_work_min = config.deep_work_minutes.max(1) as i64 * 60_000;
+
+    let apps = stretches(
+        items
+            .iter()
+            .map(|i| (Some(i.app.clone()), i.productive, i.begin, i.end)),
+        flicker,
+    );
+    let projects = stretches(
+        items
+            .iter()
+            .map(|i| (i.project_id, i.productive, i.begin, i.end)),
+        flicker,
+    );
+
+    let active_duration: i64 = items.iter().map(|i| i.end - i.begin).sum();
+    let switches = apps.windows(2).filter(|w| w[0].key != w[1].key).count();
+    let switches_per_hour = if active_duration > 0 {
+        switches as f64 * 3_600_000.0 / active_duration as f64
+    } else {
+        0.0
+    };
+    let total: f64 = apps.iter().map(|s| (s.end - s.begin) as f64).sum();
+    let fragmentation = if total > 0.0 {
+        1.0 - apps
+            .iter()
+            .map(|s| ((s.end - s.begin) as f64 / total).powi(2))
+            .sum::<f64>()
+    } else {
+        0.0
+    };
+
+    // Productive stretches that follow each other without a gap
+    let mut deep_work = Vec::new();
+    let mut block: Option<DeepWorkBlock> = None;
+    for stretch in &apps {
+        let continues = block
+            .as_ref()
+            .is_some_and(|b| stretch.productive && stretch.begin - b.end_date <= flicker);
+        if continues {
+            if let Some(b) = block.as_mut() {
+                b.end_date = stretch.end;
+            }
+            continue;
+        }
+        deep_work.extend(
+            block
+                .take()
+                .filter(|b| b.end_date - b.begin_date >= deep_work_min),
+        );
+        if stretch.productive {
+            block = Some(DeepWorkBlock {
+                begin_date: stretch.begin,
+                end_date: stretch.end,
+            });
+        }
+    }
+    deep_work.extend(block.filter(|b| b.end_date - b.begin_date >= deep_work_min));
+
+    DayAttention {
+        date: date.format("%Y-%m-%d").to_string(),
+        active_duration,
+        switches,
+        switches_per_hour,
+        fragmentation,
+        apps: stretch_stats(&apps, UsageKey::App),
+        projects: stretch_stats(&projects, UsageKey::Project),
+        deep_work_duration: deep_work.iter().map(|b| b.end_date - b.begin_date).sum(),
+        deep_work,
+    }
+}
+
+impl Database {
+    /// Attention metrics for each date from `first` to `last`, inclusive
+    ///
+    /// Built from the sequence of app items; an item linked to several
+    /// projects counts toward the first one.
+    pub fn get_attention_stats(
+        &self,
+        first: NaiveDate,
+        last: NaiveDate,
+        bounds: &DayBoundaries,
+        config: &AttentionConfig,
+    ) -> Result<Vec<DayAttention>> {
+        let (from, to) = bounds.date_range(first, last);
+        let mut stmt = self.conn.prepare(
+            "SELECT t.app, t.begin_date, t.end_date, t.productivity, MIN(l.project_id)
+             FROM track_items t
+             LEFT JOIN track_item_projects l ON l.track_item_id = t.id
+             WHERE t.task_name = 'AppTrackItem' AND t.end_date > ?1 AND t.begin_date < ?2
+             GROUP BY t.id
+             ORDER BY t.begin_date ASC",
+        )?;
+
+        let mut days: BTreeMap<NaiveDate, Vec<Focused>> = BTreeMap::new();
+        let mut rows = stmt.query(params![from, to])?;
+        while let Some(row) = rows.next()? {
+            let item = TrackItem {
+                id: None,
+                app: row.get(0)?,
+                task_name: "AppTrackItem".to_string(),
+                title: String::new(),
+                url: None,
+                domain: None,
+                color: None,
+                begin_date: row.get(1)?,
+                end_date: row.get(2)?,
+                productivity: row.get(3)?,
+            };
+            let project_id: Option<i64> = row.get(4)?;
+
+            for piece in bounds.split_item(&item, from, to) {
+                days.entry(bounds.date_of(piece.begin_date))
+                    .or_default()
+                    .push(Focused {
+                        app: piece.app,
+                        project_id,
+                        productive: piece.productivity == Some(Productivity::Productive),
+                        begin: piece.begin_date,
+                        end: piece.end_date,
+                    });
+            }
+        }
+
+        let mut result = Vec::new();
+        let mut date = first;
+        while date <= last {
+            let items = days.remove(&date).unwrap_or_default();
+            result.push(day_attention(date, &items, config));
+            date += Duration::days(1);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::days::parse_date;
+    use crate::database::TestItem;
+
+    const SECOND: i64 = 1000;
+    const MINUTE: i64 = 60 * SECOND;
+
+    fn focused(app: &str, productive: bool, begin: i64, end: i64) -> Focused {
+        Focused {
+            app: app.to_string(),
+            project_id: None,
+            productive,
+            begin,
+            end,
+        }
+    }
+
+    fn date() -> NaiveDate {
+        parse_date("2024-03-04").unwrap()
+    }
+
+    #[test]
+    fn test_flickers_fold_into_the_stretch() {
+        let items = [
+            ("Code", 0, 10 * MINUTE),
+            ("Slack", 10 * MINUTE, 10 * MINUTE + 3 * SECOND),
+            ("Code", 10 * MINUTE + 3 * SECOND, 20 * MINUTE),
+            ("Firefox", 20 * MINUTE, 25 * MINUTE),
+        ];
+        let spans = stretches(items.map(|(app, b, e)| (app, true, b, e)), 10 * SECOND);
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[0].key, spans[0].end), ("Code", 20 * MINUTE));
+        assert_eq!((spans[1].key, spans[1].begin), ("Firefox", 20 * MINUTE));
+    }
+
+    #[test]
+    fn test_leading_flicker_takes_the_next_key() {
+        let items = [("Slack", 0, 2 * SECOND), ("Code", 2 * SECOND, MINUTE)];
+        let spans = stretches(items.map(|(app, b, e)| (app, true, b, e)), 10 * SECOND);
+
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].key, spans[0].begin), ("Code", 0));
+    }
+
+    #[test]
+    fn test_switches_and_stretches() {
+        let items = [
+            focused("Code", true, 0, 30 * MINUTE),
+            focused("Slack", false, 30 * MINUTE, 50 * MINUTE),
+            focused("Code", true, 50 * MINUTE, 60 * MINUTE),
+            focused("Code", true, 70 * MINUTE, 90 * MINUTE),
+        ];
+        let day = day_attention(date(), &items, &AttentionConfig::default());
+
+        assert_eq!(day.active_duration, 80 * MINUTE);
+        // The idle gap ends a stretch but is no switch
+        assert_eq!(day.switches, 2);
+        assert_eq!(day.switches_per_hour, 1.5);
+        assert_eq!(
+            day.apps[0],
+            StretchStats {
+                key: UsageKey::App("Code".to_string()),
+                count: 3,
+                median: 20 * MINUTE,
+                longest: 30 * MINUTE,
+            }
+        );
+        assert!(day.projects.is_empty());
+        assert!(day.fragmentation > 0.0 && day.fragmentation < 1.0);
+    }
+
+    #[test]
+    fn test_deep_work_blocks() {
+        let items = [
+            focused("Code", true, 0, 20 * MINUTE),
+            focused("Terminal", true, 20 * MINUTE, 30 * MINUTE),
+            // A flicker does not break the block
+            focused("Slack", false, 30 * MINUTE, 30 * MINUTE + 5 * SECOND),
+            focused("Code", true, 30 * MINUTE + 5 * SECOND, 40 * MINUTE),
+            focused("Slack", false, 40 * MINUTE, 45 * MINUTE),
+            focused("Code", true, 45 * MINUTE, 60 * MINUTE),
+        ];
+        let day = day_attention(date(), &items, &AttentionConfig::default());
+
+        assert_eq!(
+            day.deep_work,
+            vec![DeepWorkBlock {
+                begin_date: 0,
+                end_date: 40 * MINUTE,
+            }]
+        );
+        assert_eq!(day.deep_work_duration, 40 * MINUTE);
+    }
+
+    #[test]
+    fn test_attention_stats_per_day() {
+        let db = Database::new(":memory:").unwrap();
+        let days = DayBoundaries::new(Some("UTC"), 0).unwrap();
+        let (start, _) = days.date_range(date(), date());
+        let project = db.create_project("Website", None).unwrap();
+
+        // Crosses midnight into the next day
+        let item = TestItem::new(start + 23 * 60 * MINUTE, start + 25 * 60 * MINUTE)
+            .productivity(Productivity::Productive)
+            .create(&db);
+        db.assign_project(project.id, &[item]).unwrap();
+
+        let stats = db
+            .get_attention_stats(
+                date(),
+                date().succ_opt().unwrap(),
+                &days,
+                &AttentionConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].active_duration, 60 * MINUTE);
+        assert_eq!(stats[1].date, "2024-03-05");
+        assert_eq!(stats[1].deep_work_duration, 60 * MINUTE);
+        assert_eq!(stats[1].projects[0].key, UsageKey::Project(project.id));
+    }
+}
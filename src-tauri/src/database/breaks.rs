@@ -0,0 +1,171 @@
+// Break Reminders Module
+// 休息提醒记录模块
+
+use super::Database;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Result, ToSql};
+use serde::{Deserialize, Serialize};
+
+/// What happened after a break reminder was shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BreakOutcome {
+    /// The user stepped away long enough to count as a break
+    Taken,
+    /// Asked to be reminded again later
+    Snoozed,
+    /// Closed the reminder and kept working
+    Dismissed,
+}
+
+impl BreakOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakOutcome::Taken => "taken",
+            BreakOutcome::Snoozed => "snoozed",
+            BreakOutcome::Dismissed => "dismissed",
+        }
+    }
+}
+
+impl ToSql for BreakOutcome {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for BreakOutcome {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "taken" => Ok(BreakOutcome::Taken),
+            "snoozed" => Ok(BreakOutcome::Snoozed),
+            "dismissed" => Ok(BreakOutcome::Dismissed),
+            other => Err(FromSqlError::Other(
+                format!("unknown break outcome: {}", other).into(),
+            )),
+        }
+    }
+}
+
+/// How break reminders in a time range went
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakStats {
+    pub shown: i64,
+    pub taken: i64,
+    pub snoozed: i64,
+    pub dismissed: i64,
+    /// Average active time before a reminder (milliseconds)
+    pub average_active_duration: i64,
+}
+
+impl Database {
+    /// Log a reminder shown after `active_duration` of continuous activity
+    pub fn log_break_reminder(&self, shown_at: i64, active_duration: i64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO break_reminders (shown_at, active_duration) VALUES (?1, ?2)",
+            params![shown_at, active_duration],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Record what happened to a reminder
+    ///
+    /// Only the first outcome counts, except that a break taken during a
+    /// snooze replaces `snoozed`.
+    pub fn resolve_break_reminder(
+        &self,
+        id: i64,
+        outcome: BreakOutcome,
+        resolved_at: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE break_reminders SET outcome = ?1, resolved_at = ?2
+             WHERE id = ?3
+               AND (outcome IS NULL OR (outcome = ?4 AND ?1 = ?5))",
+            params![
+                outcome,
+                resolved_at,
+                id,
+                BreakOutcome::Snoozed,
+                BreakOutcome::Taken
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Count reminders shown in `[from, to)` by outcome
+    pub fn get_break_stats(&self, from: i64, to: i64) -> Result<BreakStats> {
+        self.conn.query_row(
+            "SELECT COUNT(*),
+                    COUNT(*) FILTER (WHERE outcome = ?3),
+                    COUNT(*) FILTER (WHERE outcome = ?4),
+                    COUNT(*) FILTER (WHERE outcome = ?5),
+                    COALESCE(AVG(active_duration), 0)
+             FROM break_reminders WHERE shown_at >= ?1 AND shown_at < ?2",
+            params![
+                from,
+                to,
+                BreakOutcome::Taken,
+                BreakOutcome::Snoozed,
+                BreakOutcome::Dismissed
+            ],
+            |row| {
+                Ok(BreakStats {
+                    shown: row.get(0)?,
+                    taken: row.get(1)?,
+                    snoozed: row.get(2)?,
+                    dismissed: row.get(3)?,
+                    average_active_duration: row.get::<_, f64>(4)? as i64,
+                })
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_stats() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.log_break_reminder(1_000, 50 * 60_000).unwrap();
+        let second = db.log_break_reminder(2_000, 70 * 60_000).unwrap();
+        db.log_break_reminder(3_000, 60 * 60_000).unwrap();
+
+        db.resolve_break_reminder(first, BreakOutcome::Snoozed, 1_500)
+            .unwrap();
+        db.resolve_break_reminder(second, BreakOutcome::Dismissed, 2_500)
+            .unwrap();
+        // Later outcomes do not overwrite the first one
+        db.resolve_break_reminder(second, BreakOutcome::Taken, 2_800)
+            .unwrap();
+
+        let stats = db.get_break_stats(0, 10_000).unwrap();
+        assert_eq!(
+            stats,
+            BreakStats {
+                shown: 3,
+                taken: 0,
+                snoozed: 1,
+                dismissed: 1,
+                average_active_duration: 60 * 60_000,
+            }
+        );
+        assert_eq!(db.get_break_stats(0, 1_000).unwrap().shown, 0);
+    }
+
+    #[test]
+    fn test_break_during_snooze_is_taken() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.log_break_reminder(1_000, 50 * 60_000).unwrap();
+        db.resolve_break_reminder(id, BreakOutcome::Snoozed, 1_500)
+            .unwrap();
+        db.resolve_break_reminder(id, BreakOutcome::Taken, 1_800)
+            .unwrap();
+
+        let stats = db.get_break_stats(0, 10_000).unwrap();
+        assert_eq!((stats.taken, stats.snoozed), (1, 0));
+    }
+}
@@ -0,0 +1,476 @@
+// Timeline Compaction Module
+// 时间线压缩模块
+
+use super::{Database, DayBoundaries, Productivity};
+use rusqlite::{params, OptionalExtension, Result, Transaction};
+use serde::{Deserialize, Serialize};
+
+/// Timeline compaction configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompactionConfig {
+    /// Compact finished days in the background
+    pub nightly: bool,
+    /// Merge neighbors of the same app even when their titles differ
+    pub ignore_titles: bool,
+    /// Items shorter than this are absorbed into a neighbor (seconds);
+    /// 0 keeps them
+    pub blip_seconds: u64,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            nightly: false,
+            ignore_titles: false,
+            blip_seconds: 5,
+        }
+    }
+}
+
+/// What compacting a range did, or would do
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionResult {
+    /// App items in the range before compacting
+    pub items_before: i64,
+    /// Items merged into a neighbor and removed
+    pub items_removed: i64,
+    /// Of those, short items of another kind absorbed by a neighbor
+    pub blips_absorbed: i64,
+    /// Whether changes were written
+    pub applied: bool,
+}
+
+/// What must match for two items to merge
+#[derive(Debug, Clone, PartialEq)]
+struct RowKey {
+    app: String,
+    domain: Option<String>,
+    productivity: Option<Productivity>,
+    project_ids: Option<String>,
+    tag_ids: Option<String>,
+    /// Title and URL, unless titles are ignored
+    title: Option<(String, Option<String>)>,
+}
+
+/// A closed app item considered for compaction
+#[derive(Debug, Clone)]
+struct Row {
+    id: i64,
+    key: RowKey,
+    begin: i64,
+    end: i64,
+}
+
+/// Touching rows that become one item
+#[derive(Debug, PartialEq)]
+struct Run {
+    /// Index of the row kept with the run's extent
+    keep: usize,
+    members: Vec<usize>,
+    begin: i64,
+    end: i64,
+    /// Only blips so far; the next row's kind takes over
+    provisional: bool,
+    blips: usize,
+}
+
+/// Group rows (ordered by begin) into runs
+///
+/// A row joins the run before it when it starts exactly where the run ends
+/// and is of the same kind, or is shorter than `blip`. Only touching rows
+/// merge, so the total time stays the same. The longest row of a run's
+/// kind is the one kept.
+fn plan_runs(rows: &[Row], blip: i64) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let is_blip = row.end - row.begin < blip;
+        if let Some(run) = runs.last_mut().filter(|run| run.end == row.begin) {
+            let kept = &rows[run.keep];
+            if run.provisional && !is_blip {
+                run.keep = index;
+                run.provisional = false;
+                run.blips = run.members.len();
+            } else if kept.key == row.key {
+                if row.end - row.begin > kept.end - kept.begin {
+                    run.keep = index;
+                }
+            } else if is_blip {
+                run.blips += 1;
+            } else {
+                runs.push(Run {
+                    keep: index,
+                    members: vec![index],
+                    begin: row.begin,
+                    end: row.end,
+                    provisional: false,
+                    blips: 0,
+                });
+                continue;
+            }
+            run.members.push(index);
+            run.end = row.end;
+            continue;
+        }
+
+        runs.push(Run {
+            keep: index,
+            members: vec![index],
+            begin: row.begin,
+            end: row.end,
+            provisional: is_blip,
+            blips: 0,
+        });
+    }
+    runs
+}
+
+/// Items of a compaction within `[from, to)`
+const WITHIN_RANGE: &str = "t.begin_date >= ?1 AND t.end_date <= ?2";
+/// Items of a nightly compaction step: everything that ended after the
+/// last step, including items that started before it
+const ENDED_IN_RANGE: &str = "t.end_date > ?1 AND t.end_date <= ?2";
+
+impl Database {
+    /// Merge touching app items of the same kind that lie within
+    /// `[from, to)`, absorbing blips into their neighbors
+    ///
+    /// Items still open are left alone. With `dry_run` nothing is written.
+    pub fn compact_track_items(
+        &self,
+        from: i64,
+        to: i64,
+        config: &CompactionConfig,
+        dry_run: bool,
+    ) -> Result<CompactionResult> {
+        let tx = self.conn.unchecked_transaction()?;
+        let result = compact_rows(&tx, WITHIN_RANGE, from, to, config, dry_run)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Compact the next day before `until` that has items not compacted
+    /// yet, then remember where that day ends
+    ///
+    /// Each call is one short transaction, so a long history is caught up
+    /// on without blocking other writers for long. Days without items are
+    /// skipped. Items are only merged with items of the same step, so two
+    /// items touching across a day already compacted stay apart. Returns
+    /// `None` once everything before `until` is compacted.
+    pub fn compact_next_day(
+        &self,
+        until: i64,
+        bounds: &DayBoundaries,
+        config: &CompactionConfig,
+    ) -> Result<Option<CompactionResult>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let since: Option<i64> = tx
+            .query_row(
+                "SELECT json_data FROM settings WHERE name = 'compacted_until'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .and_then(|since| since.parse().ok());
+        let since = since.unwrap_or(i64::MIN);
+        if since >= until {
+            return Ok(None);
+        }
+
+        let next_end: Option<i64> = tx.query_row(
+            "SELECT MIN(end_date) FROM track_items
+             WHERE task_name = 'AppTrackItem' AND is_open = 0 AND end_date > ?1",
+            params![since],
+            |row| row.get(0),
+        )?;
+        let (step_until, result) = match next_end.filter(|&end| end <= until) {
+            Some(end) => {
+                // The day the item ends in; one ending at midnight belongs
+                // to the day before
+                let step_until = bounds.next_day(bounds.day_start(end - 1)).min(until);
+                let result = compact_rows(&tx, ENDED_IN_RANGE, since, step_until, config, false)?;
+                (step_until, Some(result))
+            }
+            None => (until, None),
+        };
+
+        tx.execute(
+            "INSERT INTO settings (name, json_data) VALUES ('compacted_until', ?1)
+             ON CONFLICT(name) DO UPDATE SET json_data = ?1",
+            params![step_until.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(result)
+    }
+}
+
+/// Compact the closed app items matching `range` (bound to `from` and `to`)
+fn compact_rows(
+    tx: &Transaction,
+    range: &str,
+    from: i64,
+    to: i64,
+    config: &CompactionConfig,
+    dry_run: bool,
+) -> Result<CompactionResult> {
+    let rows = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT t.id, t.app, t.domain, t.productivity, t.title, t.url, t.begin_date, t.end_date,
+                    (SELECT GROUP_CONCAT(project_id) FROM
+                        (SELECT project_id FROM track_item_projects
+                         WHERE track_item_id = t.id ORDER BY project_id)),
+                    (SELECT GROUP_CONCAT(tag_id) FROM
+                        (SELECT tag_id FROM track_item_tags
+                         WHERE track_item_id = t.id ORDER BY tag_id))
+             FROM track_items t
+             WHERE t.task_name = 'AppTrackItem' AND t.is_open = 0 AND {}
+             ORDER BY t.begin_date ASC, t.id ASC",
+            range
+        ))?;
+        let rows = stmt.query_map(params![from, to], |row| {
+            Ok(Row {
+                id: row.get(0)?,
+                key: RowKey {
+                    app: row.get(1)?,
+                    domain: row.get(2)?,
+                    productivity: row.get(3)?,
+                    project_ids: row.get(8)?,
+                    tag_ids: row.get(9)?,
+                    title: if config.ignore_titles {
+                        None
+                    } else {
+                        Some((row.get(4)?, row.get(5)?))
+                    },
+                },
+                begin: row.get(6)?,
+                end: row.get(7)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>>>()?
+    };
+
+    let runs = plan_runs(&rows, config.blip_seconds as i64 * 1000);
+    let mut result = CompactionResult {
+        items_before: rows.len() as i64,
+        applied: !dry_run,
+        ..Default::default()
+    };
+
+    for run in runs.iter().filter(|run| run.members.len() > 1) {
+        result.items_removed += run.members.len() as i64 - 1;
+        result.blips_absorbed += run.blips as i64;
+        if dry_run {
+            continue;
+        }
+
+        tx.execute(
+            "UPDATE track_items SET begin_date = ?1, end_date = ?2 WHERE id = ?3",
+            params![run.begin, run.end, rows[run.keep].id],
+        )?;
+        for &member in run.members.iter().filter(|&&m| m != run.keep) {
+            tx.execute(
+                "DELETE FROM track_items WHERE id = ?1",
+                params![rows[member].id],
+            )?;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    const SECOND: i64 = 1000;
+    const HOUR: i64 = 3_600_000;
+
+    fn total(db: &Database) -> i64 {
+        db.find_all_day_items(0, i64::MAX, "AppTrackItem")
+            .unwrap()
+            .iter()
+            .map(|i| i.end_date - i.begin_date)
+            .sum()
+    }
+
+    #[test]
+    fn test_merges_touching_items_of_the_same_kind() {
+        let db = Database::new(":memory:").unwrap();
+        let first = TestItem::new(0, 30 * SECOND).create(&db);
+        TestItem::new(30 * SECOND, 40 * SECOND).create(&db);
+        TestItem::new(40 * SECOND, 50 * SECOND)
+            .title("lib.rs")
+            .create(&db);
+        // Not touching
+        TestItem::new(60 * SECOND, 70 * SECOND)
+            .title("lib.rs")
+            .create(&db);
+
+        let config = CompactionConfig::default();
+        let preview = db.compact_track_items(0, i64::MAX, &config, true).unwrap();
+        assert_eq!(preview.items_removed, 1);
+        assert!(!preview.applied);
+        assert_eq!(
+            db.find_all_day_items(0, i64::MAX, "AppTrackItem")
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let result = db.compact_track_items(0, i64::MAX, &config, false).unwrap();
+        assert_eq!(
+            result,
+            CompactionResult {
+                applied: true,
+                ..preview
+            }
+        );
+
+        let items = db.find_all_day_items(0, i64::MAX, "AppTrackItem").unwrap();
+        assert_eq!(items.len(), 3);
+        let merged = items.iter().find(|i| i.id == Some(first)).unwrap();
+        assert_eq!((merged.begin_date, merged.end_date), (0, 40 * SECOND));
+        assert_eq!(total(&db), 60 * SECOND);
+    }
+
+    #[test]
+    fn test_ignore_titles_keeps_the_longest_title() {
+        let db = Database::new(":memory:").unwrap();
+        TestItem::new(0, 10 * SECOND).create(&db);
+        TestItem::new(10 * SECOND, 40 * SECOND)
+            .title("lib.rs")
+            .create(&db);
+
+        let config = CompactionConfig {
+            ignore_titles: true,
+            ..Default::default()
+        };
+        db.compact_track_items(0, i64::MAX, &config, false).unwrap();
+
+        let items = db.find_all_day_items(0, i64::MAX, "AppTrackItem").unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "lib.rs");
+        assert_eq!((items[0].begin_date, items[0].end_date), (0, 40 * SECOND));
+    }
+
+    #[test]
+    fn test_blips_are_absorbed_into_neighbors() {
+        let db = Database::new(":memory:").unwrap();
+        TestItem::new(0, 30 * SECOND).create(&db);
+        TestItem::new(30 * SECOND, 33 * SECOND)
+            .app("Slack")
+            .title("general")
+            .create(&db);
+        TestItem::new(33 * SECOND, 60 * SECOND).create(&db);
+        // A leading blip joins the item after it
+        TestItem::new(100 * SECOND, 102 * SECOND)
+            .app("Slack")
+            .title("general")
+            .create(&db);
+        TestItem::new(102 * SECOND, 130 * SECOND)
+            .app("Firefox")
+            .title("Docs")
+            .create(&db);
+
+        let result = db
+            .compact_track_items(0, i64::MAX, &CompactionConfig::default(), false)
+            .unwrap();
+        assert_eq!(result.items_removed, 3);
+        assert_eq!(result.blips_absorbed, 2);
+
+        let items = db.find_all_day_items(0, i64::MAX, "AppTrackItem").unwrap();
+        let spans: Vec<_> = items
+            .iter()
+            .map(|i| (i.app.as_str(), i.begin_date, i.end_date))
+            .collect();
+        assert!(spans.contains(&("Code", 0, 60 * SECOND)));
+        assert!(spans.contains(&("Firefox", 100 * SECOND, 130 * SECOND)));
+        assert_eq!(total(&db), 90 * SECOND);
+    }
+
+    #[test]
+    fn test_items_in_different_projects_stay_apart() {
+        let db = Database::new(":memory:").unwrap();
+        let project = db.create_project("Website", None).unwrap();
+        let first = TestItem::new(0, 30 * SECOND).create(&db);
+        TestItem::new(30 * SECOND, 60 * SECOND).create(&db);
+        db.assign_project(project.id, &[first]).unwrap();
+
+        let result = db
+            .compact_track_items(0, i64::MAX, &CompactionConfig::default(), true)
+            .unwrap();
+        assert_eq!(result.items_removed, 0);
+    }
+
+    /// Local midnight a few days after the epoch
+    fn midnight(days: i64) -> i64 {
+        let bounds = DayBoundaries::default();
+        let mut day = bounds.day_start(10 * 24 * HOUR);
+        for _ in 0..days {
+            day = bounds.next_day(day);
+        }
+        day
+    }
+
+    /// Run nightly steps until caught up with `until`
+    fn catch_up(db: &Database, until: i64) -> Vec<CompactionResult> {
+        let config = CompactionConfig::default();
+        std::iter::from_fn(|| {
+            db.compact_next_day(until, &DayBoundaries::default(), &config)
+                .unwrap()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_finished_days_are_compacted_one_per_step() {
+        let db = Database::new(":memory:").unwrap();
+        let (first, sixth) = (midnight(0), midnight(5));
+        TestItem::new(first + HOUR, first + 2 * HOUR).create(&db);
+        TestItem::new(first + 2 * HOUR, first + 3 * HOUR).create(&db);
+        TestItem::new(sixth + HOUR, sixth + 2 * HOUR).create(&db);
+        TestItem::new(sixth + 2 * HOUR, sixth + 3 * HOUR).create(&db);
+        // Not finished yet
+        let eighth = midnight(7);
+        TestItem::new(eighth + HOUR, eighth + 2 * HOUR).create(&db);
+
+        // Empty days in between are skipped
+        let steps = catch_up(&db, midnight(6));
+        assert_eq!(steps.len(), 2);
+        assert!(steps.iter().all(|step| step.items_removed == 1));
+        assert!(catch_up(&db, midnight(6)).is_empty());
+
+        // Later runs only look at what came after
+        let steps = catch_up(&db, midnight(8));
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].items_before, 1);
+    }
+
+    #[test]
+    fn test_items_are_compacted_with_the_day_they_end_in() {
+        let db = Database::new(":memory:").unwrap();
+        let (second, fourth) = (midnight(1), midnight(3));
+        // Crosses midnight and ends on the second day, with its neighbor
+        TestItem::new(second - HOUR, second + HOUR).create(&db);
+        TestItem::new(second + HOUR, second + 2 * HOUR).create(&db);
+        // Touching across the third night: each ends on its own day
+        TestItem::new(fourth - HOUR, fourth).create(&db);
+        TestItem::new(fourth, fourth + HOUR).create(&db);
+
+        let steps = catch_up(&db, midnight(5));
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].items_removed, 1);
+
+        let spans: Vec<_> = db
+            .find_all_day_items(0, i64::MAX, "AppTrackItem")
+            .unwrap()
+            .iter()
+            .map(|i| (i.begin_date, i.end_date))
+            .collect();
+        assert!(spans.contains(&(second - HOUR, second + 2 * HOUR)));
+        assert!(spans.contains(&(fourth - HOUR, fourth)));
+        assert!(spans.contains(&(fourth, fourth + HOUR)));
+    }
+}
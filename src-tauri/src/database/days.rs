@@ -0,0 +1,248 @@
+// Day Boundaries Module
+// 日界划分模块
+
+use super::TrackItem;
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+/// Time zone days are counted in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zone {
+    /// The system's zone, as it is right now
+    System,
+    Named(Tz),
+}
+
+/// Where the user's days begin and end
+///
+/// A day runs from `start_hour` on its date to `start_hour` on the next
+/// date, in the configured time zone. DST changes make some days 23 or 25
+/// hours long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayBoundaries {
+    zone: Zone,
+    start_hour: u32,
+}
+
+impl Default for DayBoundaries {
+    fn default() -> Self {
+        Self {
+            zone: Zone::System,
+            start_hour: 0,
+        }
+    }
+}
+
+/// Earliest instant of a local time, moving past DST gaps
+fn earliest<Z: TimeZone>(zone: &Z, time: NaiveDateTime) -> i64 {
+    (0..3)
+        .find_map(|hours| {
+            zone.from_local_datetime(&(time + Duration::hours(hours)))
+                .earliest()
+        })
+        .map(|t| t.timestamp_millis())
+        .unwrap_or_else(|| time.and_utc().timestamp_millis())
+}
+
+/// Local date-time of a timestamp
+fn local_time<Z: TimeZone>(zone: &Z, timestamp: i64) -> NaiveDateTime {
+    match zone.timestamp_millis_opt(timestamp).single() {
+        Some(time) => time.naive_local(),
+        None => chrono::DateTime::from_timestamp_millis(timestamp)
+            .unwrap_or_default()
+            .naive_utc(),
+    }
+}
+
+impl DayBoundaries {
+    /// Boundaries for an IANA time zone name (`None` for the system zone)
+    /// and the hour days start at
+    pub fn new(timezone: Option<&str>, start_hour: u32) -> Result<Self, String> {
+        let zone = match timezone.filter(|tz| !tz.is_empty()) {
+            Some(name) => Zone::Named(
+                name.parse::<Tz>()
+                    .map_err(|_| format!("Unknown time zone: {}", name))?,
+            ),
+            None => Zone::System,
+        };
+        if start_hour > 23 {
+            return Err(format!("Invalid start of day hour: {}", start_hour));
+        }
+
+        Ok(Self { zone, start_hour })
+    }
+
+    /// Identifies the boundaries; changes when any day would move
+    pub fn label(&self) -> String {
+        let zone = match self.zone {
+            Zone::Named(tz) => tz.name().to_string(),
+            Zone::System => format!(
+                "system:{}",
+                iana_time_zone::get_timezone().unwrap_or_default()
+            ),
+        };
+        format!("{}@{}", zone, self.start_hour)
+    }
+
+    /// Start of a date's day (milliseconds)
+    pub fn date_start(&self, date: NaiveDate) -> i64 {
+        let time = date.and_time(NaiveTime::MIN) + Duration::hours(self.start_hour as i64);
+        match self.zone {
+            Zone::Named(tz) => earliest(&tz, time),
+            Zone::System => earliest(&Local, time),
+        }
+    }
+
+    /// Wall-clock time of a timestamp in the configured zone
+    pub fn local_time(&self, timestamp: i64) -> NaiveDateTime {
+        match self.zone {
+            Zone::Named(tz) => local_time(&tz, timestamp),
+            Zone::System => local_time(&Local, timestamp),
+        }
+    }
+
+    /// The date of the day a timestamp falls in
+    pub fn date_of(&self, timestamp: i64) -> NaiveDate {
+        (self.local_time(timestamp) - Duration::hours(self.start_hour as i64)).date()
+    }
+
+    /// `[from, to)` covering the days from `first` to `last`, inclusive
+    pub fn date_range(&self, first: NaiveDate, last: NaiveDate) -> (i64, i64) {
+        (
+            self.date_start(first),
+            self.date_start(last.succ_opt().unwrap_or(last)),
+        )
+    }
+
+    /// Start of the day containing a timestamp
+    pub fn day_start(&self, timestamp: i64) -> i64 {
+        self.date_start(self.date_of(timestamp))
+    }
+
+    /// Start of the day after the one containing a timestamp
+    pub fn next_day(&self, timestamp: i64) -> i64 {
+        let date = self.date_of(timestamp);
+        self.date_start(date.succ_opt().unwrap_or(date))
+    }
+
+    /// Clip an item to `[from, to)` and cut it at every day boundary
+    ///
+    /// The pieces keep the item's id. Items outside the range give nothing.
+    pub fn split_item(&self, item: &TrackItem, from: i64, to: i64) -> Vec<TrackItem> {
+        let begin = item.begin_date.max(from);
+        let end = item.end_date.min(to);
+        if begin >= end {
+            return Vec::new();
+        }
+
+        let mut pieces = Vec::new();
+        let mut piece_begin = begin;
+        while piece_begin < end {
+            let piece_end = self.next_day(piece_begin).min(end);
+            pieces.push(TrackItem {
+                begin_date: piece_begin,
+                end_date: piece_end,
+                ..item.clone()
+            });
+            piece_begin = piece_end;
+        }
+
+        pieces
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date '{}': {}", date, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    const HOUR: i64 = 3_600_000;
+    const DAYS: DayBoundaries = DayBoundaries {
+        zone: Zone::System,
+        start_hour: 0,
+    };
+
+    #[test]
+    fn test_split_at_midnight() {
+        let midnight = DAYS.day_start(10 * 24 * HOUR);
+        let item = TestItem::new(midnight - HOUR / 2, midnight + HOUR)
+            .id(1)
+            .build();
+        let pieces = DAYS.split_item(&item, 0, i64::MAX);
+
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].begin_date, midnight - HOUR / 2);
+        assert_eq!(pieces[0].end_date, midnight);
+        assert_eq!(pieces[1].begin_date, midnight);
+        assert_eq!(pieces[1].end_date, midnight + HOUR);
+        assert!(pieces.iter().all(|p| p.id == Some(1)));
+    }
+
+    #[test]
+    fn test_daily_totals_add_up_to_weekly_total() {
+        let week = DAYS.day_start(10 * 24 * HOUR);
+        let mut days = vec![week];
+        for _ in 0..7 {
+            days.push(DAYS.next_day(*days.last().unwrap()));
+        }
+        let items = [
+            TestItem::new(week - HOUR, week + HOUR).build(),
+            TestItem::new(days[2] - 3 * HOUR, days[4] + HOUR).build(),
+            TestItem::new(days[7] - HOUR, days[7] + HOUR).build(),
+        ];
+        let total = |from: i64, to: i64| -> i64 {
+            items
+                .iter()
+                .flat_map(|i| DAYS.split_item(i, from, to))
+                .map(|p| p.end_date - p.begin_date)
+                .sum()
+        };
+
+        let daily: i64 = days.windows(2).map(|d| total(d[0], d[1])).sum();
+        assert_eq!(daily, total(week, days[7]));
+        assert_eq!(daily, HOUR + 2 * 24 * HOUR + 4 * HOUR + HOUR);
+    }
+
+    #[test]
+    fn test_items_outside_the_range() {
+        assert!(DAYS
+            .split_item(&TestItem::new(0, HOUR).build(), HOUR, 2 * HOUR)
+            .is_empty());
+        assert_eq!(
+            DAYS.split_item(&TestItem::new(0, 2 * HOUR).build(), HOUR, 3 * HOUR)[0].begin_date,
+            HOUR
+        );
+    }
+
+    #[test]
+    fn test_start_hour_and_time_zone() {
+        let days = DayBoundaries::new(Some("America/New_York"), 4).unwrap();
+        let date = parse_date("2024-03-09").unwrap();
+
+        // 04:00 EST, and the day is an hour short across the DST change
+        let (from, to) = days.date_range(date, date);
+        assert_eq!(from, 1_709_974_800_000);
+        assert_eq!(to - from, 23 * HOUR);
+
+        assert_eq!(days.date_of(from - 1), date.pred_opt().unwrap());
+        assert_eq!(days.date_of(from), date);
+        assert_eq!(days.day_start(to - 1), from);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(DayBoundaries::new(Some("Mars/Olympus"), 0).is_err());
+        assert!(DayBoundaries::new(None, 24).is_err());
+        assert!(parse_date("2024-13-01").is_err());
+        assert_eq!(
+            DayBoundaries::new(Some(""), 0).unwrap(),
+            DayBoundaries::default()
+        );
+    }
+}
@@ -0,0 +1,504 @@
+// Track Item Editing Module
+// 记录编辑模块
+
+use super::{Database, TrackItem};
+use rusqlite::{ffi, params, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// What to change on the app items inside a time range; `None` keeps the
+/// current value
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RangeChanges {
+    pub app: Option<String>,
+    pub title: Option<String>,
+    /// Replaces every project link of the changed items
+    pub project_id: Option<i64>,
+}
+
+impl RangeChanges {
+    fn is_empty(&self) -> bool {
+        self.app.is_none() && self.title.is_none() && self.project_id.is_none()
+    }
+}
+
+/// An edit that would leave the timeline inconsistent
+///
+/// Reported as a constraint failure so the transaction is rolled back and
+/// the message reaches the caller.
+fn rejected(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_CONSTRAINT), Some(message))
+}
+
+fn item_from_row(row: &Row) -> Result<TrackItem> {
+    Ok(TrackItem {
+        id: Some(row.get(0)?),
+        app: row.get(1)?,
+        task_name: row.get(2)?,
+        title: row.get(3)?,
+        url: row.get(4)?,
+        domain: row.get(5)?,
+        color: row.get(6)?,
+        begin_date: row.get(7)?,
+        end_date: row.get(8)?,
+        productivity: row.get(9)?,
+    })
+}
+
+impl Database {
+    /// Split an item in two at `at`
+    ///
+    /// The second part gets a new id and the same projects and tags.
+    pub fn split_track_item(&self, id: i64, at: i64) -> Result<(TrackItem, TrackItem)> {
+        let tx = self.conn.unchecked_transaction()?;
+        let item = self.get_closed_item(id)?;
+        if at <= item.begin_date || at >= item.end_date {
+            return Err(rejected(format!(
+                "Split point is outside track item {}",
+                id
+            )));
+        }
+        let parts = self.split_item_at(&item, at)?;
+        tx.commit()?;
+        Ok(parts)
+    }
+
+    /// Merge items of one task type into the earliest of them
+    ///
+    /// The merged item spans all of them and keeps the labels of each.
+    pub fn merge_track_items(&self, ids: &[i64]) -> Result<TrackItem> {
+        let ids: BTreeSet<i64> = ids.iter().copied().collect();
+        if ids.len() < 2 {
+            return Err(rejected("Select at least two items to merge".to_string()));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut items = ids
+            .iter()
+            .map(|&id| self.get_closed_item(id))
+            .collect::<Result<Vec<_>>>()?;
+        items.sort_by_key(|item| (item.begin_date, item.id));
+
+        let mut merged = items[0].clone();
+        let merged_id = merged.id.unwrap_or_default();
+        for item in &items[1..] {
+            if item.task_name != merged.task_name {
+                return Err(rejected(
+                    "Only items of the same type can be merged".to_string(),
+                ));
+            }
+            let id = item.id.unwrap_or_default();
+            self.copy_item_labels(id, merged_id)?;
+            self.conn
+                .execute("DELETE FROM track_items WHERE id = ?1", params![id])?;
+            merged.end_date = merged.end_date.max(item.end_date);
+        }
+
+        self.conn.execute(
+            "UPDATE track_items SET end_date = ?1 WHERE id = ?2",
+            params![merged.end_date, merged_id],
+        )?;
+        self.check_overlaps(&[merged_id])?;
+        tx.commit()?;
+        Ok(merged)
+    }
+
+    /// Move items by `offset` milliseconds, keeping their durations
+    pub fn shift_track_items(&self, ids: &[i64], offset: i64) -> Result<Vec<TrackItem>> {
+        let ids: Vec<i64> = ids
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut items = Vec::with_capacity(ids.len());
+        for &id in &ids {
+            let mut item = self.get_closed_item(id)?;
+            item.begin_date += offset;
+            item.end_date += offset;
+            self.conn.execute(
+                "UPDATE track_items SET begin_date = ?1, end_date = ?2 WHERE id = ?3",
+                params![item.begin_date, item.end_date, id],
+            )?;
+            items.push(item);
+        }
+        self.check_overlaps(&ids)?;
+        tx.commit()?;
+        Ok(items)
+    }
+
+    /// Cut `[from, to)` out of the timeline
+    ///
+    /// Items inside the range are deleted, items crossing an edge are
+    /// clipped and items covering the whole range are split around it.
+    /// `task_name` limits the cut to one type. Returns the number of items
+    /// changed.
+    pub fn trim_track_items(&self, from: i64, to: i64, task_name: Option<&str>) -> Result<usize> {
+        if from >= to {
+            return Err(rejected("Range to trim is empty".to_string()));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let items = self.find_closed_items(from, to, task_name)?;
+        for item in &items {
+            let id = item.id.unwrap_or_default();
+            if item.begin_date >= from && item.end_date <= to {
+                self.conn
+                    .execute("DELETE FROM track_items WHERE id = ?1", params![id])?;
+                continue;
+            }
+            if item.begin_date < from && item.end_date > to {
+                self.split_item_at(item, to)?;
+            }
+            let (begin_date, end_date) = if item.begin_date < from {
+                (item.begin_date, from)
+            } else {
+                (to, item.end_date)
+            };
+            self.conn.execute(
+                "UPDATE track_items SET begin_date = ?1, end_date = ?2 WHERE id = ?3",
+                params![begin_date, end_date, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(items.len())
+    }
+
+    /// Change the app, title or project of the app items in `[from, to)`
+    ///
+    /// Items crossing an edge are split there and only the part inside
+    /// changes. A new app takes that app's color. Returns the changed
+    /// items.
+    pub fn reassign_range(
+        &self,
+        from: i64,
+        to: i64,
+        changes: &RangeChanges,
+    ) -> Result<Vec<TrackItem>> {
+        if from >= to {
+            return Err(rejected("Range to reassign is empty".to_string()));
+        }
+        if changes.is_empty() {
+            return Err(rejected("Nothing to change".to_string()));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let color = match &changes.app {
+            Some(app) => self.get_app_color(app)?,
+            None => None,
+        };
+
+        let mut changed = Vec::new();
+        for mut item in self.find_closed_items(from, to, Some("AppTrackItem"))? {
+            if item.begin_date < from {
+                item = self.split_item_at(&item, from)?.1;
+            }
+            if item.end_date > to {
+                item = self.split_item_at(&item, to)?.0;
+            }
+
+            if let Some(app) = &changes.app {
+                item.app = app.clone();
+                item.color = color.clone().or(item.color);
+            }
+            if let Some(title) = &changes.title {
+                item.title = title.clone();
+            }
+            let id = item.id.unwrap_or_default();
+            self.conn.execute(
+                "UPDATE track_items SET app = ?1, title = ?2, color = ?3,
+                     color_by_rule = CASE WHEN color IS ?3 THEN color_by_rule ELSE 0 END
+                 WHERE id = ?4",
+                params![item.app, item.title, item.color, id],
+            )?;
+            if let Some(project_id) = changes.project_id {
+                self.conn.execute(
+                    "DELETE FROM track_item_projects WHERE track_item_id = ?1",
+                    params![id],
+                )?;
+                self.conn.execute(
+                    "INSERT INTO track_item_projects (track_item_id, project_id) VALUES (?1, ?2)",
+                    params![id, project_id],
+                )?;
+            }
+            changed.push(item);
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Load an item the tracker is no longer extending
+    fn get_closed_item(&self, id: i64) -> Result<TrackItem> {
+        self.conn
+            .query_row(
+                "SELECT id, app, task_name, title, url, domain, color, begin_date, end_date, productivity
+                 FROM track_items WHERE id = ?1 AND is_open = 0",
+                params![id],
+                item_from_row,
+            )
+            .optional()?
+            .ok_or_else(|| {
+                rejected(format!(
+                    "Track item {} does not exist or is still being tracked",
+                    id
+                ))
+            })
+    }
+
+    /// Closed items overlapping `[from, to)`, oldest first
+    fn find_closed_items(
+        &self,
+        from: i64,
+        to: i64,
+        task_name: Option<&str>,
+    ) -> Result<Vec<TrackItem>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, app, task_name, title, url, domain, color, begin_date, end_date, productivity
+             FROM track_items
+             WHERE is_open = 0 AND end_date > ?1 AND begin_date < ?2
+               AND (?3 IS NULL OR task_name = ?3)
+             ORDER BY begin_date ASC",
+        )?;
+        let items = stmt.query_map(params![from, to, task_name], item_from_row)?;
+        items.collect()
+    }
+
+    /// End `item` at `at` and insert the rest as a new item with the same
+    /// labels
+    fn split_item_at(&self, item: &TrackItem, at: i64) -> Result<(TrackItem, TrackItem)> {
+        let id = item.id.unwrap_or_default();
+        self.conn.execute(
+            "UPDATE track_items SET end_date = ?1 WHERE id = ?2",
+            params![at, id],
+        )?;
+        let second = self.create_track_item(&TrackItem {
+            id: None,
+            begin_date: at,
+            ..item.clone()
+        })?;
+        self.copy_item_labels(id, second.id.unwrap_or_default())?;
+
+        let first = TrackItem {
+            end_date: at,
+            ..item.clone()
+        };
+        Ok((first, second))
+    }
+
+    /// Give `to_id` the projects and tags of `from_id`, and what of its
+    /// classification rules set
+    fn copy_item_labels(&self, from_id: i64, to_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE track_items
+             SET (color_by_rule, productivity_by_rule) =
+                 (SELECT color_by_rule, productivity_by_rule FROM track_items WHERE id = ?1)
+             WHERE id = ?2",
+            params![from_id, to_id],
+        )?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO track_item_projects (track_item_id, project_id, by_rule)
+             SELECT ?2, project_id, by_rule FROM track_item_projects WHERE track_item_id = ?1",
+            params![from_id, to_id],
+        )?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO track_item_tags (track_item_id, tag_id, by_rule)
+             SELECT ?2, tag_id, by_rule FROM track_item_tags WHERE track_item_id = ?1",
+            params![from_id, to_id],
+        )?;
+        Ok(())
+    }
+
+    /// Fail if any of the given app items overlaps another app item
+    fn check_overlaps(&self, ids: &[i64]) -> Result<()> {
+        let mut stmt = self.conn.prepare(
+            "SELECT b.id FROM track_items a
+             JOIN track_items b ON b.task_name = 'AppTrackItem' AND b.id != a.id
+                AND b.begin_date < a.end_date AND a.begin_date < b.end_date
+             WHERE a.id = ?1 AND a.task_name = 'AppTrackItem'
+             LIMIT 1",
+        )?;
+        for &id in ids {
+            let other: Option<i64> = stmt.query_row(params![id], |row| row.get(0)).optional()?;
+            if let Some(other) = other {
+                return Err(rejected(format!(
+                    "Track item {} would overlap track item {}",
+                    id, other
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    const MINUTE: i64 = 60_000;
+
+    fn spans(db: &Database, task_name: &str) -> Vec<(String, i64, i64)> {
+        db.find_all_day_items(0, i64::MAX, task_name)
+            .unwrap()
+            .into_iter()
+            .map(|i| (i.app, i.begin_date / MINUTE, i.end_date / MINUTE))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_keeps_labels() {
+        let db = Database::new(":memory:").unwrap();
+        let id = TestItem::new(0, 60 * MINUTE).create(&db);
+        let project = db.create_project("Website", None).unwrap();
+        let tag = db.create_tag("review", None).unwrap();
+        db.assign_project(project.id, &[id]).unwrap();
+        db.tag_items(tag.id, &[id]).unwrap();
+
+        let (first, second) = db.split_track_item(id, 20 * MINUTE).unwrap();
+        assert_eq!(first.id, Some(id));
+        assert_eq!(first.end_date, 20 * MINUTE);
+        assert_eq!(second.begin_date, 20 * MINUTE);
+        assert_eq!(second.end_date, 60 * MINUTE);
+
+        let labels = db.get_item_labels(&[second.id.unwrap()]).unwrap();
+        assert_eq!(labels[0].project_ids, vec![project.id]);
+        assert_eq!(labels[0].tag_ids, vec![tag.id]);
+
+        assert!(db.split_track_item(id, 20 * MINUTE).is_err());
+        assert!(db.split_track_item(id, 90 * MINUTE).is_err());
+    }
+
+    #[test]
+    fn test_merge_rejects_overlap() {
+        let db = Database::new(":memory:").unwrap();
+        let a = TestItem::new(0, 10 * MINUTE).create(&db);
+        let b = TestItem::new(10 * MINUTE, 20 * MINUTE).create(&db);
+        let c = TestItem::new(25 * MINUTE, 30 * MINUTE)
+            .app("Slack")
+            .create(&db);
+        let d = TestItem::new(40 * MINUTE, 50 * MINUTE).create(&db);
+
+        let merged = db.merge_track_items(&[b, a]).unwrap();
+        assert_eq!(merged.id, Some(a));
+        assert_eq!(
+            spans(&db, "AppTrackItem"),
+            vec![
+                ("Code".to_string(), 0, 20),
+                ("Slack".to_string(), 25, 30),
+                ("Code".to_string(), 40, 50),
+            ]
+        );
+
+        // Spanning the Slack item would overlap it; nothing changes
+        let err = db.merge_track_items(&[a, d]).unwrap_err();
+        assert!(err.to_string().contains(&c.to_string()));
+        assert_eq!(spans(&db, "AppTrackItem").len(), 3);
+
+        let log = TestItem::new(60 * MINUTE, 70 * MINUTE)
+            .app("Log")
+            .task_name("LogTrackItem")
+            .create(&db);
+        assert!(db.merge_track_items(&[d, log]).is_err());
+    }
+
+    #[test]
+    fn test_shift_rejects_overlap() {
+        let db = Database::new(":memory:").unwrap();
+        let a = TestItem::new(0, 10 * MINUTE).create(&db);
+        let b = TestItem::new(10 * MINUTE, 20 * MINUTE)
+            .app("Slack")
+            .create(&db);
+
+        assert!(db.shift_track_items(&[a], 5 * MINUTE).is_err());
+        db.shift_track_items(&[a, b], 5 * MINUTE).unwrap();
+        assert_eq!(
+            spans(&db, "AppTrackItem"),
+            vec![("Code".to_string(), 5, 15), ("Slack".to_string(), 15, 25)]
+        );
+    }
+
+    #[test]
+    fn test_trim_range() {
+        let db = Database::new(":memory:").unwrap();
+        TestItem::new(0, 60 * MINUTE).create(&db);
+        TestItem::new(60 * MINUTE, 70 * MINUTE)
+            .app("Slack")
+            .create(&db);
+        TestItem::new(70 * MINUTE, 90 * MINUTE).create(&db);
+        TestItem::new(0, 90 * MINUTE)
+            .app("Meeting")
+            .task_name("LogTrackItem")
+            .create(&db);
+
+        let changed = db
+            .trim_track_items(30 * MINUTE, 80 * MINUTE, Some("AppTrackItem"))
+            .unwrap();
+        assert_eq!(changed, 3);
+        assert_eq!(
+            spans(&db, "AppTrackItem"),
+            vec![("Code".to_string(), 0, 30), ("Code".to_string(), 80, 90)]
+        );
+        assert_eq!(
+            spans(&db, "LogTrackItem"),
+            vec![("Meeting".to_string(), 0, 90)]
+        );
+
+        db.trim_track_items(30 * MINUTE, 40 * MINUTE, None).unwrap();
+        assert_eq!(
+            spans(&db, "LogTrackItem"),
+            vec![
+                ("Meeting".to_string(), 0, 30),
+                ("Meeting".to_string(), 40, 90)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reassign_range() {
+        let db = Database::new(":memory:").unwrap();
+        let a = TestItem::new(0, 30 * MINUTE).app("Firefox").create(&db);
+        TestItem::new(30 * MINUTE, 60 * MINUTE)
+            .app("Slack")
+            .create(&db);
+        let project = db.create_project("Research", None).unwrap();
+        db.update_track_item_color("Zotero", "#123456").unwrap();
+
+        let changed = db
+            .reassign_range(
+                20 * MINUTE,
+                40 * MINUTE,
+                &RangeChanges {
+                    app: Some("Zotero".to_string()),
+                    project_id: Some(project.id),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(changed.len(), 2);
+        assert!(changed
+            .iter()
+            .all(|i| i.color.as_deref() == Some("#123456")));
+        assert_eq!(
+            spans(&db, "AppTrackItem"),
+            vec![
+                ("Firefox".to_string(), 0, 20),
+                ("Zotero".to_string(), 20, 30),
+                ("Zotero".to_string(), 30, 40),
+                ("Slack".to_string(), 40, 60),
+            ]
+        );
+
+        let ids: Vec<i64> = changed.iter().filter_map(|i| i.id).collect();
+        let labels = db.get_item_labels(&ids).unwrap();
+        assert!(labels.iter().all(|l| l.project_ids == vec![project.id]));
+        let labels = db.get_item_labels(&[a]).unwrap();
+        assert!(labels.iter().all(|l| l.project_ids.is_empty()));
+
+        assert!(db
+            .reassign_range(0, MINUTE, &RangeChanges::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// Encryption Module
+// 数据库加密模块
+
+use super::Database;
+use rusqlite::{params, Connection, DatabaseName, Result};
+use std::io::Read;
+use std::path::Path;
+
+/// Keyring entry holding the generated database key
+const KEYRING_SERVICE: &str = "timlyzer";
+const KEYRING_USER: &str = "database-key";
+/// Keyring entry holding a new key while the database is re-encrypted
+const KEYRING_STAGED_USER: &str = "database-key-staged";
+/// Every unencrypted SQLite file starts with this header
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+/// Alias of the database that copies are exported into
+const EXPORT_DB: &str = "export";
+
+/// A SQLCipher key
+#[derive(Clone)]
+pub enum DatabaseKey {
+    /// Typed by the user; SQLCipher derives the key from it
+    Passphrase(String),
+    /// 32 random bytes, hex encoded, kept in the OS secret store
+    Raw(String),
+}
+
+impl DatabaseKey {
+    /// Generate a new random key
+    pub fn generate() -> std::result::Result<Self, String> {
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+        Ok(DatabaseKey::Raw(
+            bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        ))
+    }
+
+    /// Value for `PRAGMA key` and `ATTACH ... KEY`
+    fn pragma_value(&self) -> String {
+        match self {
+            DatabaseKey::Passphrase(passphrase) => passphrase.clone(),
+            DatabaseKey::Raw(hex) => format!("x'{}'", hex),
+        }
+    }
+}
+
+fn keyring_entry(user: &str) -> std::result::Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, user).map_err(|e| e.to_string())
+}
+
+fn delete_keyring_entry(user: &str) -> std::result::Result<(), String> {
+    match keyring_entry(user)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Read the keys stored in the OS secret store: the current one, then a
+/// staged one left behind by an interrupted re-key
+pub fn load_keyring_keys() -> std::result::Result<Vec<DatabaseKey>, String> {
+    let mut keys = Vec::new();
+    for user in [KEYRING_USER, KEYRING_STAGED_USER] {
+        match keyring_entry(user)?.get_password() {
+            Ok(hex) => keys.push(DatabaseKey::Raw(hex)),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(keys)
+}
+
+/// Save a generated key next to the current one before the database is
+/// re-encrypted with it
+///
+/// Passphrases are never stored.
+pub fn stage_key(key: &DatabaseKey) -> std::result::Result<(), String> {
+    match key {
+        DatabaseKey::Raw(hex) => keyring_entry(KEYRING_STAGED_USER)?
+            .set_password(hex)
+            .map_err(|e| e.to_string()),
+        DatabaseKey::Passphrase(_) => Ok(()),
+    }
+}
+
+/// Drop a staged key once re-encrypting failed
+pub fn discard_staged_key() -> std::result::Result<(), String> {
+    delete_keyring_entry(KEYRING_STAGED_USER)
+}
+
+/// Make a key the one remembered in the OS secret store
+///
+/// Only generated keys are stored. Passphrases, or no key at all, clear
+/// the saved entry so the next launch asks for the passphrase. The staged
+/// key is dropped only once the current entry is replaced.
+pub fn remember_key(key: Option<&DatabaseKey>) -> std::result::Result<(), String> {
+    match key {
+        Some(DatabaseKey::Raw(hex)) => keyring_entry(KEYRING_USER)?
+            .set_password(hex)
+            .map_err(|e| e.to_string())?,
+        _ => delete_keyring_entry(KEYRING_USER)?,
+    }
+    discard_staged_key()
+}
+
+/// Whether a database file is encrypted
+///
+/// Missing and empty files count as unencrypted.
+pub fn is_encrypted_file(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        Err(_) => false,
+    }
+}
+
+/// Apply a key to a freshly opened connection and check that it works
+pub(super) fn apply_key(conn: &Connection, key: &DatabaseKey) -> Result<()> {
+    conn.pragma_update(None, "key", key.pragma_value())?;
+    // A wrong key only shows up once a page is read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })?;
+    Ok(())
+}
+
+impl Database {
+    /// Write a full copy of the database to a new file
+    ///
+    /// With a key the copy is encrypted with it; without one it is a
+    /// plain SQLite file.
+    pub fn export_copy(&self, path: &str, key: Option<&DatabaseKey>) -> Result<()> {
+        let key = key.map(DatabaseKey::pragma_value).unwrap_or_default();
+        let version: i64 = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        self.conn.execute(
+            &format!("ATTACH DATABASE ?1 AS {} KEY ?2", EXPORT_DB),
+            params![path, key],
+        )?;
+        let exported = self
+            .conn
+            .query_row(
+                "SELECT sqlcipher_export(?1)",
+                params![EXPORT_DB],
+                |_| Ok(()),
+            )
+            .and_then(|_| {
+                // sqlcipher_export leaves the schema version behind
+                self.conn.pragma_update(
+                    Some(DatabaseName::Attached(EXPORT_DB)),
+                    "user_version",
+                    version,
+                )
+            });
+        self.conn
+            .execute(&format!("DETACH DATABASE {}", EXPORT_DB), [])?;
+
+        exported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("timlyzer-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_encrypted_copy_round_trip() {
+        let plain = temp_path("plain");
+        let encrypted = temp_path("encrypted");
+        let decrypted = temp_path("decrypted");
+        let key = DatabaseKey::generate().unwrap();
+
+        let db = Database::new(plain.to_str().unwrap()).unwrap();
+        db.create_track_item(&TestItem::new(0, 1_000).build())
+            .unwrap();
+        assert!(!is_encrypted_file(&plain));
+
+        db.export_copy(encrypted.to_str().unwrap(), Some(&key))
+            .unwrap();
+        assert!(is_encrypted_file(&encrypted));
+        assert!(Database::open(encrypted.to_str().unwrap(), None).is_err());
+
+        let db = Database::open(encrypted.to_str().unwrap(), Some(&key)).unwrap();
+        assert_eq!(
+            db.find_all_day_items(0, 1_000, "AppTrackItem")
+                .unwrap()
+                .len(),
+            1
+        );
+        let (_, total, _) = db
+            .search_items(0, 1_000, None, Some("main"), 50, 0)
+            .unwrap();
+        assert_eq!(total, 1);
+
+        db.export_copy(decrypted.to_str().unwrap(), None).unwrap();
+        assert!(!is_encrypted_file(&decrypted));
+        let db = Database::new(decrypted.to_str().unwrap()).unwrap();
+        assert_eq!(
+            db.find_all_day_items(0, 1_000, "AppTrackItem")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        for path in [plain, encrypted, decrypted] {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let plain = temp_path("source");
+        let encrypted = temp_path("passphrase");
+        let key = DatabaseKey::Passphrase("correct horse".to_string());
+        let wrong = DatabaseKey::Passphrase("battery staple".to_string());
+
+        let db = Database::new(plain.to_str().unwrap()).unwrap();
+        db.export_copy(encrypted.to_str().unwrap(), Some(&key))
+            .unwrap();
+
+        assert!(Database::open(encrypted.to_str().unwrap(), Some(&wrong)).is_err());
+        assert!(Database::open(encrypted.to_str().unwrap(), Some(&key)).is_ok());
+
+        for path in [plain, encrypted] {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,450 @@
+// Goals Module
+// 目标与限制模块
+
+use super::Database;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Result, Row, ToSql};
+use serde::{Deserialize, Serialize};
+
+/// Whether a goal asks for at least or at most its duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GoalKind {
+    /// Spend at least the duration
+    Goal,
+    /// Spend no more than the duration
+    Limit,
+}
+
+impl GoalKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GoalKind::Goal => "goal",
+            GoalKind::Limit => "limit",
+        }
+    }
+}
+
+impl ToSql for GoalKind {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for GoalKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "goal" => Ok(GoalKind::Goal),
+            "limit" => Ok(GoalKind::Limit),
+            other => Err(FromSqlError::Other(
+                format!("unknown goal kind: {}", other).into(),
+            )),
+        }
+    }
+}
+
+/// The stretch of time a goal's duration applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GoalPeriod {
+    Day,
+    /// Monday to Sunday
+    Week,
+}
+
+impl GoalPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GoalPeriod::Day => "day",
+            GoalPeriod::Week => "week",
+        }
+    }
+}
+
+impl ToSql for GoalPeriod {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for GoalPeriod {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "day" => Ok(GoalPeriod::Day),
+            "week" => Ok(GoalPeriod::Week),
+            other => Err(FromSqlError::Other(
+                format!("unknown goal period: {}", other).into(),
+            )),
+        }
+    }
+}
+
+/// Which time counts toward a goal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum GoalTarget {
+    /// App items of an app
+    App(String),
+    /// App items on a domain
+    Domain(String),
+    /// Items assigned to a project
+    Project(i64),
+    /// Items with a tag
+    Tag(i64),
+}
+
+/// A daily or weekly goal or limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    pub id: Option<i64>,
+    pub name: String,
+    pub enabled: bool,
+    pub kind: GoalKind,
+    pub period: GoalPeriod,
+    pub target: GoalTarget,
+    /// Target time per period (milliseconds)
+    pub duration: i64,
+    /// Weekdays a daily goal applies on (0 = Monday); empty for every day
+    #[serde(default)]
+    pub weekdays: Vec<u32>,
+}
+
+/// How a goal went in one period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalHistoryEntry {
+    pub goal_id: i64,
+    pub period_start: i64,
+    /// Local date the period starts on (`YYYY-MM-DD`)
+    pub date: String,
+    /// Time counted toward the goal (milliseconds)
+    pub progress: i64,
+    pub met: bool,
+}
+
+fn json_err(e: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+}
+
+impl Goal {
+    fn from_row(row: &Row) -> Result<Self> {
+        let target: String = row.get(5)?;
+        let weekdays: String = row.get(7)?;
+
+        Ok(Self {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            enabled: row.get(2)?,
+            kind: row.get(3)?,
+            period: row.get(4)?,
+            target: serde_json::from_str(&target).map_err(json_err)?,
+            duration: row.get(6)?,
+            weekdays: serde_json::from_str(&weekdays).map_err(json_err)?,
+        })
+    }
+
+    /// Whether progress of `progress` meets the goal
+    ///
+    /// A limit counts as met until it is exceeded.
+    pub fn is_met(&self, progress: i64) -> bool {
+        match self.kind {
+            GoalKind::Goal => progress >= self.duration,
+            GoalKind::Limit => progress <= self.duration,
+        }
+    }
+}
+
+impl Database {
+    /// List all goals
+    pub fn get_goals(&self) -> Result<Vec<Goal>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, enabled, kind, period, target, duration, weekdays FROM goals
+             ORDER BY id ASC",
+        )?;
+
+        let goals = stmt.query_map([], Goal::from_row)?;
+        goals.collect()
+    }
+
+    /// Insert a goal, or update it if it has an id
+    pub fn save_goal(&self, goal: &Goal) -> Result<Goal> {
+        let target = serde_json::to_string(&goal.target).map_err(json_err)?;
+        let weekdays = serde_json::to_string(&goal.weekdays).map_err(json_err)?;
+
+        let id = match goal.id {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE goals SET name = ?1, enabled = ?2, kind = ?3, period = ?4, target = ?5, duration = ?6, weekdays = ?7
+                     WHERE id = ?8",
+                    params![
+                        goal.name,
+                        goal.enabled,
+                        goal.kind,
+                        goal.period,
+                        target,
+                        goal.duration,
+                        weekdays,
+                        id
+                    ],
+                )?;
+                id
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO goals (name, enabled, kind, period, target, duration, weekdays)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        goal.name,
+                        goal.enabled,
+                        goal.kind,
+                        goal.period,
+                        target,
+                        goal.duration,
+                        weekdays
+                    ],
+                )?;
+                self.conn.last_insert_rowid()
+            }
+        };
+
+        Ok(Goal {
+            id: Some(id),
+            ..goal.clone()
+        })
+    }
+
+    /// Delete a goal and its history
+    pub fn delete_goal(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM goals WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Time counted toward a goal's target in `[from, to)`, clipped to it
+    ///
+    /// Counts closed app and log items, the same ones the usage statistics
+    /// are built from. A domain also counts its subdomains.
+    pub fn get_goal_progress(&self, target: &GoalTarget, from: i64, to: i64) -> Result<i64> {
+        let domain = match target {
+            GoalTarget::Domain(domain) => domain.to_lowercase(),
+            _ => String::new(),
+        };
+        let (filter, value): (&str, &dyn ToSql) = match target {
+            GoalTarget::App(app) => ("t.app = ?3", app),
+            GoalTarget::Domain(_) => (
+                "(LOWER(t.domain) = ?3 OR t.domain LIKE '%.' || ?3)",
+                &domain,
+            ),
+            GoalTarget::Project(id) => (
+                "EXISTS (SELECT 1 FROM track_item_projects l
+                         WHERE l.track_item_id = t.id AND l.project_id = ?3)",
+                id,
+            ),
+            GoalTarget::Tag(id) => (
+                "EXISTS (SELECT 1 FROM track_item_tags l
+                         WHERE l.track_item_id = t.id AND l.tag_id = ?3)",
+                id,
+            ),
+        };
+
+        self.conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(MIN(t.end_date, ?2) - MAX(t.begin_date, ?1)), 0)
+                 FROM track_items t
+                 WHERE t.task_name IN ('AppTrackItem', 'LogTrackItem') AND t.is_open = 0
+                   AND t.end_date > ?1 AND t.begin_date < ?2 AND {}",
+                filter
+            ),
+            params![from, to, value],
+            |row| row.get(0),
+        )
+    }
+
+    /// Record how a goal went in the period starting at `period_start`
+    pub fn save_goal_history(&self, entry: &GoalHistoryEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO goal_history (goal_id, period_start, date, progress, met)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(goal_id, period_start) DO UPDATE SET date = ?3, progress = ?4, met = ?5",
+            params![
+                entry.goal_id,
+                entry.period_start,
+                entry.date,
+                entry.progress,
+                entry.met
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// How a goal went in the periods starting in `[from, to)`, oldest first
+    pub fn get_goal_history(
+        &self,
+        goal_id: i64,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<GoalHistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT goal_id, period_start, date, progress, met FROM goal_history
+             WHERE goal_id = ?1 AND period_start >= ?2 AND period_start < ?3
+             ORDER BY period_start ASC",
+        )?;
+
+        let rows = stmt.query_map(params![goal_id, from, to], |row| {
+            Ok(GoalHistoryEntry {
+                goal_id: row.get(0)?,
+                period_start: row.get(1)?,
+                date: row.get(2)?,
+                progress: row.get(3)?,
+                met: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{RuleActions, TestItem};
+
+    const HOUR: i64 = 3_600_000;
+
+    fn goal(target: GoalTarget) -> Goal {
+        Goal {
+            id: None,
+            name: "Deep work".to_string(),
+            enabled: true,
+            kind: GoalKind::Goal,
+            period: GoalPeriod::Day,
+            target,
+            duration: 4 * HOUR,
+            weekdays: vec![0, 1, 2, 3, 4],
+        }
+    }
+
+    #[test]
+    fn test_save_and_delete_goal() {
+        let db = Database::new(":memory:").unwrap();
+        let saved = db
+            .save_goal(&goal(GoalTarget::App("Code".to_string())))
+            .unwrap();
+        assert_eq!(db.get_goals().unwrap(), vec![saved.clone()]);
+
+        let updated = db
+            .save_goal(&Goal {
+                kind: GoalKind::Limit,
+                target: GoalTarget::Domain("youtube.com".to_string()),
+                ..saved.clone()
+            })
+            .unwrap();
+        assert_eq!(db.get_goals().unwrap(), vec![updated]);
+
+        db.save_goal_history(&GoalHistoryEntry {
+            goal_id: saved.id.unwrap(),
+            period_start: 0,
+            date: "1970-01-01".to_string(),
+            progress: HOUR,
+            met: true,
+        })
+        .unwrap();
+        db.delete_goal(saved.id.unwrap()).unwrap();
+        assert!(db.get_goals().unwrap().is_empty());
+        assert!(db
+            .get_goal_history(saved.id.unwrap(), 0, HOUR)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_goal_progress_is_clipped() {
+        let db = Database::new(":memory:").unwrap();
+        TestItem::new(0, 2 * HOUR)
+            .app("Firefox")
+            .domain("youtube.com")
+            .create(&db);
+        TestItem::new(2 * HOUR, 3 * HOUR)
+            .app("Firefox")
+            .domain("github.com")
+            .create(&db);
+        TestItem::new(3 * HOUR, 5 * HOUR).create(&db);
+        // Subdomains count, look-alikes do not
+        TestItem::new(5 * HOUR, 6 * HOUR)
+            .app("Firefox")
+            .domain("www.youtube.com")
+            .create(&db);
+        TestItem::new(6 * HOUR, 7 * HOUR)
+            .app("Firefox")
+            .domain("notyoutube.com")
+            .create(&db);
+
+        let domain = GoalTarget::Domain("YouTube.com".to_string());
+        assert_eq!(
+            db.get_goal_progress(&domain, HOUR, 10 * HOUR).unwrap(),
+            2 * HOUR
+        );
+        let app = GoalTarget::App("Firefox".to_string());
+        assert_eq!(db.get_goal_progress(&app, 0, 10 * HOUR).unwrap(), 5 * HOUR);
+    }
+
+    #[test]
+    fn test_goal_progress_by_project_and_tag() {
+        let db = Database::new(":memory:").unwrap();
+        let project = db.create_project("Writing", None).unwrap();
+        let tag = db.create_tag("Deep Work", None).unwrap();
+        let item = TestItem::new(0, HOUR).create(&db);
+        TestItem::new(HOUR, 2 * HOUR).create(&db);
+        db.apply_rule_actions(
+            item,
+            &RuleActions {
+                project_id: Some(project.id),
+                tag_ids: vec![tag.id],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let project = GoalTarget::Project(project.id);
+        assert_eq!(db.get_goal_progress(&project, 0, 10 * HOUR).unwrap(), HOUR);
+        let tag = GoalTarget::Tag(tag.id);
+        assert_eq!(db.get_goal_progress(&tag, 0, 10 * HOUR).unwrap(), HOUR);
+    }
+
+    #[test]
+    fn test_goal_history_keeps_one_row_per_period() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db
+            .save_goal(&goal(GoalTarget::App("Code".to_string())))
+            .unwrap()
+            .id
+            .unwrap();
+
+        let entry = GoalHistoryEntry {
+            goal_id: id,
+            period_start: 0,
+            date: "1970-01-01".to_string(),
+            progress: HOUR,
+            met: false,
+        };
+        db.save_goal_history(&entry).unwrap();
+        db.save_goal_history(&GoalHistoryEntry {
+            progress: 5 * HOUR,
+            met: true,
+            ..entry.clone()
+        })
+        .unwrap();
+        db.save_goal_history(&GoalHistoryEntry {
+            period_start: 24 * HOUR,
+            date: "1970-01-02".to_string(),
+            ..entry
+        })
+        .unwrap();
+
+        let history = db.get_goal_history(id, 0, 48 * HOUR).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].progress, 5 * HOUR);
+        assert!(history[0].met);
+        assert!(!history[1].met);
+    }
+}
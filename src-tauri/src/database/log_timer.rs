@@ -0,0 +1,171 @@
+// Log Timer Module
+// 手动计时模块
+
+use super::{Database, TrackItem, MIN_ITEM_DURATION};
+use rusqlite::{params, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+
+/// App name of items logged with the timer
+pub const LOG_APP: &str = "Log";
+
+/// A running manual timer
+///
+/// Kept in the database so it keeps running across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogTimer {
+    pub title: String,
+    pub project_id: Option<i64>,
+    pub begin_date: i64,
+}
+
+impl Database {
+    /// The running timer, if any
+    pub fn get_log_timer(&self) -> Result<Option<LogTimer>> {
+        self.conn
+            .query_row(
+                "SELECT title, project_id, begin_date FROM log_timer WHERE id = 1",
+                [],
+                |row| {
+                    Ok(LogTimer {
+                        title: row.get(0)?,
+                        project_id: row.get(1)?,
+                        begin_date: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Start a timer at `now`
+    ///
+    /// Only one timer runs at a time: a running one is stopped first and
+    /// its item returned along with the new timer.
+    pub fn start_log_timer(
+        &self,
+        title: &str,
+        project_id: Option<i64>,
+        now: i64,
+    ) -> Result<(LogTimer, Option<TrackItem>)> {
+        let tx = self.conn.unchecked_transaction()?;
+        let stopped = self.finish_log_timer(now)?;
+
+        let timer = LogTimer {
+            title: title.to_string(),
+            project_id,
+            begin_date: now,
+        };
+        tx.execute(
+            "INSERT INTO log_timer (id, title, project_id, begin_date) VALUES (1, ?1, ?2, ?3)",
+            params![timer.title, timer.project_id, timer.begin_date],
+        )?;
+
+        tx.commit()?;
+        Ok((timer, stopped))
+    }
+
+    /// Stop the running timer at `now` and log its time
+    ///
+    /// The `LogTrackItem` takes the project's color and is assigned to the
+    /// project. Returns `None` when no timer ran or it ran too briefly to
+    /// keep.
+    pub fn stop_log_timer(&self, now: i64) -> Result<Option<TrackItem>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let item = self.finish_log_timer(now)?;
+        tx.commit()?;
+        Ok(item)
+    }
+
+    /// `stop_log_timer` without its own transaction
+    fn finish_log_timer(&self, now: i64) -> Result<Option<TrackItem>> {
+        let Some(timer) = self.get_log_timer()? else {
+            return Ok(None);
+        };
+        self.conn.execute("DELETE FROM log_timer", [])?;
+
+        let end_date = now.max(timer.begin_date);
+        if end_date - timer.begin_date < MIN_ITEM_DURATION {
+            return Ok(None);
+        }
+
+        let color = match timer.project_id {
+            Some(id) => self
+                .conn
+                .query_row(
+                    "SELECT color FROM projects WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten(),
+            None => None,
+        };
+        let item = self.create_track_item(&TrackItem {
+            id: None,
+            app: LOG_APP.to_string(),
+            task_name: "LogTrackItem".to_string(),
+            title: timer.title,
+            url: None,
+            domain: None,
+            color,
+            begin_date: timer.begin_date,
+            end_date,
+            productivity: None,
+        })?;
+        if let Some(project_id) = timer.project_id {
+            self.conn.execute(
+                "INSERT INTO track_item_projects (track_item_id, project_id) VALUES (?1, ?2)",
+                params![item.id, project_id],
+            )?;
+        }
+
+        Ok(Some(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+
+    #[test]
+    fn test_timer_logs_an_item_for_its_project() {
+        let db = Database::new(":memory:").unwrap();
+        let project = db.create_project("Hiring", Some("#ff0000")).unwrap();
+
+        db.start_log_timer("Interview", Some(project.id), 0)
+            .unwrap();
+        assert_eq!(db.get_log_timer().unwrap().unwrap().title, "Interview");
+
+        let item = db.stop_log_timer(30 * MINUTE).unwrap().unwrap();
+        assert_eq!(item.task_name, "LogTrackItem");
+        assert_eq!(item.end_date - item.begin_date, 30 * MINUTE);
+        assert_eq!(item.color.as_deref(), Some("#ff0000"));
+        assert_eq!(
+            db.get_item_labels(&[item.id.unwrap()]).unwrap()[0].project_ids,
+            vec![project.id]
+        );
+
+        assert!(db.get_log_timer().unwrap().is_none());
+        assert!(db.stop_log_timer(40 * MINUTE).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_only_one_timer_runs() {
+        let db = Database::new(":memory:").unwrap();
+        db.start_log_timer("Call", None, 0).unwrap();
+
+        let (timer, stopped) = db.start_log_timer("Meeting", None, 10 * MINUTE).unwrap();
+        assert_eq!(stopped.unwrap().title, "Call");
+        assert_eq!(db.get_log_timer().unwrap(), Some(timer));
+
+        // The meeting ran too briefly to keep
+        db.start_log_timer("Lunch", None, 10 * MINUTE + 500)
+            .unwrap();
+        let items = db
+            .find_all_day_items(0, 20 * MINUTE, "LogTrackItem")
+            .unwrap();
+        assert_eq!(items.len(), 1);
+    }
+}
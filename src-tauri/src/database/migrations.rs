@@ -0,0 +1,446 @@
+// Schema Migrations Module
+// 数据库迁移模块
+
+use rusqlite::{ffi, Connection, Error, Result, TransactionBehavior};
+
+/// A single schema migration
+///
+/// Migrations are applied in order of `version`, each inside its own
+/// transaction. The applied version is stored in `PRAGMA user_version`.
+pub struct Migration {
+    /// Schema version this migration upgrades to
+    pub version: i64,
+    /// Short human-readable description (used in logs)
+    pub description: &'static str,
+    /// Applies the migration
+    pub up: fn(&Connection) -> Result<()>,
+}
+
+/// All migrations, ordered by version. Append new migrations to the end;
+/// never edit or reorder one that has already shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        up: migrate_v1_baseline,
+    },
+    Migration {
+        version: 2,
+        description: "track open items for crash recovery",
+        up: migrate_v2_open_items,
+    },
+    Migration {
+        version: 3,
+        description: "full-text search index",
+        up: migrate_v3_search_index,
+    },
+    Migration {
+        version: 4,
+        description: "projects and tags",
+        up: migrate_v4_projects_and_tags,
+    },
+    Migration {
+        version: 5,
+        description: "classification rules",
+        up: migrate_v5_rules,
+    },
+    Migration {
+        version: 6,
+        description: "daily usage rollups",
+        up: migrate_v6_daily_rollups,
+    },
+    Migration {
+        version: 7,
+        description: "running log timer",
+        up: migrate_v7_log_timer,
+    },
+    Migration {
+        version: 8,
+        description: "break reminder log",
+        up: migrate_v8_break_reminders,
+    },
+    Migration {
+        version: 9,
+        description: "goals, limits and their history",
+        up: migrate_v9_goals,
+    },
+    Migration {
+        version: 10,
+        description: "mark labels, colors and productivity set by rules",
+        up: migrate_v10_rule_labels,
+    },
+];
+
+/// Latest schema version known to this build
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Read the schema version stored in the database
+pub fn current_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Bring the database schema up to date
+///
+/// Refuses to touch a database written by a newer version of the app.
+pub fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_CANTOPEN),
+            Some(format!(
+                "Database schema version {} is newer than supported version {}; \
+                 please update Timlyzer",
+                current, latest
+            )),
+        ));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        log::info!(
+            "Applying database migration {}: {}",
+            migration.version,
+            migration.description
+        );
+
+        // Take the write lock up front and re-check the version, so two
+        // processes opening the same file cannot apply a migration twice
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if current_version(&tx)? >= migration.version {
+            continue;
+        }
+        (migration.up)(&tx)?;
+        // PRAGMA does not accept bound parameters
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Check whether a table has a given column
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// v1: the schema as it existed before versioning was introduced
+///
+/// Databases created by earlier builds already have some or all of these
+/// tables (possibly without the `domain` column), so everything here must
+/// be idempotent.
+fn migrate_v1_baseline(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS track_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app TEXT NOT NULL,
+            task_name TEXT NOT NULL,
+            title TEXT NOT NULL,
+            url TEXT,
+            domain TEXT,
+            color TEXT,
+            begin_date INTEGER NOT NULL,
+            end_date INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS app_settings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS settings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            json_data TEXT
+        );",
+    )?;
+
+    if !has_column(conn, "track_items", "domain")? {
+        conn.execute("ALTER TABLE track_items ADD COLUMN domain TEXT", [])?;
+    }
+
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_track_items_begin_date ON track_items(begin_date);
+        CREATE INDEX IF NOT EXISTS idx_track_items_end_date ON track_items(end_date);
+        CREATE INDEX IF NOT EXISTS idx_track_items_task_name ON track_items(task_name);",
+    )?;
+
+    Ok(())
+}
+
+/// v2: flag rows the tracker is still extending
+fn migrate_v2_open_items(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE track_items ADD COLUMN is_open INTEGER NOT NULL DEFAULT 0;
+        CREATE INDEX idx_track_items_open ON track_items(is_open) WHERE is_open = 1;",
+    )
+}
+
+/// v3: FTS5 index over the searchable text of track items
+///
+/// The index stores no content of its own; triggers keep it in sync with
+/// `track_items`. Updates that only move `end_date` (heartbeats) skip it.
+fn migrate_v3_search_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE track_items_fts USING fts5(
+            app, title, url, domain,
+            content = 'track_items',
+            content_rowid = 'id'
+        );
+
+        CREATE TRIGGER track_items_fts_insert AFTER INSERT ON track_items BEGIN
+            INSERT INTO track_items_fts (rowid, app, title, url, domain)
+            VALUES (new.id, new.app, new.title, new.url, new.domain);
+        END;
+
+        CREATE TRIGGER track_items_fts_delete AFTER DELETE ON track_items BEGIN
+            INSERT INTO track_items_fts (track_items_fts, rowid, app, title, url, domain)
+            VALUES ('delete', old.id, old.app, old.title, old.url, old.domain);
+        END;
+
+        CREATE TRIGGER track_items_fts_update
+        AFTER UPDATE OF app, title, url, domain ON track_items BEGIN
+            INSERT INTO track_items_fts (track_items_fts, rowid, app, title, url, domain)
+            VALUES ('delete', old.id, old.app, old.title, old.url, old.domain);
+            INSERT INTO track_items_fts (rowid, app, title, url, domain)
+            VALUES (new.id, new.app, new.title, new.url, new.domain);
+        END;
+
+        INSERT INTO track_items_fts (track_items_fts) VALUES ('rebuild');",
+    )
+}
+
+/// v4: projects and tags, linked many-to-many to track items
+fn migrate_v4_projects_and_tags(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT,
+            archived INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT
+        );
+
+        CREATE TABLE track_item_projects (
+            track_item_id INTEGER NOT NULL REFERENCES track_items(id) ON DELETE CASCADE,
+            project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+            PRIMARY KEY (track_item_id, project_id)
+        );
+        CREATE INDEX idx_track_item_projects_project ON track_item_projects(project_id);
+
+        CREATE TABLE track_item_tags (
+            track_item_id INTEGER NOT NULL REFERENCES track_items(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (track_item_id, tag_id)
+        );
+        CREATE INDEX idx_track_item_tags_tag ON track_item_tags(tag_id);",
+    )
+}
+
+/// v5: ordered classification rules and per-item productivity
+fn migrate_v5_rules(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            position INTEGER NOT NULL DEFAULT 0,
+            conditions TEXT NOT NULL,
+            actions TEXT NOT NULL
+        );
+
+        ALTER TABLE track_items ADD COLUMN productivity TEXT;",
+    )
+}
+
+/// v6: per day, app and domain totals for fast long-range summaries
+///
+/// Triggers record the time ranges of changed app items in
+/// `rollup_dirty`; the affected days are rebuilt in the background and
+/// read from the items until then.
+/// Days are keyed by their local start in milliseconds.
+fn migrate_v6_daily_rollups(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE daily_rollups (
+            day INTEGER NOT NULL,
+            app TEXT NOT NULL,
+            domain TEXT NOT NULL DEFAULT '',
+            duration INTEGER NOT NULL,
+            item_count INTEGER NOT NULL,
+            PRIMARY KEY (day, app, domain)
+        ) WITHOUT ROWID;
+
+        CREATE TABLE rollup_dirty (
+            begin_date INTEGER NOT NULL,
+            end_date INTEGER NOT NULL
+        );
+
+        CREATE TRIGGER rollups_insert AFTER INSERT ON track_items
+        WHEN new.task_name = 'AppTrackItem' BEGIN
+            INSERT INTO rollup_dirty (begin_date, end_date)
+            VALUES (new.begin_date, new.end_date);
+        END;
+
+        CREATE TRIGGER rollups_delete AFTER DELETE ON track_items
+        WHEN old.task_name = 'AppTrackItem' BEGIN
+            INSERT INTO rollup_dirty (begin_date, end_date)
+            VALUES (old.begin_date, old.end_date);
+        END;
+
+        CREATE TRIGGER rollups_update
+        AFTER UPDATE OF app, domain, task_name, begin_date, end_date ON track_items
+        WHEN old.task_name = 'AppTrackItem' OR new.task_name = 'AppTrackItem' BEGIN
+            INSERT INTO rollup_dirty (begin_date, end_date)
+            VALUES (MIN(old.begin_date, new.begin_date), MAX(old.end_date, new.end_date));
+        END;
+
+        INSERT INTO rollup_dirty (begin_date, end_date)
+        SELECT MIN(begin_date), MAX(end_date) FROM track_items
+        WHERE task_name = 'AppTrackItem'
+        HAVING COUNT(*) > 0;",
+    )
+}
+
+/// v7: the manual timer that is running, if any
+///
+/// At most one row; it becomes a `LogTrackItem` when the timer stops.
+fn migrate_v7_log_timer(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE log_timer (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            title TEXT NOT NULL,
+            project_id INTEGER REFERENCES projects(id) ON DELETE SET NULL,
+            begin_date INTEGER NOT NULL
+        );",
+    )
+}
+
+/// v8: break reminders shown and what the user did about them
+fn migrate_v8_break_reminders(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE break_reminders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            shown_at INTEGER NOT NULL,
+            active_duration INTEGER NOT NULL,
+            outcome TEXT,
+            resolved_at INTEGER
+        );
+        CREATE INDEX idx_break_reminders_shown ON break_reminders(shown_at);",
+    )
+}
+
+/// v9: time goals and limits, and how each period went
+fn migrate_v9_goals(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            kind TEXT NOT NULL,
+            period TEXT NOT NULL,
+            target TEXT NOT NULL,
+            duration INTEGER NOT NULL,
+            weekdays TEXT NOT NULL DEFAULT '[]'
+        );
+
+        CREATE TABLE goal_history (
+            goal_id INTEGER NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+            period_start INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            progress INTEGER NOT NULL,
+            met INTEGER NOT NULL,
+            PRIMARY KEY (goal_id, period_start)
+        ) WITHOUT ROWID;",
+    )
+}
+
+/// v10: whether a project or tag link, an item's color or its
+/// productivity was set by a rule
+///
+/// Re-applying rules replaces these and keeps what was set by hand.
+fn migrate_v10_rule_labels(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE track_item_projects ADD COLUMN by_rule INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE track_item_tags ADD COLUMN by_rule INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE track_items ADD COLUMN color_by_rule INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE track_items ADD COLUMN productivity_by_rule INTEGER NOT NULL DEFAULT 0;",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_database_is_migrated_to_latest() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(has_column(&conn, "track_items", "domain").unwrap());
+    }
+
+    #[test]
+    fn test_legacy_database_without_domain_is_upgraded() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE track_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                app TEXT NOT NULL,
+                task_name TEXT NOT NULL,
+                title TEXT NOT NULL,
+                url TEXT,
+                color TEXT,
+                begin_date INTEGER NOT NULL,
+                end_date INTEGER NOT NULL
+            );
+            INSERT INTO track_items (app, task_name, title, begin_date, end_date)
+            VALUES ('Code', 'AppTrackItem', 'main.rs', 0, 1000);",
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        assert!(has_column(&conn, "track_items", "domain").unwrap());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM track_items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_newer_database_is_refused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!("PRAGMA user_version = {}", latest_version() + 1))
+            .unwrap();
+
+        assert!(run_migrations(&mut conn).is_err());
+    }
+}
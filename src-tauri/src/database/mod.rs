@@ -0,0 +1,670 @@
+pub mod attention;
+pub mod breaks;
+pub mod compaction;
+pub mod days;
+pub mod editing;
+pub mod encryption;
+pub mod goals;
+pub mod log_timer;
+pub mod migrations;
+pub mod pool;
+pub mod projects;
+pub mod rollups;
+pub mod rules;
+pub mod search;
+pub mod trends;
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Result, ToSql};
+use search::SearchQuery;
+
+pub use attention::{AttentionConfig, DayAttention, DeepWorkBlock, StretchStats};
+pub use breaks::{BreakOutcome, BreakStats};
+pub use compaction::{CompactionConfig, CompactionResult};
+pub use days::DayBoundaries;
+pub use editing::RangeChanges;
+pub use encryption::DatabaseKey;
+pub use goals::{Goal, GoalHistoryEntry, GoalKind, GoalPeriod, GoalTarget};
+pub use log_timer::LogTimer;
+pub use pool::{DatabasePool, PooledDatabase};
+pub use projects::{ItemLabels, Project, Tag};
+pub use rules::{Rule, RuleActions, RuleConditions, RuleMarks};
+use serde::{Deserialize, Serialize};
+pub use trends::{DayTotal, TrendStats, UsageDelta, UsageKey};
+
+/// Items shorter than this are not worth keeping (milliseconds)
+pub const MIN_ITEM_DURATION: i64 = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackItem {
+    pub id: Option<i64>,
+    pub app: String,
+    pub task_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub domain: Option<String>,
+    pub color: Option<String>,
+    pub begin_date: i64,
+    pub end_date: i64,
+    #[serde(default)]
+    pub productivity: Option<Productivity>,
+}
+
+/// How an item counts toward productive time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Productivity {
+    Productive,
+    Neutral,
+    Distracting,
+}
+
+impl Productivity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Productivity::Productive => "productive",
+            Productivity::Neutral => "neutral",
+            Productivity::Distracting => "distracting",
+        }
+    }
+}
+
+impl ToSql for Productivity {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Productivity {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "productive" => Ok(Productivity::Productive),
+            "neutral" => Ok(Productivity::Neutral),
+            "distracting" => Ok(Productivity::Distracting),
+            other => Err(FromSqlError::Other(
+                format!("unknown productivity: {}", other).into(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSetting {
+    pub id: Option<i64>,
+    pub name: String,
+    pub color: Option<String>,
+}
+
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    pub fn new(path: &str) -> Result<Self> {
+        Self::open(path, None)
+    }
+
+    /// Open a database, unlocking it with a key if it is encrypted
+    pub fn open(path: &str, key: Option<&DatabaseKey>) -> Result<Self> {
+        let mut conn = Connection::open(path)?;
+
+        if let Some(key) = key {
+            encryption::apply_key(&conn, key)?;
+        }
+
+        // Enable WAL mode for better concurrency; enforce link cleanup
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")?;
+
+        // Bring the schema up to date
+        migrations::run_migrations(&mut conn)?;
+
+        log::info!("Database initialized at: {}", path);
+
+        let db = Self { conn };
+
+        // Close items left open by a crash or power loss
+        let recovered = db.recover_open_items()?;
+        if recovered > 0 {
+            log::info!(
+                "Recovered {} item(s) left open by previous session",
+                recovered
+            );
+        }
+
+        Ok(db)
+    }
+
+    /// Open a read-only connection to a database the writer has set up
+    pub fn open_reader(path: &str, key: Option<&DatabaseKey>) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX
+                | OpenFlags::SQLITE_OPEN_URI,
+        )?;
+
+        if let Some(key) = key {
+            encryption::apply_key(&conn, key)?;
+        }
+
+        Ok(Self { conn })
+    }
+
+    /// Find all items for a specific day and task type
+    pub fn find_all_day_items(
+        &self,
+        from: i64,
+        to: i64,
+        task_name: &str,
+    ) -> Result<Vec<TrackItem>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, app, task_name, title, url, domain, color, begin_date, end_date, productivity
+             FROM track_items 
+             WHERE task_name = ?1 AND end_date > ?2 AND begin_date < ?3
+             ORDER BY begin_date ASC",
+        )?;
+
+        let items = stmt.query_map(params![task_name, from, to], |row| {
+            Ok(TrackItem {
+                id: Some(row.get(0)?),
+                app: row.get(1)?,
+                task_name: row.get(2)?,
+                title: row.get(3)?,
+                url: row.get(4)?,
+                domain: row.get(5)?,
+                color: row.get(6)?,
+                begin_date: row.get(7)?,
+                end_date: row.get(8)?,
+                productivity: row.get(9)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for item in items {
+            result.push(item?);
+        }
+
+        Ok(result)
+    }
+
+    /// Find items of a task type clipped to `[from, to)` and split at
+    /// day boundaries
+    ///
+    /// Pieces of one item share its id, so durations add up exactly across
+    /// days. Use `find_all_day_items` when items are edited.
+    pub fn find_clipped_items(
+        &self,
+        from: i64,
+        to: i64,
+        task_name: &str,
+        days: &DayBoundaries,
+    ) -> Result<Vec<TrackItem>> {
+        let items = self.find_all_day_items(from, to, task_name)?;
+
+        Ok(items
+            .iter()
+            .flat_map(|item| days.split_item(item, from, to))
+            .collect())
+    }
+
+    /// Create a new track item
+    pub fn create_track_item(&self, item: &TrackItem) -> Result<TrackItem> {
+        self.conn.execute(
+            "INSERT INTO track_items (app, task_name, title, url, domain, color, begin_date, end_date, productivity)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                item.app,
+                item.task_name,
+                item.title,
+                item.url,
+                item.domain,
+                item.color,
+                item.begin_date,
+                item.end_date,
+                item.productivity,
+            ],
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+
+        Ok(TrackItem {
+            id: Some(id),
+            ..item.clone()
+        })
+    }
+
+    /// Insert an item the tracker is still extending
+    ///
+    /// The row is written immediately so a crash loses at most one heartbeat
+    /// interval. `end_date` is provisional until `close_track_item`.
+    pub fn open_track_item(&self, item: &TrackItem) -> Result<TrackItem> {
+        self.conn.execute(
+            "INSERT INTO track_items (app, task_name, title, url, domain, color, begin_date, end_date, productivity, is_open)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 1)",
+            params![
+                item.app,
+                item.task_name,
+                item.title,
+                item.url,
+                item.domain,
+                item.color,
+                item.begin_date,
+                item.end_date,
+                item.productivity,
+            ],
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+
+        Ok(TrackItem {
+            id: Some(id),
+            ..item.clone()
+        })
+    }
+
+    /// Extend an open item's provisional end date
+    pub fn heartbeat_track_item(&self, id: i64, end_date: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE track_items SET end_date = ?1 WHERE id = ?2 AND is_open = 1",
+            params![end_date, id],
+        )?;
+        Ok(())
+    }
+
+    /// Close an open item at its final end date
+    ///
+    /// Items that ended up shorter than `MIN_ITEM_DURATION` are removed.
+    pub fn close_track_item(&self, id: i64, end_date: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM track_items WHERE id = ?1 AND ?2 - begin_date < ?3",
+            params![id, end_date, MIN_ITEM_DURATION],
+        )?;
+        self.conn.execute(
+            "UPDATE track_items SET end_date = ?1, is_open = 0 WHERE id = ?2",
+            params![end_date, id],
+        )?;
+        Ok(())
+    }
+
+    /// Close every item still marked open at its last heartbeat
+    ///
+    /// Only call this when no tracker is running against the database.
+    pub fn recover_open_items(&self) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM track_items WHERE is_open = 1 AND end_date - begin_date < ?1",
+            params![MIN_ITEM_DURATION],
+        )?;
+        self.conn
+            .execute("UPDATE track_items SET is_open = 0 WHERE is_open = 1", [])
+    }
+
+    /// Update an existing track item
+    ///
+    /// A color or productivity changed here no longer counts as set by a
+    /// rule.
+    pub fn update_track_item(&self, item: &TrackItem) -> Result<TrackItem> {
+        if let Some(id) = item.id {
+            self.conn.execute(
+                "UPDATE track_items 
+                 SET app = ?1, task_name = ?2, title = ?3, url = ?4, domain = ?5, color = ?6, begin_date = ?7, end_date = ?8, productivity = ?9,
+                     color_by_rule = CASE WHEN color IS ?6 THEN color_by_rule ELSE 0 END,
+                     productivity_by_rule = CASE WHEN productivity IS ?9 THEN productivity_by_rule ELSE 0 END
+                 WHERE id = ?10",
+                params![
+                    item.app,
+                    item.task_name,
+                    item.title,
+                    item.url,
+                    item.domain,
+                    item.color,
+                    item.begin_date,
+                    item.end_date,
+                    item.productivity,
+                    id,
+                ],
+            )?;
+        }
+
+        Ok(item.clone())
+    }
+
+    /// Update several track items in one transaction
+    pub fn update_track_items(&self, items: &[TrackItem]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for item in items {
+            self.update_track_item(item)?;
+        }
+        tx.commit()
+    }
+
+    /// Delete track items by IDs
+    pub fn delete_by_ids(&self, ids: &[i64]) -> Result<()> {
+        for id in ids {
+            self.conn
+                .execute("DELETE FROM track_items WHERE id = ?1", params![id])?;
+        }
+        Ok(())
+    }
+
+    /// Search track items
+    ///
+    /// `search_str` uses the syntax described on `SearchQuery`. Returns one
+    /// page of matches, newest first, together with the number and total
+    /// duration of all matches.
+    pub fn search_items(
+        &self,
+        from: i64,
+        to: i64,
+        task_name: Option<&str>,
+        search_str: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<TrackItem>, i64, i64)> {
+        let mut filter = String::from("end_date > ? AND begin_date < ?");
+        let mut args = vec![Value::from(from), Value::from(to)];
+
+        if let Some(tn) = task_name {
+            filter.push_str(" AND task_name = ?");
+            args.push(Value::from(tn.to_string()));
+        }
+
+        if let Some(query) = search_str.and_then(SearchQuery::parse) {
+            if let Some(include) = query.include {
+                filter.push_str(
+                    " AND id IN (SELECT rowid FROM track_items_fts WHERE track_items_fts MATCH ?)",
+                );
+                args.push(Value::from(include));
+            }
+            if let Some(exclude) = query.exclude {
+                filter.push_str(
+                    " AND id NOT IN (SELECT rowid FROM track_items_fts WHERE track_items_fts MATCH ?)",
+                );
+                args.push(Value::from(exclude));
+            }
+        }
+
+        // Durations are clipped to the range; the range is bound first
+        let (total, total_duration) = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(MIN(end_date, ?) - MAX(begin_date, ?)), 0)
+                 FROM track_items WHERE {}",
+                filter
+            ),
+            params_from_iter([Value::from(to), Value::from(from)].iter().chain(&args)),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, app, task_name, title, url, domain, color, begin_date, end_date, productivity
+             FROM track_items WHERE {}
+             ORDER BY begin_date DESC LIMIT ? OFFSET ?",
+            filter
+        ))?;
+
+        args.push(Value::from(limit));
+        args.push(Value::from(offset));
+
+        let items = stmt.query_map(params_from_iter(&args), |row| {
+            Ok(TrackItem {
+                id: Some(row.get(0)?),
+                app: row.get(1)?,
+                task_name: row.get(2)?,
+                title: row.get(3)?,
+                url: row.get(4)?,
+                domain: row.get(5)?,
+                color: row.get(6)?,
+                begin_date: row.get(7)?,
+                end_date: row.get(8)?,
+                productivity: row.get(9)?,
+            })
+        })?;
+
+        let result = items.collect::<Result<Vec<_>>>()?;
+
+        Ok((result, total, total_duration))
+    }
+
+    /// Update track item color
+    pub fn update_track_item_color(&self, app_name: &str, color: &str) -> Result<()> {
+        // Update existing items
+        self.conn.execute(
+            "UPDATE track_items SET color = ?1, color_by_rule = 0 WHERE app = ?2",
+            params![color, app_name],
+        )?;
+
+        // Upsert app setting
+        self.conn.execute(
+            "INSERT INTO app_settings (name, color) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET color = ?2",
+            params![app_name, color],
+        )?;
+
+        Ok(())
+    }
+
+    /// Get app color
+    pub fn get_app_color(&self, app_name: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT color FROM app_settings WHERE name = ?1")?;
+
+        let result = stmt.query_row(params![app_name], |row| row.get(0));
+
+        match result {
+            Ok(color) => Ok(Some(color)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get application settings
+    pub fn get_settings(&self) -> Result<crate::commands::AppSettings> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT json_data FROM settings WHERE name = 'app_settings'")?;
+
+        let result: std::result::Result<String, _> = stmt.query_row([], |row| row.get(0));
+
+        match result {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                Ok(crate::commands::AppSettings::default())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Save application settings
+    pub fn save_settings(&self, settings: &crate::commands::AppSettings) -> Result<()> {
+        let json = serde_json::to_string(settings)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO settings (id, name, json_data) 
+             VALUES ((SELECT id FROM settings WHERE name = 'app_settings'), 'app_settings', ?1)",
+            params![json],
+        )?;
+
+        Ok(())
+    }
+
+    /// Get all tracked apps with their colors and total time
+    pub fn get_tracked_apps(&self) -> Result<Vec<crate::commands::TrackedApp>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT app, color, SUM(end_date - begin_date) as total_time
+             FROM track_items
+             WHERE task_name = 'AppTrackItem'
+             GROUP BY app
+             ORDER BY total_time DESC",
+        )?;
+
+        let items = stmt.query_map([], |row| {
+            Ok(crate::commands::TrackedApp {
+                name: row.get(0)?,
+                color: row.get(1)?,
+                total_time: row.get(2)?,
+            })
+        })?;
+
+        items.collect()
+    }
+
+    /// Clear data before a specific date
+    pub fn clear_data_before(&self, before_date: i64) -> Result<i64> {
+        let deleted = self.conn.execute(
+            "DELETE FROM track_items WHERE end_date < ?1",
+            params![before_date],
+        )?;
+
+        Ok(deleted as i64)
+    }
+}
+
+/// Track item fixture for tests: "Code" editing main.rs unless changed
+#[cfg(test)]
+pub(crate) struct TestItem(TrackItem);
+
+#[cfg(test)]
+impl TestItem {
+    pub fn new(begin_date: i64, end_date: i64) -> Self {
+        Self(TrackItem {
+            id: None,
+            app: "Code".to_string(),
+            task_name: "AppTrackItem".to_string(),
+            title: "main.rs".to_string(),
+            url: None,
+            domain: None,
+            color: None,
+            begin_date,
+            end_date,
+            productivity: None,
+        })
+    }
+
+    pub fn id(mut self, id: i64) -> Self {
+        self.0.id = Some(id);
+        self
+    }
+
+    pub fn app(mut self, app: &str) -> Self {
+        self.0.app = app.to_string();
+        self
+    }
+
+    pub fn task_name(mut self, task_name: &str) -> Self {
+        self.0.task_name = task_name.to_string();
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.0.title = title.to_string();
+        self
+    }
+
+    /// Open a page of `domain`
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.0.url = Some(format!("https://{}/", domain));
+        self.0.domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn color(mut self, color: &str) -> Self {
+        self.0.color = Some(color.to_string());
+        self
+    }
+
+    pub fn productivity(mut self, productivity: Productivity) -> Self {
+        self.0.productivity = Some(productivity);
+        self
+    }
+
+    pub fn build(self) -> TrackItem {
+        self.0
+    }
+
+    /// Insert the item and return its id
+    pub fn create(self, db: &Database) -> i64 {
+        db.create_track_item(&self.0).unwrap().id.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_items_are_recovered_at_last_heartbeat() {
+        let db = Database::new(":memory:").unwrap();
+        let open = db.open_track_item(&TestItem::new(0, 0).build()).unwrap();
+        db.heartbeat_track_item(open.id.unwrap(), 15_000).unwrap();
+
+        assert_eq!(db.recover_open_items().unwrap(), 1);
+        assert_eq!(db.recover_open_items().unwrap(), 0);
+
+        let items = db.find_all_day_items(0, 60_000, "AppTrackItem").unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].end_date, 15_000);
+    }
+
+    #[test]
+    fn test_short_open_items_are_discarded() {
+        let db = Database::new(":memory:").unwrap();
+        let closed = db.open_track_item(&TestItem::new(0, 0).build()).unwrap();
+        db.close_track_item(closed.id.unwrap(), 500).unwrap();
+        db.open_track_item(&TestItem::new(1_000, 1_000).build())
+            .unwrap();
+
+        assert_eq!(db.recover_open_items().unwrap(), 0);
+        assert!(db
+            .find_all_day_items(0, 60_000, "AppTrackItem")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_counts_all_matches() {
+        let db = Database::new(":memory:").unwrap();
+        for i in 0..5 {
+            TestItem::new(i * 10_000, i * 10_000 + 2_000)
+                .title(&format!("Pull request #{}", i))
+                .create(&db);
+        }
+        TestItem::new(60_000, 61_000)
+            .title("Pull request draft")
+            .create(&db);
+        TestItem::new(70_000, 71_000).create(&db);
+
+        let (page, total, duration) = db
+            .search_items(0, 100_000, None, Some("pull -draft"), 2, 2)
+            .unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].begin_date, 20_000);
+        assert_eq!(total, 5);
+        assert_eq!(duration, 10_000);
+
+        let (_, total, _) = db
+            .search_items(0, 100_000, Some("AppTrackItem"), Some("app:code"), 50, 0)
+            .unwrap();
+        assert_eq!(total, 7);
+    }
+
+    #[test]
+    fn test_search_index_follows_updates() {
+        let db = Database::new(":memory:").unwrap();
+        let mut it = db
+            .create_track_item(&TestItem::new(0, 2_000).build())
+            .unwrap();
+        it.title = "lib.rs".to_string();
+        db.update_track_item(&it).unwrap();
+
+        let search = |s| db.search_items(0, 10_000, None, Some(s), 50, 0).unwrap().1;
+        assert_eq!(search("main"), 0);
+        assert_eq!(search("title:lib"), 1);
+
+        db.delete_by_ids(&[it.id.unwrap()]).unwrap();
+        assert_eq!(search("lib"), 0);
+    }
+}
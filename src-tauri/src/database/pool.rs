@@ -0,0 +1,172 @@
+// Connection Pool Module
+// 数据库连接池模块
+
+use super::{Database, DatabaseKey};
+use rusqlite::Result;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Read-only connections kept next to the writer
+const READER_COUNT: usize = 3;
+
+/// A connection borrowed from a `DatabasePool`
+pub enum PooledDatabase<'a> {
+    Writer(MutexGuard<'a, Database>),
+    /// Only handed out for slots that hold a connection
+    Reader(MutexGuard<'a, Option<Database>>),
+}
+
+impl Deref for PooledDatabase<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        match self {
+            PooledDatabase::Writer(db) => db,
+            PooledDatabase::Reader(db) => db.as_ref().expect("empty reader slot"),
+        }
+    }
+}
+
+/// The app's connections to its database: one writer and a few read-only
+/// readers
+///
+/// Under WAL readers and the writer do not block each other, so long
+/// report queries and the tracker's writes can run at the same time.
+/// In-memory databases cannot be shared, so their reads use the writer.
+pub struct DatabasePool {
+    writer: Mutex<Database>,
+    readers: Vec<Mutex<Option<Database>>>,
+    next_reader: AtomicUsize,
+}
+
+impl DatabasePool {
+    /// Open the writer, run migrations, then open the readers
+    pub fn open(path: &str, key: Option<&DatabaseKey>) -> Result<Self> {
+        let writer = Database::open(path, key)?;
+        let readers = open_readers(path, key)?;
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+            readers: readers.into_iter().map(Mutex::new).collect(),
+            next_reader: AtomicUsize::new(0),
+        })
+    }
+
+    /// A connection for work that writes
+    pub fn write(&self) -> std::result::Result<PooledDatabase<'_>, String> {
+        self.writer
+            .lock()
+            .map(PooledDatabase::Writer)
+            .map_err(|e| e.to_string())
+    }
+
+    /// A connection for work that only reads
+    ///
+    /// Takes an idle reader if there is one, otherwise waits for one.
+    pub fn read(&self) -> std::result::Result<PooledDatabase<'_>, String> {
+        for slot in &self.readers {
+            if let Ok(reader) = slot.try_lock() {
+                if reader.is_some() {
+                    return Ok(PooledDatabase::Reader(reader));
+                }
+            }
+        }
+
+        if !self.readers.is_empty() {
+            let index = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+            let reader = self.readers[index].lock().map_err(|e| e.to_string())?;
+            if reader.is_some() {
+                return Ok(PooledDatabase::Reader(reader));
+            }
+        }
+
+        self.write()
+    }
+
+    /// Replace every connection with new ones to `path`
+    ///
+    /// Open `":memory:"` to close the file, e.g. before it is swapped out.
+    pub fn reopen(&self, path: &str, key: Option<&DatabaseKey>) -> std::result::Result<(), String> {
+        // Readers first, so none outlives the writer's file
+        for slot in &self.readers {
+            *slot.lock().map_err(|e| e.to_string())? = None;
+        }
+        *self.writer.lock().map_err(|e| e.to_string())? =
+            Database::open(path, key).map_err(|e| e.to_string())?;
+
+        let readers = open_readers(path, key).map_err(|e| e.to_string())?;
+        for (slot, reader) in self.readers.iter().zip(readers) {
+            *slot.lock().map_err(|e| e.to_string())? = reader;
+        }
+        Ok(())
+    }
+}
+
+fn open_readers(path: &str, key: Option<&DatabaseKey>) -> Result<Vec<Option<Database>>> {
+    (0..READER_COUNT)
+        .map(|_| {
+            if path == ":memory:" {
+                Ok(None)
+            } else {
+                Database::open_reader(path, key).map(Some)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    #[test]
+    fn test_readers_see_writes() {
+        let path = std::env::temp_dir().join(format!("timlyzer-pool-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let pool = DatabasePool::open(path.to_str().unwrap(), None).unwrap();
+
+        // A long read does not hold up the writer
+        let reader = pool.read().unwrap();
+        assert!(matches!(reader, PooledDatabase::Reader(_)));
+        pool.write()
+            .unwrap()
+            .create_track_item(&TestItem::new(0, 1_000).build())
+            .unwrap();
+        assert_eq!(
+            reader
+                .find_all_day_items(0, 1_000, "AppTrackItem")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Readers cannot write
+        assert!(reader
+            .create_track_item(&TestItem::new(0, 1_000).build())
+            .is_err());
+        drop(reader);
+
+        pool.reopen(":memory:", None).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_in_memory_reads_use_writer() {
+        let pool = DatabasePool::open(":memory:", None).unwrap();
+        pool.write()
+            .unwrap()
+            .create_track_item(&TestItem::new(0, 1_000).build())
+            .unwrap();
+
+        let reader = pool.read().unwrap();
+        assert!(matches!(reader, PooledDatabase::Writer(_)));
+        assert_eq!(
+            reader
+                .find_all_day_items(0, 1_000, "AppTrackItem")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,482 @@
+// Projects and Tags Module
+// 项目与标签模块
+
+use super::{Database, Goal, GoalTarget, Rule, RuleActions};
+use rusqlite::{params, Result, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    pub archived: bool,
+    pub created_at: i64,
+}
+
+impl Project {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            color: row.get(2)?,
+            archived: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+}
+
+/// Projects and tags linked to one track item
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemLabels {
+    pub item_id: i64,
+    pub project_ids: Vec<i64>,
+    pub tag_ids: Vec<i64>,
+}
+
+impl Database {
+    /// List projects, optionally including archived ones
+    pub fn get_projects(&self, include_archived: bool) -> Result<Vec<Project>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, color, archived, created_at FROM projects
+             WHERE archived = 0 OR ?1
+             ORDER BY name COLLATE NOCASE",
+        )?;
+
+        let projects = stmt.query_map(params![include_archived], Project::from_row)?;
+        projects.collect()
+    }
+
+    /// Create a project; names must be unique
+    pub fn create_project(&self, name: &str, color: Option<&str>) -> Result<Project> {
+        let created_at = chrono::Utc::now().timestamp_millis();
+        self.conn.execute(
+            "INSERT INTO projects (name, color, created_at) VALUES (?1, ?2, ?3)",
+            params![name, color, created_at],
+        )?;
+
+        Ok(Project {
+            id: self.conn.last_insert_rowid(),
+            name: name.to_string(),
+            color: color.map(str::to_string),
+            archived: false,
+            created_at,
+        })
+    }
+
+    /// Rename a project
+    pub fn rename_project(&self, id: i64, name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE projects SET name = ?1 WHERE id = ?2",
+            params![name, id],
+        )?;
+        Ok(())
+    }
+
+    /// Archive or unarchive a project
+    ///
+    /// Archived projects keep their items but are hidden from pickers.
+    pub fn set_project_archived(&self, id: i64, archived: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE projects SET archived = ?1 WHERE id = ?2",
+            params![archived, id],
+        )?;
+        Ok(())
+    }
+
+    /// Move every item of the source projects to the target and delete the
+    /// sources
+    ///
+    /// Rules, goals and the running log timer that refer to a source are
+    /// pointed at the target.
+    pub fn merge_projects(&self, source_ids: &[i64], target_id: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let sources: Vec<i64> = source_ids
+            .iter()
+            .copied()
+            .filter(|&id| id != target_id)
+            .collect();
+
+        for rule in self.get_rules()? {
+            if rule
+                .actions
+                .project_id
+                .is_some_and(|id| sources.contains(&id))
+            {
+                let actions = RuleActions {
+                    project_id: Some(target_id),
+                    ..rule.actions.clone()
+                };
+                self.save_rule(&Rule { actions, ..rule })?;
+            }
+        }
+        for goal in self.get_goals()? {
+            if matches!(goal.target, GoalTarget::Project(id) if sources.contains(&id)) {
+                self.save_goal(&Goal {
+                    target: GoalTarget::Project(target_id),
+                    ..goal
+                })?;
+            }
+        }
+
+        for &source_id in &sources {
+            tx.execute(
+                "INSERT OR IGNORE INTO track_item_projects (track_item_id, project_id, by_rule)
+                 SELECT track_item_id, ?1, by_rule FROM track_item_projects WHERE project_id = ?2",
+                params![target_id, source_id],
+            )?;
+            tx.execute(
+                "UPDATE log_timer SET project_id = ?1 WHERE project_id = ?2",
+                params![target_id, source_id],
+            )?;
+            tx.execute("DELETE FROM projects WHERE id = ?1", params![source_id])?;
+        }
+
+        tx.commit()
+    }
+
+    /// Link track items to a project
+    ///
+    /// Links a rule made become manual, so re-applying rules keeps them.
+    pub fn assign_project(&self, project_id: i64, item_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for id in item_ids {
+            tx.execute(
+                "INSERT INTO track_item_projects (track_item_id, project_id) VALUES (?1, ?2)
+                 ON CONFLICT(track_item_id, project_id) DO UPDATE SET by_rule = 0",
+                params![id, project_id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Unlink track items from a project
+    pub fn unassign_project(&self, project_id: i64, item_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for id in item_ids {
+            tx.execute(
+                "DELETE FROM track_item_projects WHERE track_item_id = ?1 AND project_id = ?2",
+                params![id, project_id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// List all tags
+    pub fn get_tags(&self) -> Result<Vec<Tag>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, color FROM tags ORDER BY name COLLATE NOCASE")?;
+
+        let tags = stmt.query_map([], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                color: row.get(2)?,
+            })
+        })?;
+        tags.collect()
+    }
+
+    /// Create a tag; names must be unique
+    pub fn create_tag(&self, name: &str, color: Option<&str>) -> Result<Tag> {
+        self.conn.execute(
+            "INSERT INTO tags (name, color) VALUES (?1, ?2)",
+            params![name, color],
+        )?;
+
+        Ok(Tag {
+            id: self.conn.last_insert_rowid(),
+            name: name.to_string(),
+            color: color.map(str::to_string),
+        })
+    }
+
+    /// Delete a tag and its links
+    ///
+    /// Rules stop adding the tag and goals on it are deleted.
+    pub fn delete_tag(&self, id: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        for rule in self.get_rules()? {
+            if rule.actions.tag_ids.contains(&id) {
+                let actions = RuleActions {
+                    tag_ids: rule
+                        .actions
+                        .tag_ids
+                        .iter()
+                        .copied()
+                        .filter(|&tag_id| tag_id != id)
+                        .collect(),
+                    ..rule.actions.clone()
+                };
+                self.save_rule(&Rule { actions, ..rule })?;
+            }
+        }
+        for goal in self.get_goals()? {
+            if let (Some(goal_id), GoalTarget::Tag(tag_id)) = (goal.id, &goal.target) {
+                if *tag_id == id {
+                    self.delete_goal(goal_id)?;
+                }
+            }
+        }
+
+        tx.execute("DELETE FROM tags WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    /// Add a tag to track items
+    ///
+    /// Tags a rule added become manual, so re-applying rules keeps them.
+    pub fn tag_items(&self, tag_id: i64, item_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for id in item_ids {
+            tx.execute(
+                "INSERT INTO track_item_tags (track_item_id, tag_id) VALUES (?1, ?2)
+                 ON CONFLICT(track_item_id, tag_id) DO UPDATE SET by_rule = 0",
+                params![id, tag_id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Remove a tag from track items
+    pub fn untag_items(&self, tag_id: i64, item_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for id in item_ids {
+            tx.execute(
+                "DELETE FROM track_item_tags WHERE track_item_id = ?1 AND tag_id = ?2",
+                params![id, tag_id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Get the projects and tags of track items
+    pub fn get_item_labels(&self, item_ids: &[i64]) -> Result<Vec<ItemLabels>> {
+        let mut projects = self
+            .conn
+            .prepare("SELECT project_id FROM track_item_projects WHERE track_item_id = ?1")?;
+        let mut tags = self
+            .conn
+            .prepare("SELECT tag_id FROM track_item_tags WHERE track_item_id = ?1")?;
+
+        item_ids
+            .iter()
+            .map(|&item_id| {
+                Ok(ItemLabels {
+                    item_id,
+                    project_ids: projects
+                        .query_map(params![item_id], |row| row.get(0))?
+                        .collect::<Result<_>>()?,
+                    tag_ids: tags
+                        .query_map(params![item_id], |row| row.get(0))?
+                        .collect::<Result<_>>()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Total app time per project in a time range, clipped to the range
+    ///
+    /// Items without a project are reported under `None`. An item linked
+    /// to several projects counts toward each of them.
+    pub fn get_project_durations(&self, from: i64, to: i64) -> Result<Vec<(Option<Project>, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.name, p.color, p.archived, p.created_at,
+                    SUM(MIN(t.end_date, ?2) - MAX(t.begin_date, ?1)) AS total_duration
+             FROM track_items t
+             LEFT JOIN track_item_projects l ON l.track_item_id = t.id
+             LEFT JOIN projects p ON p.id = l.project_id
+             WHERE t.task_name = 'AppTrackItem' AND t.end_date > ?1 AND t.begin_date < ?2
+             GROUP BY p.id
+             ORDER BY total_duration DESC",
+        )?;
+
+        let rows = stmt.query_map(params![from, to], |row| {
+            let project = match row.get::<_, Option<i64>>(0)? {
+                Some(_) => Some(Project::from_row(row)?),
+                None => None,
+            };
+            Ok((project, row.get(5)?))
+        })?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    #[test]
+    fn test_project_durations() {
+        let db = Database::new(":memory:").unwrap();
+        let a = TestItem::new(0, 1_000).create(&db);
+        let b = TestItem::new(1_000, 4_000).create(&db);
+        TestItem::new(4_000, 6_000).create(&db);
+
+        let acme = db.create_project("Acme", None).unwrap();
+        db.assign_project(acme.id, &[a, b]).unwrap();
+        db.assign_project(acme.id, &[a]).unwrap();
+
+        let durations = db.get_project_durations(0, 10_000).unwrap();
+        assert_eq!(durations.len(), 2);
+        assert_eq!(durations[0].0.as_ref().unwrap().name, "Acme");
+        assert_eq!(durations[0].1, 4_000);
+        assert!(durations[1].0.is_none());
+        assert_eq!(durations[1].1, 2_000);
+    }
+
+    #[test]
+    fn test_merge_projects() {
+        let db = Database::new(":memory:").unwrap();
+        let a = TestItem::new(0, 1_000).create(&db);
+        let b = TestItem::new(1_000, 2_000).create(&db);
+
+        let old = db.create_project("Old", None).unwrap();
+        let new = db.create_project("New", None).unwrap();
+        db.assign_project(old.id, &[a, b]).unwrap();
+        db.assign_project(new.id, &[a]).unwrap();
+
+        db.merge_projects(&[old.id, new.id], new.id).unwrap();
+
+        let projects = db.get_projects(true).unwrap();
+        assert_eq!(projects.len(), 1);
+        let labels = db.get_item_labels(&[a, b]).unwrap();
+        assert_eq!(labels[0].project_ids, vec![new.id]);
+        assert_eq!(labels[1].project_ids, vec![new.id]);
+    }
+
+    #[test]
+    fn test_merge_repoints_references() {
+        let db = Database::new(":memory:").unwrap();
+        let old = db.create_project("Old", None).unwrap();
+        let new = db.create_project("New", None).unwrap();
+        let actions = RuleActions {
+            project_id: Some(old.id),
+            color: Some("#ff0000".to_string()),
+            ..Default::default()
+        };
+        db.save_rule(&Rule {
+            id: None,
+            name: "Code".to_string(),
+            enabled: true,
+            position: 0,
+            conditions: Default::default(),
+            actions: actions.clone(),
+        })
+        .unwrap();
+        db.save_goal(&Goal {
+            id: None,
+            name: "Old work".to_string(),
+            enabled: true,
+            kind: crate::database::GoalKind::Goal,
+            period: crate::database::GoalPeriod::Day,
+            target: GoalTarget::Project(old.id),
+            duration: 1_000,
+            weekdays: vec![],
+        })
+        .unwrap();
+        db.start_log_timer("Call", Some(old.id), 0).unwrap();
+
+        db.merge_projects(&[old.id], new.id).unwrap();
+
+        let rule = &db.get_rules().unwrap()[0];
+        assert_eq!(rule.actions.project_id, Some(new.id));
+        assert_eq!(rule.actions.color, actions.color);
+        assert_eq!(
+            db.get_goals().unwrap()[0].target,
+            GoalTarget::Project(new.id)
+        );
+        assert_eq!(
+            db.get_log_timer().unwrap().unwrap().project_id,
+            Some(new.id)
+        );
+
+        // Newly tracked items still get the rule's actions
+        let a = TestItem::new(0, 1_000).create(&db);
+        db.apply_rule_actions(a, &rule.actions).unwrap();
+        let labels = db.get_item_labels(&[a]).unwrap();
+        assert_eq!(labels[0].project_ids, vec![new.id]);
+    }
+
+    #[test]
+    fn test_delete_tag_strips_references() {
+        let db = Database::new(":memory:").unwrap();
+        let kept = db.create_tag("Deep", None).unwrap();
+        let deleted = db.create_tag("Meeting", None).unwrap();
+        let actions = RuleActions {
+            tag_ids: vec![kept.id, deleted.id],
+            ..Default::default()
+        };
+        db.save_rule(&Rule {
+            id: None,
+            name: "Calls".to_string(),
+            enabled: true,
+            position: 0,
+            conditions: Default::default(),
+            actions,
+        })
+        .unwrap();
+        db.save_goal(&Goal {
+            id: None,
+            name: "Fewer meetings".to_string(),
+            enabled: true,
+            kind: crate::database::GoalKind::Limit,
+            period: crate::database::GoalPeriod::Day,
+            target: GoalTarget::Tag(deleted.id),
+            duration: 1_000,
+            weekdays: vec![],
+        })
+        .unwrap();
+
+        db.delete_tag(deleted.id).unwrap();
+
+        let rule = &db.get_rules().unwrap()[0];
+        assert_eq!(rule.actions.tag_ids, vec![kept.id]);
+        assert!(db.rule_labels_exist(&rule.actions).unwrap());
+        assert!(db.get_goals().unwrap().is_empty());
+
+        // The rule still classifies new items
+        let a = TestItem::new(0, 1_000).create(&db);
+        db.apply_rule_actions(a, &rule.actions).unwrap();
+        assert_eq!(db.get_item_labels(&[a]).unwrap()[0].tag_ids, vec![kept.id]);
+    }
+
+    #[test]
+    fn test_links_are_removed_with_items() {
+        let db = Database::new(":memory:").unwrap();
+        let a = TestItem::new(0, 1_000).create(&db);
+        let tag = db.create_tag("billable", None).unwrap();
+        db.tag_items(tag.id, &[a]).unwrap();
+
+        db.delete_by_ids(&[a]).unwrap();
+
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM track_item_tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_archived_projects_are_hidden() {
+        let db = Database::new(":memory:").unwrap();
+        let project = db.create_project("Acme", Some("#ff0000")).unwrap();
+        db.set_project_archived(project.id, true).unwrap();
+
+        assert!(db.get_projects(false).unwrap().is_empty());
+        assert!(db.get_projects(true).unwrap()[0].archived);
+    }
+}
@@ -0,0 +1,388 @@
+// Daily Rollups Module
+// 每日汇总统计模块
+
+use super::days::DayBoundaries;
+use super::Database;
+use rusqlite::{params, OptionalExtension, Result};
+use std::collections::BTreeSet;
+
+/// A time range cut into whole days, served from the rollups, and the
+/// partial days (and days not rebuilt yet) on either side, aggregated from
+/// the items
+///
+/// Bound as `?1` = from, `?2`..`?3` = whole days, `?4` = to.
+struct UsageRange {
+    from: i64,
+    days_from: i64,
+    days_to: i64,
+    to: i64,
+}
+
+impl UsageRange {
+    fn new(from: i64, to: i64, bounds: &DayBoundaries) -> Self {
+        let first = bounds.day_start(from);
+        let days_from = if first == from {
+            from
+        } else {
+            bounds.next_day(from)
+        };
+        let days_to = bounds.day_start(to);
+
+        if days_from < days_to {
+            Self {
+                from,
+                days_from,
+                days_to,
+                to,
+            }
+        } else {
+            Self {
+                from,
+                days_from: to,
+                days_to: to,
+                to,
+            }
+        }
+    }
+}
+
+/// Rows of `key, duration, items` for app usage in a `UsageRange`, with
+/// item durations clipped to the range
+fn usage_rows_sql(key: &str, filter: &str) -> String {
+    format!(
+        "SELECT {key} AS key, duration, item_count AS items FROM daily_rollups
+         WHERE day >= ?2 AND day < ?3 {filter}
+         UNION ALL
+         SELECT {key}, MIN(end_date, ?2) - MAX(begin_date, ?1), 1 FROM track_items
+         WHERE task_name = 'AppTrackItem' AND end_date > ?1 AND begin_date < ?2 {filter}
+         UNION ALL
+         SELECT {key}, MIN(end_date, ?4) - MAX(begin_date, ?3), 1 FROM track_items
+         WHERE task_name = 'AppTrackItem' AND end_date > ?3 AND begin_date < ?4 {filter}",
+        key = key,
+        filter = filter,
+    )
+}
+
+impl Database {
+    /// Cut `[from, to)` into days served from the rollups and the rest
+    ///
+    /// The rollups end at the first day changed since the last refresh, or
+    /// are skipped when they were built with other boundaries.
+    fn usage_range(&self, from: i64, to: i64, bounds: &DayBoundaries) -> Result<UsageRange> {
+        let mut range = UsageRange::new(from, to, bounds);
+        if range.days_from == range.days_to {
+            return Ok(range);
+        }
+
+        let built_with: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT json_data FROM settings WHERE name = 'rollup_boundaries'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if built_with != Some(bounds.label()) {
+            range.days_to = range.days_from;
+            return Ok(range);
+        }
+
+        let first_dirty: Option<i64> = self.conn.query_row(
+            "SELECT MIN(begin_date) FROM rollup_dirty WHERE end_date >= ?1 AND begin_date < ?2",
+            params![range.days_from, range.days_to],
+            |row| row.get(0),
+        )?;
+        if let Some(begin) = first_dirty {
+            range.days_to = bounds
+                .day_start(begin)
+                .clamp(range.days_from, range.days_to);
+        }
+        Ok(range)
+    }
+
+    /// Rebuild the rollups of every day touched since the last refresh
+    ///
+    /// Triggers on `track_items` record the changed time ranges. When the
+    /// day boundaries changed since the rollups were built, every day is
+    /// rebuilt. Returns the number of days rebuilt.
+    pub fn refresh_rollups(&self, bounds: &DayBoundaries) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let label = bounds.label();
+        let built_with: Option<String> = tx
+            .query_row(
+                "SELECT json_data FROM settings WHERE name = 'rollup_boundaries'",
+                [],
+                |row| row.get(0),
+            )
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+        if built_with.as_deref() != Some(label.as_str()) {
+            self.reset_rollups()?;
+            tx.execute(
+                "INSERT INTO settings (name, json_data) VALUES ('rollup_boundaries', ?1)
+                 ON CONFLICT(name) DO UPDATE SET json_data = ?1",
+                params![label],
+            )?;
+        }
+
+        let mut days = BTreeSet::new();
+        {
+            let mut stmt = tx.prepare("SELECT begin_date, end_date FROM rollup_dirty")?;
+            let ranges = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)))?;
+            for range in ranges {
+                let (begin, end): (i64, i64) = range?;
+                let mut day = bounds.day_start(begin);
+                loop {
+                    days.insert(day);
+                    day = bounds.next_day(day);
+                    if day >= end {
+                        break;
+                    }
+                }
+            }
+        }
+
+        for &day in &days {
+            tx.execute("DELETE FROM daily_rollups WHERE day = ?1", params![day])?;
+            tx.execute(
+                "INSERT INTO daily_rollups (day, app, domain, duration, item_count)
+                 SELECT ?1, app, COALESCE(domain, ''),
+                        SUM(MIN(end_date, ?2) - MAX(begin_date, ?1)), COUNT(*)
+                 FROM track_items
+                 WHERE task_name = 'AppTrackItem' AND end_date > ?1 AND begin_date < ?2
+                 GROUP BY app, COALESCE(domain, '')",
+                params![day, bounds.next_day(day)],
+            )?;
+        }
+        tx.execute("DELETE FROM rollup_dirty", [])?;
+
+        tx.commit()?;
+        Ok(days.len())
+    }
+
+    /// Drop all rollups and mark the whole history for the next refresh
+    pub fn reset_rollups(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "DELETE FROM daily_rollups;
+             DELETE FROM rollup_dirty;
+             INSERT INTO rollup_dirty (begin_date, end_date)
+             SELECT MIN(begin_date), MAX(end_date) FROM track_items
+             WHERE task_name = 'AppTrackItem'
+             HAVING COUNT(*) > 0;",
+        )
+    }
+
+    /// App time in a range, clipped to it, with each app's color
+    ///
+    /// Reads whole days from the rollups where they are up to date for
+    /// these boundaries.
+    pub fn get_app_durations(
+        &self,
+        from: i64,
+        to: i64,
+        bounds: &DayBoundaries,
+    ) -> Result<Vec<(String, Option<String>, i64)>> {
+        let range = self.usage_range(from, to, bounds)?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT u.key, s.color, SUM(u.duration) AS total_duration
+             FROM ({}) u
+             LEFT JOIN app_settings s ON s.name = u.key
+             GROUP BY u.key
+             ORDER BY total_duration DESC",
+            usage_rows_sql("app", "")
+        ))?;
+
+        let rows = stmt.query_map(
+            params![range.from, range.days_from, range.days_to, range.to],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        rows.collect()
+    }
+
+    /// Time and item count per domain of one app in a range, clipped to it
+    ///
+    /// Items without a domain are reported under `None`. Reads whole days
+    /// from the rollups where they are up to date for these boundaries.
+    pub fn get_domain_durations(
+        &self,
+        from: i64,
+        to: i64,
+        app: &str,
+        bounds: &DayBoundaries,
+    ) -> Result<Vec<(Option<String>, i64, i64)>> {
+        let range = self.usage_range(from, to, bounds)?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT u.key, SUM(u.duration) AS total_duration, SUM(u.items)
+             FROM ({}) u
+             GROUP BY u.key
+             ORDER BY total_duration DESC",
+            usage_rows_sql("COALESCE(domain, '')", "AND app = ?5")
+        ))?;
+
+        let rows = stmt.query_map(
+            params![range.from, range.days_from, range.days_to, range.to, app],
+            |row| {
+                let domain: String = row.get(0)?;
+                Ok((
+                    Some(domain).filter(|d| !d.is_empty()),
+                    row.get(1)?,
+                    row.get(2)?,
+                ))
+            },
+        )?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    const HOUR: i64 = 3_600_000;
+
+    /// Local midnight a few days after the epoch
+    fn midnight(days: i64) -> i64 {
+        let bounds = DayBoundaries::default();
+        let mut day = bounds.day_start(10 * 24 * HOUR);
+        for _ in 0..days {
+            day = bounds.next_day(day);
+        }
+        day
+    }
+
+    #[test]
+    fn test_durations_are_clipped_to_the_range() {
+        let db = Database::new(":memory:").unwrap();
+        let day = midnight(0);
+        TestItem::new(day - HOUR, day + HOUR).create(&db);
+        TestItem::new(day + 2 * HOUR, day + 3 * HOUR).create(&db);
+        db.refresh_rollups(&DayBoundaries::default()).unwrap();
+
+        let apps = db
+            .get_app_durations(day, day + 10 * HOUR, &DayBoundaries::default())
+            .unwrap();
+        assert_eq!(apps, vec![("Code".to_string(), None, 2 * HOUR)]);
+    }
+
+    #[test]
+    fn test_rollups_match_item_totals() {
+        let db = Database::new(":memory:").unwrap();
+        let (first, second, third) = (midnight(0), midnight(1), midnight(2));
+        TestItem::new(first + HOUR, first + 3 * HOUR)
+            .app("Safari")
+            .domain("github.com")
+            .create(&db);
+        TestItem::new(second - HOUR, second + HOUR)
+            .app("Safari")
+            .create(&db);
+        TestItem::new(second + 5 * HOUR, second + 6 * HOUR).create(&db);
+        TestItem::new(third + HOUR, third + 2 * HOUR)
+            .app("Safari")
+            .domain("github.com")
+            .create(&db);
+
+        assert_eq!(db.refresh_rollups(&DayBoundaries::default()).unwrap(), 3);
+        assert_eq!(db.refresh_rollups(&DayBoundaries::default()).unwrap(), 0);
+
+        // Two whole days from the rollups plus a partial third
+        let apps = db
+            .get_app_durations(first, third + 90 * 60_000, &DayBoundaries::default())
+            .unwrap();
+        assert_eq!(apps[0], ("Safari".to_string(), None, 4 * HOUR + HOUR / 2));
+        assert_eq!(apps[1], ("Code".to_string(), None, HOUR));
+
+        let domains = db
+            .get_domain_durations(
+                first,
+                third + 90 * 60_000,
+                "Safari",
+                &DayBoundaries::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            domains[0],
+            (Some("github.com".to_string()), 2 * HOUR + HOUR / 2, 2)
+        );
+        // The item across midnight is counted on both days
+        assert_eq!(domains[1], (None, 2 * HOUR, 2));
+    }
+
+    #[test]
+    fn test_rollups_follow_changes() {
+        let db = Database::new(":memory:").unwrap();
+        let (first, third) = (midnight(0), midnight(2));
+        TestItem::new(first + HOUR, first + 2 * HOUR).create(&db);
+        db.refresh_rollups(&DayBoundaries::default()).unwrap();
+
+        db.update_track_item_color("Code", "#00ff00").unwrap();
+        db.clear_data_before(first + 3 * HOUR).unwrap();
+        TestItem::new(first + 4 * HOUR, first + 5 * HOUR)
+            .app("Mail")
+            .create(&db);
+        db.refresh_rollups(&DayBoundaries::default()).unwrap();
+
+        let apps = db
+            .get_app_durations(first, third, &DayBoundaries::default())
+            .unwrap();
+        assert_eq!(apps, vec![("Mail".to_string(), None, HOUR)]);
+
+        db.reset_rollups().unwrap();
+        assert_eq!(db.refresh_rollups(&DayBoundaries::default()).unwrap(), 1);
+        assert_eq!(
+            db.get_app_durations(first, third, &DayBoundaries::default())
+                .unwrap(),
+            apps
+        );
+    }
+
+    #[test]
+    fn test_days_not_rebuilt_are_read_from_items() {
+        let db = Database::new(":memory:").unwrap();
+        let (first, second, third) = (midnight(0), midnight(1), midnight(2));
+        TestItem::new(first + HOUR, first + 2 * HOUR).create(&db);
+        TestItem::new(second + HOUR, second + 2 * HOUR).create(&db);
+        db.refresh_rollups(&DayBoundaries::default()).unwrap();
+
+        TestItem::new(second + 3 * HOUR, second + 4 * HOUR)
+            .app("Mail")
+            .create(&db);
+        let apps = db
+            .get_app_durations(first, third, &DayBoundaries::default())
+            .unwrap();
+        assert_eq!(
+            apps,
+            vec![
+                ("Code".to_string(), None, 2 * HOUR),
+                ("Mail".to_string(), None, HOUR)
+            ]
+        );
+
+        // Rollups built with other boundaries are not used
+        let bounds = DayBoundaries::new(None, 4).unwrap();
+        let apps = db
+            .get_app_durations(bounds.day_start(first + 5 * HOUR), third, &bounds)
+            .unwrap();
+        assert_eq!(apps.len(), 2);
+        assert!(apps.contains(&("Code".to_string(), None, HOUR)));
+    }
+
+    #[test]
+    fn test_changed_boundaries_rebuild_rollups() {
+        let db = Database::new(":memory:").unwrap();
+        let first = midnight(0);
+        TestItem::new(first + HOUR, first + 2 * HOUR).create(&db);
+        db.refresh_rollups(&DayBoundaries::default()).unwrap();
+
+        let bounds = DayBoundaries::new(None, 4).unwrap();
+        assert_eq!(db.refresh_rollups(&bounds).unwrap(), 1);
+        let day: i64 = db
+            .conn
+            .query_row("SELECT day FROM daily_rollups", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(day, bounds.day_start(first + HOUR));
+    }
+}
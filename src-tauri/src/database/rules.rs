@@ -0,0 +1,280 @@
+// Classification Rules Module
+// 自动分类规则存储模块
+
+use super::{Database, Productivity};
+use rusqlite::{params, Result, Row};
+use serde::{Deserialize, Serialize};
+
+/// An ordered, user-defined rule that classifies app activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: Option<i64>,
+    pub name: String,
+    pub enabled: bool,
+    /// Rules are evaluated in ascending position
+    pub position: i64,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+}
+
+/// What an item must look like for a rule to apply
+///
+/// Every condition that is set must match; a rule without conditions
+/// matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuleConditions {
+    /// App name, compared case-insensitively
+    pub app: Option<String>,
+    /// Regular expression matched against the window title
+    pub title_pattern: Option<String>,
+    /// Domain; also matches its subdomains
+    pub domain: Option<String>,
+    /// Regular expression matched against the URL
+    pub url_pattern: Option<String>,
+    /// Start of the time-of-day window (minutes after local midnight)
+    pub start_minute: Option<u32>,
+    /// End of the time-of-day window (exclusive); may wrap past midnight
+    pub end_minute: Option<u32>,
+}
+
+/// What a matching rule does to an item
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuleActions {
+    pub project_id: Option<i64>,
+    pub tag_ids: Vec<i64>,
+    pub color: Option<String>,
+    pub productivity: Option<Productivity>,
+}
+
+impl RuleActions {
+    /// Whether applying these actions would change nothing
+    pub fn is_empty(&self) -> bool {
+        self.project_id.is_none()
+            && self.tag_ids.is_empty()
+            && self.color.is_none()
+            && self.productivity.is_none()
+    }
+}
+
+/// What rules set on a stored item, as opposed to by hand
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleMarks {
+    pub project_ids: Vec<i64>,
+    pub tag_ids: Vec<i64>,
+    /// Whether the color came from a rule
+    pub color: bool,
+    /// Whether the productivity came from a rule
+    pub productivity: bool,
+}
+
+fn json_err(e: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+}
+
+impl Rule {
+    fn from_row(row: &Row) -> Result<Self> {
+        let conditions: String = row.get(4)?;
+        let actions: String = row.get(5)?;
+
+        Ok(Self {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            enabled: row.get(2)?,
+            position: row.get(3)?,
+            conditions: serde_json::from_str(&conditions).map_err(json_err)?,
+            actions: serde_json::from_str(&actions).map_err(json_err)?,
+        })
+    }
+}
+
+impl Database {
+    /// List all rules in evaluation order
+    pub fn get_rules(&self) -> Result<Vec<Rule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, enabled, position, conditions, actions FROM rules
+             ORDER BY position ASC, id ASC",
+        )?;
+
+        let rules = stmt.query_map([], Rule::from_row)?;
+        rules.collect()
+    }
+
+    /// Whether the project and tags a rule assigns all exist
+    pub fn rule_labels_exist(&self, actions: &RuleActions) -> Result<bool> {
+        let exists = |table: &str, id: i64| -> Result<bool> {
+            self.conn.query_row(
+                &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table),
+                params![id],
+                |row| row.get(0),
+            )
+        };
+        if let Some(project_id) = actions.project_id {
+            if !exists("projects", project_id)? {
+                return Ok(false);
+            }
+        }
+        for &tag_id in &actions.tag_ids {
+            if !exists("tags", tag_id)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Insert a rule, or update it if it has an id
+    pub fn save_rule(&self, rule: &Rule) -> Result<Rule> {
+        let conditions = serde_json::to_string(&rule.conditions).map_err(json_err)?;
+        let actions = serde_json::to_string(&rule.actions).map_err(json_err)?;
+
+        let id = match rule.id {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE rules SET name = ?1, enabled = ?2, position = ?3, conditions = ?4, actions = ?5
+                     WHERE id = ?6",
+                    params![rule.name, rule.enabled, rule.position, conditions, actions, id],
+                )?;
+                id
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO rules (name, enabled, position, conditions, actions)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![rule.name, rule.enabled, rule.position, conditions, actions],
+                )?;
+                self.conn.last_insert_rowid()
+            }
+        };
+
+        Ok(Rule {
+            id: Some(id),
+            ..rule.clone()
+        })
+    }
+
+    /// Delete a rule
+    pub fn delete_rule(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM rules WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Set rule positions to the order of the given ids
+    pub fn reorder_rules(&self, ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE rules SET position = ?1 WHERE id = ?2",
+                params![position as i64, id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Apply rule actions to a stored item
+    ///
+    /// Color and productivity are overwritten; the project and tags are
+    /// added to any the item already has.
+    pub fn apply_rule_actions(&self, item_id: i64, actions: &RuleActions) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.write_rule_actions(item_id, actions)?;
+        tx.commit()
+    }
+
+    /// Re-classify stored items in one transaction
+    ///
+    /// What rules set earlier is replaced by the new actions; what was set
+    /// by hand is kept. A color no rule sets anymore goes back to the
+    /// app's color, a productivity to none.
+    pub fn replace_rule_actions(&self, changes: &[(i64, RuleActions)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (item_id, actions) in changes {
+            tx.execute(
+                "UPDATE track_items
+                 SET color = (SELECT color FROM app_settings WHERE name = track_items.app),
+                     color_by_rule = 0
+                 WHERE id = ?1 AND color_by_rule = 1",
+                params![item_id],
+            )?;
+            tx.execute(
+                "UPDATE track_items SET productivity = NULL, productivity_by_rule = 0
+                 WHERE id = ?1 AND productivity_by_rule = 1",
+                params![item_id],
+            )?;
+            tx.execute(
+                "DELETE FROM track_item_projects WHERE track_item_id = ?1 AND by_rule = 1",
+                params![item_id],
+            )?;
+            tx.execute(
+                "DELETE FROM track_item_tags WHERE track_item_id = ?1 AND by_rule = 1",
+                params![item_id],
+            )?;
+            self.write_rule_actions(*item_id, actions)?;
+        }
+        tx.commit()
+    }
+
+    /// What rules set on a stored item
+    pub fn get_rule_marks(&self, item_id: i64) -> Result<RuleMarks> {
+        let mut projects = self.conn.prepare(
+            "SELECT project_id FROM track_item_projects WHERE track_item_id = ?1 AND by_rule = 1",
+        )?;
+        let mut tags = self.conn.prepare(
+            "SELECT tag_id FROM track_item_tags WHERE track_item_id = ?1 AND by_rule = 1",
+        )?;
+
+        let project_ids = projects
+            .query_map(params![item_id], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        let tag_ids = tags
+            .query_map(params![item_id], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        let (color, productivity) = self.conn.query_row(
+            "SELECT color_by_rule, productivity_by_rule FROM track_items WHERE id = ?1",
+            params![item_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        Ok(RuleMarks {
+            project_ids,
+            tag_ids,
+            color,
+            productivity,
+        })
+    }
+
+    /// `apply_rule_actions` without its own transaction
+    ///
+    /// Everything written is marked as set by a rule.
+    fn write_rule_actions(&self, item_id: i64, actions: &RuleActions) -> Result<()> {
+        if let Some(color) = &actions.color {
+            self.conn.execute(
+                "UPDATE track_items SET color = ?1, color_by_rule = 1 WHERE id = ?2",
+                params![color, item_id],
+            )?;
+        }
+        if let Some(productivity) = actions.productivity {
+            self.conn.execute(
+                "UPDATE track_items SET productivity = ?1, productivity_by_rule = 1 WHERE id = ?2",
+                params![productivity, item_id],
+            )?;
+        }
+        if let Some(project_id) = actions.project_id {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO track_item_projects (track_item_id, project_id, by_rule)
+                 VALUES (?1, ?2, 1)",
+                params![item_id, project_id],
+            )?;
+        }
+        for tag_id in &actions.tag_ids {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO track_item_tags (track_item_id, tag_id, by_rule)
+                 VALUES (?1, ?2, 1)",
+                params![item_id, tag_id],
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+// Search Query Module
+// 全文搜索查询解析模块
+
+/// Fields that can be targeted with a `field:` prefix
+const SEARCH_FIELDS: &[&str] = &["app", "title", "domain"];
+
+/// A user search string translated into FTS5 MATCH expressions
+///
+/// Supported syntax:
+/// - `word` matches any indexed field starting with `word`
+/// - `"exact phrase"` matches the words in order
+/// - `app:`, `title:`, `domain:` restrict a word or phrase to one field
+/// - `-word` excludes items that match
+///
+/// All included terms must match; an item matching any excluded term is
+/// dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Expression every result must match
+    pub include: Option<String>,
+    /// Expression no result may match
+    pub exclude: Option<String>,
+}
+
+impl SearchQuery {
+    /// Parse a search string; returns `None` if it contains no terms
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut include = Vec::new();
+        let mut exclude = Vec::new();
+
+        for token in tokenize(input) {
+            if token.text.is_empty() {
+                continue;
+            }
+
+            let mut expr = quote(&token.text);
+            if !token.phrase {
+                expr.push('*');
+            }
+            if let Some(field) = token.field {
+                expr = format!("{} : {}", field, expr);
+            }
+
+            if token.negated {
+                exclude.push(expr);
+            } else {
+                include.push(expr);
+            }
+        }
+
+        if include.is_empty() && exclude.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            include: (!include.is_empty()).then(|| include.join(" AND ")),
+            exclude: (!exclude.is_empty()).then(|| exclude.join(" OR ")),
+        })
+    }
+}
+
+/// One term of a search string
+#[derive(Debug)]
+struct Token {
+    field: Option<&'static str>,
+    text: String,
+    phrase: bool,
+    negated: bool,
+}
+
+/// Split a search string into terms, honoring quotes, `-` and field prefixes
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let negated = chars.next_if_eq(&'-').is_some();
+
+        // Read up to the next quote or whitespace, then split off a known
+        // field prefix
+        let mut word = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '"') {
+            word.push(c);
+        }
+
+        let field = word.split_once(':').and_then(|(name, _)| {
+            SEARCH_FIELDS
+                .iter()
+                .copied()
+                .find(|f| f.eq_ignore_ascii_case(name))
+        });
+        if let Some(field) = field {
+            word.drain(..=field.len());
+        }
+
+        let phrase = word.is_empty() && chars.next_if_eq(&'"').is_some();
+        if phrase {
+            // Unterminated phrases run to the end of the input
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                word.push(c);
+            }
+        }
+
+        tokens.push(Token {
+            field,
+            text: word.trim().to_string(),
+            phrase,
+            negated,
+        });
+    }
+
+    tokens
+}
+
+/// Quote a term as an FTS5 string so user input is never parsed as syntax
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (Option<String>, Option<String>) {
+        let query = SearchQuery::parse(input).unwrap();
+        (query.include, query.exclude)
+    }
+
+    #[test]
+    fn test_empty_query() {
+        assert_eq!(SearchQuery::parse(""), None);
+        assert_eq!(SearchQuery::parse("   \"\" "), None);
+    }
+
+    #[test]
+    fn test_plain_terms_are_prefix_matches() {
+        assert_eq!(
+            parse("rust docs"),
+            (Some(r#""rust"* AND "docs"*"#.to_string()), None)
+        );
+    }
+
+    #[test]
+    fn test_phrases_fields_and_negation() {
+        assert_eq!(
+            parse(r#"app:Code "pull request" -title:draft -"CI failed""#),
+            (
+                Some(r#"app : "Code"* AND "pull request""#.to_string()),
+                Some(r#"title : "draft"* OR "CI failed""#.to_string())
+            )
+        );
+        assert_eq!(
+            parse(r#"domain:"github.com""#),
+            (Some(r#"domain : "github.com""#.to_string()), None)
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_and_syntax_are_literal() {
+        assert_eq!(
+            parse("url:x a\"b NEAR(c)"),
+            (
+                Some(r#""url:x"* AND "a"* AND "b NEAR(c)""#.to_string()),
+                None
+            )
+        );
+    }
+}
@@ -0,0 +1,319 @@
+// Trend Statistics Module
+// 趋势统计模块
+
+use super::days::DayBoundaries;
+use super::{Database, TrackItem};
+use chrono::{Datelike, Duration, NaiveDate, Timelike};
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const HOUR_MS: i64 = 3_600_000;
+/// Days in the week-over-week comparison
+const WEEK_DAYS: i64 = 7;
+/// Days in the month-over-month comparison
+const MONTH_DAYS: i64 = 30;
+
+/// App time on one local date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayTotal {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub duration: i64,
+    /// Mean daily time over the 7 days ending on this date
+    pub average_7: i64,
+    /// Mean daily time over the 30 days ending on this date
+    pub average_30: i64,
+}
+
+/// What a usage delta is about
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum UsageKey {
+    App(String),
+    Domain(String),
+    Project(i64),
+}
+
+/// Time in the latest period and the one before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageDelta {
+    pub key: UsageKey,
+    pub current: i64,
+    pub previous: i64,
+}
+
+/// Calendar and trend statistics for a range of dates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendStats {
+    /// One entry per date in the range, including empty ones
+    pub days: Vec<DayTotal>,
+    /// Time per weekday (0 = Monday) and wall-clock hour over the range
+    pub hourly: Vec<Vec<i64>>,
+    /// The 7 days ending on the last date against the 7 before them
+    pub week_deltas: Vec<UsageDelta>,
+    /// The 30 days ending on the last date against the 30 before them
+    pub month_deltas: Vec<UsageDelta>,
+}
+
+/// Durations of one key in the comparison windows
+#[derive(Default)]
+struct Windows {
+    week: i64,
+    previous_week: i64,
+    month: i64,
+    previous_month: i64,
+}
+
+/// Deltas with time in either window, largest current time first
+fn sorted_deltas(
+    windows: &HashMap<UsageKey, Windows>,
+    pick: impl Fn(&Windows) -> (i64, i64),
+) -> Vec<UsageDelta> {
+    let mut deltas: Vec<UsageDelta> = windows
+        .iter()
+        .map(|(key, w)| {
+            let (current, previous) = pick(w);
+            UsageDelta {
+                key: key.clone(),
+                current,
+                previous,
+            }
+        })
+        .filter(|d| d.current > 0 || d.previous > 0)
+        .collect();
+    deltas.sort_by(|a, b| b.current.cmp(&a.current).then(b.previous.cmp(&a.previous)));
+    deltas
+}
+
+/// Mean of the `n` daily totals ending at `index`
+fn trailing_average(daily: &[i64], index: usize, n: usize) -> i64 {
+    let start = (index + 1).saturating_sub(n);
+    daily[start..=index].iter().sum::<i64>() / n as i64
+}
+
+impl Database {
+    /// Daily totals, rolling averages, the weekday and hour distribution
+    /// and week-over-week and month-over-month deltas for `first` to
+    /// `last`, inclusive
+    ///
+    /// App items are read in a single pass, starting early enough to fill
+    /// the averages and comparison windows.
+    pub fn get_trend_stats(
+        &self,
+        first: NaiveDate,
+        last: NaiveDate,
+        bounds: &DayBoundaries,
+    ) -> Result<TrendStats> {
+        let scan_first =
+            (first - Duration::days(MONTH_DAYS - 1)).min(last - Duration::days(2 * MONTH_DAYS - 1));
+        let (from, to) = bounds.date_range(scan_first, last);
+        let day_count = (last - scan_first).num_days() as usize + 1;
+
+        let mut daily = vec![0i64; day_count];
+        let mut hourly = vec![vec![0i64; 24]; 7];
+        let mut windows: HashMap<UsageKey, Windows> = HashMap::new();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT t.app, t.domain, t.begin_date, t.end_date, GROUP_CONCAT(l.project_id)
+             FROM track_items t
+             LEFT JOIN track_item_projects l ON l.track_item_id = t.id
+             WHERE t.task_name = 'AppTrackItem' AND t.end_date > ?1 AND t.begin_date < ?2
+             GROUP BY t.id",
+        )?;
+        let mut rows = stmt.query(params![from, to])?;
+        while let Some(row) = rows.next()? {
+            let item = TrackItem {
+                id: None,
+                app: row.get(0)?,
+                task_name: "AppTrackItem".to_string(),
+                title: String::new(),
+                url: None,
+                domain: row.get(1)?,
+                color: None,
+                begin_date: row.get(2)?,
+                end_date: row.get(3)?,
+                productivity: None,
+            };
+            let projects: Option<String> = row.get(4)?;
+
+            let mut keys = vec![UsageKey::App(item.app.clone())];
+            keys.extend(item.domain.clone().map(UsageKey::Domain));
+            keys.extend(
+                projects
+                    .iter()
+                    .flat_map(|p| p.split(','))
+                    .filter_map(|id| id.parse().ok())
+                    .map(UsageKey::Project),
+            );
+
+            for piece in bounds.split_item(&item, from, to) {
+                let duration = piece.end_date - piece.begin_date;
+                let date = bounds.date_of(piece.begin_date);
+                daily[(date - scan_first).num_days() as usize] += duration;
+
+                let days_back = (last - date).num_days();
+                for key in &keys {
+                    let w = windows.entry(key.clone()).or_default();
+                    match days_back {
+                        d if d < WEEK_DAYS => w.week += duration,
+                        d if d < 2 * WEEK_DAYS => w.previous_week += duration,
+                        _ => {}
+                    }
+                    match days_back {
+                        d if d < MONTH_DAYS => w.month += duration,
+                        d if d < 2 * MONTH_DAYS => w.previous_month += duration,
+                        _ => {}
+                    }
+                }
+
+                if date < first {
+                    continue;
+                }
+                // Cut at wall-clock hours
+                let mut begin = piece.begin_date;
+                while begin < piece.end_date {
+                    let local = bounds.local_time(begin);
+                    let into_hour = (local.minute() * 60 + local.second()) as i64 * 1000
+                        + (local.nanosecond() / 1_000_000) as i64;
+                    let end = (begin + HOUR_MS - into_hour).min(piece.end_date);
+                    hourly[local.weekday().num_days_from_monday() as usize]
+                        [local.hour() as usize] += end - begin;
+                    begin = end;
+                }
+            }
+        }
+
+        let offset = (first - scan_first).num_days() as usize;
+        let days = (offset..day_count)
+            .map(|index| DayTotal {
+                date: (scan_first + Duration::days(index as i64))
+                    .format("%Y-%m-%d")
+                    .to_string(),
+                duration: daily[index],
+                average_7: trailing_average(&daily, index, WEEK_DAYS as usize),
+                average_30: trailing_average(&daily, index, MONTH_DAYS as usize),
+            })
+            .collect();
+
+        Ok(TrendStats {
+            days,
+            hourly,
+            week_deltas: sorted_deltas(&windows, |w| (w.week, w.previous_week)),
+            month_deltas: sorted_deltas(&windows, |w| (w.month, w.previous_month)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::days::parse_date;
+    use crate::database::{RuleActions, TestItem};
+
+    #[test]
+    fn test_daily_totals_and_averages() {
+        let db = Database::new(":memory:").unwrap();
+        let days = DayBoundaries::new(Some("UTC"), 0).unwrap();
+        let first = parse_date("2024-03-01").unwrap();
+        let last = parse_date("2024-03-07").unwrap();
+        let (start, _) = days.date_range(first, first);
+
+        // 23:00 on the first to 01:00 on the second
+        TestItem::new(start + 23 * HOUR_MS, start + 25 * HOUR_MS).create(&db);
+        // 14 hours on the last date, outside the heatmap's range before it
+        TestItem::new(
+            start + 6 * 24 * HOUR_MS,
+            start + 6 * 24 * HOUR_MS + 14 * HOUR_MS,
+        )
+        .create(&db);
+        TestItem::new(start - 24 * HOUR_MS, start - 23 * HOUR_MS).create(&db);
+
+        let stats = db.get_trend_stats(first, last, &days).unwrap();
+        assert_eq!(stats.days.len(), 7);
+        assert_eq!(stats.days[0].date, "2024-03-01");
+        assert_eq!(stats.days[0].duration, HOUR_MS);
+        assert_eq!(stats.days[1].duration, HOUR_MS);
+        assert_eq!(stats.days[6].duration, 14 * HOUR_MS);
+        // The day before the range counts toward the averages
+        assert_eq!(stats.days[0].average_7, 2 * HOUR_MS / 7);
+        assert_eq!(stats.days[6].average_7, 16 * HOUR_MS / 7);
+        assert_eq!(stats.days[6].average_30, 17 * HOUR_MS / 30);
+    }
+
+    #[test]
+    fn test_hourly_distribution() {
+        let db = Database::new(":memory:").unwrap();
+        let days = DayBoundaries::new(Some("Asia/Kolkata"), 0).unwrap();
+        // 2024-03-04 is a Monday
+        let date = parse_date("2024-03-04").unwrap();
+        let (start, _) = days.date_range(date, date);
+        TestItem::new(start + 9 * HOUR_MS + HOUR_MS / 2, start + 11 * HOUR_MS).create(&db);
+
+        let stats = db.get_trend_stats(date, date, &days).unwrap();
+        assert_eq!(stats.hourly[0][9], HOUR_MS / 2);
+        assert_eq!(stats.hourly[0][10], HOUR_MS);
+        assert_eq!(stats.hourly.iter().flatten().sum::<i64>(), 3 * HOUR_MS / 2);
+    }
+
+    #[test]
+    fn test_week_and_month_deltas() {
+        let db = Database::new(":memory:").unwrap();
+        let days = DayBoundaries::new(Some("UTC"), 0).unwrap();
+        let last = parse_date("2024-03-31").unwrap();
+        let (start, _) = days.date_range(last, last);
+        let project = db.create_project("Website", None).unwrap();
+
+        let item = TestItem::new(start, start + 2 * HOUR_MS)
+            .app("Firefox")
+            .domain("github.com")
+            .create(&db);
+        db.apply_rule_actions(
+            item,
+            &RuleActions {
+                project_id: Some(project.id),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // Ten days back: last week, but this month
+        let earlier = start - 10 * 24 * HOUR_MS;
+        TestItem::new(earlier, earlier + HOUR_MS)
+            .app("Firefox")
+            .domain("youtube.com")
+            .create(&db);
+
+        let stats = db.get_trend_stats(last, last, &days).unwrap();
+        let delta = |deltas: &[UsageDelta], key: UsageKey| {
+            deltas
+                .iter()
+                .find(|d| d.key == key)
+                .map(|d| (d.current, d.previous))
+        };
+
+        let firefox = UsageKey::App("Firefox".to_string());
+        assert_eq!(
+            delta(&stats.week_deltas, firefox.clone()),
+            Some((2 * HOUR_MS, HOUR_MS))
+        );
+        assert_eq!(delta(&stats.month_deltas, firefox), Some((3 * HOUR_MS, 0)));
+        assert_eq!(
+            delta(
+                &stats.week_deltas,
+                UsageKey::Domain("youtube.com".to_string())
+            ),
+            Some((0, HOUR_MS))
+        );
+        assert_eq!(
+            delta(&stats.week_deltas, UsageKey::Project(project.id)),
+            Some((2 * HOUR_MS, 0))
+        );
+        assert_eq!(
+            stats.week_deltas[0].key,
+            UsageKey::App("Firefox".to_string())
+        );
+    }
+}
@@ -1,19 +1,65 @@
 pub mod commands;
 pub mod database;
+pub mod notifications;
 pub mod services;
 pub mod tray;
 
-use database::Database;
-use services::TrackerService;
+use commands::AppSettings;
+use database::{encryption, DatabaseKey, DatabasePool, DayBoundaries};
+use services::{
+    BreakService, CompactionService, FocusService, GoalService, RollupService, TrackerConfig,
+    TrackerService,
+};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 use tauri::Manager;
 
 /// Application state - shared across all commands
 pub struct AppState {
-    /// Database connection (wrapped in Mutex for thread-safe access)
-    pub db: Mutex<Database>,
+    /// Database connections, shared with the tracker
+    pub db: Arc<DatabasePool>,
+    /// Key the database was opened with; `None` if unencrypted or locked
+    pub db_key: Mutex<Option<DatabaseKey>>,
+    /// Whether the database waits for its passphrase (or is being
+    /// re-keyed); the connections then point at an in-memory placeholder
+    pub locked: AtomicBool,
     /// Tracker service for automatic time tracking
     pub tracker: Arc<TrackerService>,
+    /// Focus (Pomodoro) sessions
+    pub focus: Arc<FocusService>,
+    /// Reminders to take a break
+    pub breaks: Arc<BreakService>,
+    /// Progress of goals and limits
+    pub goals: Arc<GoalService>,
+    /// Nightly timeline compaction
+    pub compaction: Arc<CompactionService>,
+    /// Background rebuild of the daily rollups
+    pub rollups: Arc<RollupService>,
+}
+
+/// Open an encrypted database with the first keyring key that fits
+///
+/// A staged key left behind by an interrupted re-key is promoted once it
+/// opens the file.
+fn open_with_keyring(path: &Path) -> Option<(DatabasePool, DatabaseKey)> {
+    let keys = encryption::load_keyring_keys()
+        .map_err(|e| log::error!("Failed to read database key: {}", e))
+        .ok()?;
+    for (index, key) in keys.into_iter().enumerate() {
+        match DatabasePool::open(path.to_str()?, Some(&key)) {
+            Ok(db) => {
+                if index > 0 {
+                    if let Err(e) = encryption::remember_key(Some(&key)) {
+                        log::error!("Failed to remember database key: {}", e);
+                    }
+                }
+                return Some((db, key));
+            }
+            Err(e) => log::error!("Database key from the keyring does not fit: {}", e),
+        }
+    }
+    None
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -24,6 +70,7 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_autostart::init(
             tauri_plugin_autostart::MacosLauncher::LaunchAgent,
             Some(vec!["--minimized"]),
@@ -38,46 +85,96 @@ pub fn run() {
 
             // Initialize database
             let db_path = app_dir.join("timlyzer.db");
-            let db =
-                Database::new(db_path.to_str().unwrap()).expect("Failed to initialize database");
+
+            // Encrypted databases open with a key from the OS secret store;
+            // without one that fits they stay locked until unlocked
+            let encrypted = encryption::is_encrypted_file(&db_path);
+            let opened = if encrypted {
+                open_with_keyring(&db_path)
+            } else {
+                None
+            };
+            let locked = encrypted && opened.is_none();
+            let (db, key) = match opened {
+                Some((db, key)) => (Ok(db), Some(key)),
+                None if locked => (DatabasePool::open(":memory:", None), None),
+                None => (DatabasePool::open(db_path.to_str().unwrap(), None), None),
+            };
+            let db = Arc::new(db.expect("Failed to initialize database"));
             log::info!("Database initialized at: {:?}", db_path);
 
-            // Create database Arc for tracker
-            let db_arc = Arc::new(Mutex::new(
-                Database::new(db_path.to_str().unwrap())
-                    .expect("Failed to initialize database for tracker"),
-            ));
+            // Load saved settings
+            let settings = db
+                .read()
+                .and_then(|db| db.get_settings().map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to load settings, using defaults: {}", e);
+                    AppSettings::default()
+                });
 
             // Create tracker service
-            let tracker = Arc::new(TrackerService::new(Arc::clone(&db_arc)));
+            let goals = Arc::new(GoalService::new(Arc::clone(&db)));
+            let tracker = Arc::new(TrackerService::new(
+                Arc::clone(&db),
+                Arc::clone(&goals),
+                TrackerConfig::from(&settings),
+            ));
 
-            // Start the tracker automatically
-            tracker.start(app.handle().clone());
-            log::info!("Tracker service started");
+            let focus = Arc::new(FocusService::new(Arc::clone(&db), settings.focus.clone()));
+            let breaks = Arc::new(BreakService::new(Arc::clone(&db), settings.breaks.clone()));
+            let compaction = Arc::new(CompactionService::new(
+                Arc::clone(&db),
+                settings.compaction.clone(),
+            ));
+            let rollups = Arc::new(RollupService::new(Arc::clone(&db)));
+
+            // Start tracking and the background jobs automatically; nothing
+            // may write to the placeholder of a locked database
+            if locked {
+                log::info!("Database is encrypted; tracking starts once it is unlocked");
+            } else {
+                tracker.start(app.handle().clone());
+                breaks.start(app.handle().clone());
+                compaction.start(app.handle().clone());
+                rollups.start();
+                log::info!("Tracker service started");
+            }
 
             // Setup system tray
             if let Err(e) = tray::setup_tray(app) {
                 log::error!("Failed to setup tray: {}", e);
             }
 
+            // A log timer started before the last quit is still running
+            let timer = db
+                .read()
+                .and_then(|db| db.get_log_timer().map_err(|e| e.to_string()));
+            if let Ok(Some(timer)) = timer {
+                let days =
+                    DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)
+                        .unwrap_or_default();
+                tray::show_log_timer(app.handle(), Some(&timer), &days);
+            }
+
             // Manage application state
             app.manage(AppState {
-                db: Mutex::new(db),
+                db,
+                db_key: Mutex::new(key),
+                locked: AtomicBool::new(locked),
                 tracker,
+                focus,
+                breaks,
+                goals,
+                compaction,
+                rollups,
             });
 
             // Restore Dock visibility from saved settings
             #[cfg(target_os = "macos")]
-            {
-                let settings_db = Database::new(db_path.to_str().unwrap())
-                    .expect("Failed to open database for settings");
-                if let Ok(settings) = settings_db.get_settings() {
-                    if settings.hide_dock {
-                        use tauri::ActivationPolicy;
-                        let _ = app.handle().set_activation_policy(ActivationPolicy::Accessory);
-                        log::info!("Dock icon hidden (restored from settings)");
-                    }
-                }
+            if settings.hide_dock {
+                use tauri::ActivationPolicy;
+                let _ = app.handle().set_activation_policy(ActivationPolicy::Accessory);
+                log::info!("Dock icon hidden (restored from settings)");
             }
 
             log::info!("Timlyzer initialized successfully");
@@ -100,6 +197,11 @@ pub fn run() {
             commands::create_track_item,
             commands::update_track_item,
             commands::delete_by_ids,
+            commands::split_track_item,
+            commands::merge_track_items,
+            commands::shift_track_items,
+            commands::trim_track_items,
+            commands::reassign_range,
             commands::search_items,
             commands::update_track_item_color,
             commands::get_app_version,
@@ -111,15 +213,69 @@ pub fn run() {
             commands::pause_tracking,
             commands::resume_tracking,
             commands::get_idle_time_command,
+            // Log timer commands
+            commands::start_log_timer,
+            commands::stop_log_timer,
+            commands::get_running_log_timer,
+            // Focus session commands
+            commands::start_focus_session,
+            commands::pause_focus_session,
+            commands::resume_focus_session,
+            commands::skip_focus_phase,
+            commands::stop_focus_session,
+            commands::get_focus_status,
+            // Break reminder commands
+            commands::get_break_status,
+            commands::snooze_break_reminder,
+            commands::dismiss_break_reminder,
+            commands::get_break_stats,
+            // Goal commands
+            commands::get_goals,
+            commands::save_goal,
+            commands::delete_goal,
+            commands::get_goal_progress,
+            commands::get_goal_history,
             // Statistics commands
+            commands::get_date_range,
             commands::get_app_usage_stats,
             commands::get_domain_usage_stats,
+            commands::get_trend_stats,
+            commands::get_attention_stats,
+            commands::get_project_usage_stats,
+            // Project & tag commands
+            commands::get_projects,
+            commands::create_project,
+            commands::rename_project,
+            commands::archive_project,
+            commands::merge_projects,
+            commands::assign_project,
+            commands::unassign_project,
+            commands::get_tags,
+            commands::create_tag,
+            commands::delete_tag,
+            commands::tag_items,
+            commands::untag_items,
+            commands::get_item_labels,
+            // Rule commands
+            commands::get_rules,
+            commands::save_rule,
+            commands::delete_rule,
+            commands::reorder_rules,
+            commands::apply_rules_to_range,
+            // Compaction commands
+            commands::compact_timeline,
             // Settings commands
             commands::get_settings,
             commands::save_settings,
+            commands::redact_stored_items,
             commands::get_tracked_apps,
             commands::get_database_info,
             commands::clear_data_before,
+            // Encryption commands
+            commands::enable_encryption,
+            commands::change_encryption_key,
+            commands::export_decrypted_copy,
+            commands::unlock_database,
             // Export commands
             commands::export_to_csv,
             commands::export_to_json,
@@ -130,6 +286,25 @@ pub fn run() {
             commands::get_autostart,
             commands::set_autostart,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // Flush the tracker's open items and the focus session however
+            // the app exits
+            if let tauri::RunEvent::Exit = event {
+                let state = app_handle.state::<AppState>();
+                let tracker = Arc::clone(&state.tracker);
+                let focus = Arc::clone(&state.focus);
+                let breaks = Arc::clone(&state.breaks);
+                let compaction = Arc::clone(&state.compaction);
+                let rollups = Arc::clone(&state.rollups);
+                tauri::async_runtime::block_on(async {
+                    tracker.stop().await;
+                    focus.stop(app_handle).await;
+                    breaks.stop().await;
+                    compaction.stop().await;
+                    rollups.stop().await;
+                });
+            }
+        });
 }
@@ -0,0 +1,12 @@
+// Desktop Notifications Module
+// 桌面通知模块
+
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+/// Show a desktop notification
+pub fn show(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::error!("Failed to show notification: {}", e);
+    }
+}
@@ -2,7 +2,6 @@
 // 活动窗口检测模块
 
 use serde::{Deserialize, Serialize};
-use std::process::Command;
 
 /// Information about the currently active window
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,46 +31,91 @@ impl WindowInfo {
     }
 }
 
-/// List of known browser app names
-const KNOWN_BROWSERS: &[&str] = &[
-    "Google Chrome",
-    "Safari",
-    "Arc",
-    "Microsoft Edge",
-    "Brave Browser",
-    "Firefox",
-    "Opera",
-    "Vivaldi",
-];
+/// How a browser exposes the URL of its active tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BrowserKind {
+    /// Chromium-based (Chrome, Edge, Brave, Arc, Opera, Vivaldi, ...)
+    Chromium,
+    /// Safari and Safari Technology Preview
+    Safari,
+    /// Firefox-based; URL capture is not supported
+    Firefox,
+}
+
+impl BrowserKind {
+    /// AppleScript that returns the active tab's URL
+    #[cfg(any(target_os = "macos", test))]
+    fn url_script(&self, app_name: &str) -> Option<String> {
+        let app = app_name.replace('\\', "\\\\").replace('"', "\\\"");
+        match self {
+            BrowserKind::Chromium => Some(format!(
+                r#"tell application "{}" to return URL of active tab of front window"#,
+                app
+            )),
+            BrowserKind::Safari => Some(format!(
+                r#"tell application "{}" to return URL of front document"#,
+                app
+            )),
+            // Firefox doesn't support AppleScript well
+            BrowserKind::Firefox => None,
+        }
+    }
+}
+
+/// A browser the tracker knows how to read URLs from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserConfig {
+    /// Application name as reported by the OS (e.g., "Google Chrome")
+    pub app_name: String,
+    /// How to read the current URL
+    pub kind: BrowserKind,
+    /// Whether URLs may be captured from this browser
+    pub capture_url: bool,
+}
+
+impl BrowserConfig {
+    pub fn new(app_name: &str, kind: BrowserKind) -> Self {
+        Self {
+            app_name: app_name.to_string(),
+            kind,
+            capture_url: true,
+        }
+    }
+}
 
-/// Check if an app is a known browser
-fn is_browser(app_name: &str) -> bool {
-    KNOWN_BROWSERS.contains(&app_name)
+/// Browsers known out of the box
+pub fn default_browsers() -> Vec<BrowserConfig> {
+    vec![
+        BrowserConfig::new("Google Chrome", BrowserKind::Chromium),
+        BrowserConfig::new("Safari", BrowserKind::Safari),
+        BrowserConfig::new("Arc", BrowserKind::Chromium),
+        BrowserConfig::new("Microsoft Edge", BrowserKind::Chromium),
+        BrowserConfig::new("Brave Browser", BrowserKind::Chromium),
+        BrowserConfig::new("Firefox", BrowserKind::Firefox),
+        BrowserConfig::new("Opera", BrowserKind::Chromium),
+        BrowserConfig::new("Vivaldi", BrowserKind::Chromium),
+    ]
+}
+
+/// Find the browser entry for an app, if URL capture is allowed for it
+fn find_capturable_browser<'a>(
+    app_name: &str,
+    browsers: &'a [BrowserConfig],
+) -> Option<&'a BrowserConfig> {
+    browsers
+        .iter()
+        .find(|b| b.app_name == app_name)
+        .filter(|b| b.capture_url)
 }
 
 /// Get the URL from a browser using AppleScript (macOS only)
 #[cfg(target_os = "macos")]
-fn get_browser_url(app_name: &str) -> Option<String> {
-    let script = match app_name {
-        "Google Chrome" => {
-            r#"tell application "Google Chrome" to return URL of active tab of front window"#
-        }
-        "Safari" => r#"tell application "Safari" to return URL of front document"#,
-        "Arc" => r#"tell application "Arc" to return URL of active tab of front window"#,
-        "Microsoft Edge" => {
-            r#"tell application "Microsoft Edge" to return URL of active tab of front window"#
-        }
-        "Brave Browser" => {
-            r#"tell application "Brave Browser" to return URL of active tab of front window"#
-        }
-        "Firefox" => {
-            // Firefox doesn't support AppleScript well, try via accessibility
-            return None;
-        }
-        "Opera" => r#"tell application "Opera" to return URL of active tab of front window"#,
-        "Vivaldi" => r#"tell application "Vivaldi" to return URL of active tab of front window"#,
-        _ => return None,
-    };
+fn get_browser_url(browser: &BrowserConfig) -> Option<String> {
+    use std::process::Command;
+
+    let script = browser.kind.url_script(&browser.app_name)?;
 
     let output = Command::new("osascript")
         .arg("-e")
@@ -89,7 +133,7 @@ fn get_browser_url(app_name: &str) -> Option<String> {
     } else {
         log::debug!(
             "AppleScript failed for {}: {}",
-            app_name,
+            browser.app_name,
             String::from_utf8_lossy(&output.stderr)
         );
         None
@@ -97,7 +141,7 @@ fn get_browser_url(app_name: &str) -> Option<String> {
 }
 
 #[cfg(not(target_os = "macos"))]
-fn get_browser_url(_app_name: &str) -> Option<String> {
+fn get_browser_url(_browser: &BrowserConfig) -> Option<String> {
     // URL extraction not implemented for non-macOS platforms
     None
 }
@@ -136,36 +180,23 @@ fn extract_domain(url: &str) -> Option<String> {
     }
 }
 
+/// Whether `domain` is `pattern` or one of its subdomains
+pub fn domain_matches(domain: &str, pattern: &str) -> bool {
+    let domain = domain.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    domain == pattern || domain.ends_with(&format!(".{}", pattern))
+}
+
 /// Get the currently active window information
 ///
-/// Uses the `active-win-pos-rs` crate for cross-platform support
-/// For browsers, also attempts to fetch the current URL
+/// Uses the `active-win-pos-rs` crate for cross-platform support.
+/// The URL is not filled in; see `capture_browser_url`.
 pub fn get_active_window() -> Result<WindowInfo, String> {
     match active_win_pos_rs::get_active_window() {
         Ok(window) => {
-            let mut info = WindowInfo {
-                app_name: window.app_name.clone(),
-                title: window.title,
-                process_id: window.process_id as u32,
-                url: None,
-                domain: None,
-            };
-
-            // If it's a browser, try to get the URL
-            if is_browser(&window.app_name) {
-                if let Some(url) = get_browser_url(&window.app_name) {
-                    info.domain = extract_domain(&url);
-                    info.url = Some(url);
-                }
-            }
-
-            log::debug!(
-                "Active window: {} - {} (url: {:?}, domain: {:?})",
-                info.app_name,
-                info.title,
-                info.url,
-                info.domain
-            );
+            let info = WindowInfo::new(window.app_name, window.title, window.process_id as u32);
+
+            log::debug!("Active window: {} - {}", info.app_name, info.title);
             Ok(info)
         }
         Err(()) => {
@@ -175,6 +206,21 @@ pub fn get_active_window() -> Result<WindowInfo, String> {
     }
 }
 
+/// Fill in the URL and domain if the window belongs to a browser that
+/// allows URL capture
+pub fn capture_browser_url(info: &mut WindowInfo, browsers: &[BrowserConfig]) {
+    let Some(browser) = find_capturable_browser(&info.app_name, browsers) else {
+        return;
+    };
+
+    if let Some(url) = get_browser_url(browser) {
+        info.domain = extract_domain(&url);
+        info.url = Some(url);
+    }
+
+    log::debug!("Browser url: {:?}, domain: {:?}", info.url, info.domain);
+}
+
 /// Check if two windows are the same (same app and title)
 pub fn is_same_window(a: &WindowInfo, b: &WindowInfo) -> bool {
     a.app_name == b.app_name && a.title == b.title
@@ -202,4 +248,39 @@ mod tests {
         assert!(is_same_window(&a, &b));
         assert!(!is_same_window(&a, &c));
     }
+
+    #[test]
+    fn test_find_capturable_browser() {
+        let mut browsers = default_browsers();
+        browsers.push(BrowserConfig::new("Chromium", BrowserKind::Chromium));
+        browsers[0].capture_url = false;
+
+        assert!(find_capturable_browser("Google Chrome", &browsers).is_none());
+        assert!(find_capturable_browser("Chromium", &browsers).is_some());
+        assert!(find_capturable_browser("Terminal", &browsers).is_none());
+    }
+
+    #[test]
+    fn test_url_script() {
+        assert_eq!(
+            BrowserKind::Chromium.url_script("Brave Browser").unwrap(),
+            r#"tell application "Brave Browser" to return URL of active tab of front window"#
+        );
+        assert_eq!(
+            BrowserKind::Safari
+                .url_script("Safari Technology Preview")
+                .unwrap(),
+            r#"tell application "Safari Technology Preview" to return URL of front document"#
+        );
+        assert!(BrowserKind::Firefox.url_script("Firefox").is_none());
+    }
+
+    #[test]
+    fn test_extract_domain() {
+        assert_eq!(
+            extract_domain("https://Docs.Rust-Lang.org:443/std/index.html").as_deref(),
+            Some("docs.rust-lang.org")
+        );
+        assert_eq!(extract_domain("https://"), None);
+    }
 }
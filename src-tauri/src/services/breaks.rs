@@ -0,0 +1,389 @@
+// Break Reminder Module
+// 休息提醒模块
+
+use crate::database::{BreakOutcome, DatabasePool};
+use crate::services::get_idle_time;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{oneshot, watch};
+
+const MINUTE_MS: i64 = 60_000;
+/// How often activity is checked (seconds)
+const CHECK_INTERVAL_SECS: u64 = 5;
+
+/// Break reminder configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BreakConfig {
+    pub enabled: bool,
+    /// Remind after this many minutes of continuous activity
+    pub remind_after_minutes: u64,
+    /// Idle stretches at least this long count as a break
+    pub break_minutes: u64,
+    /// How long a snoozed reminder waits
+    pub snooze_minutes: u64,
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            remind_after_minutes: 50,
+            break_minutes: 5,
+            snooze_minutes: 10,
+        }
+    }
+}
+
+/// What a `BreakTracker` tick found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakEvent {
+    /// Active for too long; show a reminder
+    Remind { active_ms: i64 },
+    /// The user took a break
+    BreakTaken,
+}
+
+/// Break reminder status for frontend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakStatus {
+    /// Continuous active time so far (milliseconds)
+    pub active_ms: i64,
+    /// Whether a reminder is waiting for an answer
+    pub reminder_pending: bool,
+    pub snoozed_until: Option<i64>,
+}
+
+/// Measures continuous active time
+///
+/// Idle stretches shorter than `break_minutes` do not reset it; a longer
+/// one counts as a break.
+#[derive(Debug, Default)]
+pub struct BreakTracker {
+    /// When the current stretch of activity began
+    active_since: Option<i64>,
+    snoozed_until: Option<i64>,
+    reminder_pending: bool,
+}
+
+impl BreakTracker {
+    pub fn status(&self, now: i64) -> BreakStatus {
+        BreakStatus {
+            active_ms: self.active_since.map(|t| now - t).unwrap_or(0),
+            reminder_pending: self.reminder_pending,
+            snoozed_until: self.snoozed_until,
+        }
+    }
+
+    /// Check activity; `idle_ms` is the time since the last input
+    pub fn tick(&mut self, now: i64, idle_ms: i64, config: &BreakConfig) -> Option<BreakEvent> {
+        if idle_ms >= config.break_minutes.max(1) as i64 * MINUTE_MS {
+            let was_active = self.active_since.is_some();
+            *self = Self::default();
+            return was_active.then_some(BreakEvent::BreakTaken);
+        }
+
+        let active_since = *self.active_since.get_or_insert(now - idle_ms);
+        let active_ms = now - active_since;
+        let snoozed = self.snoozed_until.is_some_and(|until| now < until);
+        if !self.reminder_pending
+            && !snoozed
+            && active_ms >= config.remind_after_minutes.max(1) as i64 * MINUTE_MS
+        {
+            self.reminder_pending = true;
+            self.snoozed_until = None;
+            return Some(BreakEvent::Remind { active_ms });
+        }
+        None
+    }
+
+    /// Remind again after the snooze time
+    pub fn snooze(&mut self, now: i64, config: &BreakConfig) {
+        self.reminder_pending = false;
+        self.snoozed_until = Some(now + config.snooze_minutes.max(1) as i64 * MINUTE_MS);
+    }
+
+    /// Close the reminder; the next one comes after another full stretch
+    pub fn dismiss(&mut self, now: i64) {
+        self.reminder_pending = false;
+        self.snoozed_until = None;
+        self.active_since = Some(now);
+    }
+}
+
+/// Tracker state plus the last logged reminder
+///
+/// A snoozed reminder keeps its id, so a break taken during the snooze is
+/// still recorded against it.
+#[derive(Default)]
+struct BreakState {
+    tracker: BreakTracker,
+    reminder_id: Option<i64>,
+}
+
+impl BreakState {
+    /// Forget everything, dismissing a reminder nobody answered
+    fn reset(&mut self, db: &DatabasePool, now: i64) {
+        if self.tracker.reminder_pending {
+            resolve(db, self.reminder_id, BreakOutcome::Dismissed, now);
+        }
+        *self = Self::default();
+    }
+}
+
+/// Handle to a running reminder loop
+struct BreakTask {
+    stop_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Break reminder service: watches continuous activity, shows desktop
+/// notifications and logs what became of them
+pub struct BreakService {
+    db: Arc<DatabasePool>,
+    config: watch::Sender<BreakConfig>,
+    state: Arc<Mutex<BreakState>>,
+    task: Mutex<Option<BreakTask>>,
+}
+
+impl BreakService {
+    pub fn new(db: Arc<DatabasePool>, config: BreakConfig) -> Self {
+        Self {
+            db,
+            config: watch::Sender::new(config),
+            state: Arc::new(Mutex::new(BreakState::default())),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Update configuration
+    pub fn update_config(&self, config: BreakConfig) {
+        self.config.send_replace(config);
+    }
+
+    /// Get the reminder status
+    pub fn status(&self) -> BreakStatus {
+        self.state.lock().unwrap().tracker.status(now())
+    }
+
+    /// Start watching activity
+    ///
+    /// Nothing is reminded while the feature is disabled.
+    pub fn start(&self, app_handle: AppHandle) {
+        let mut task = self.task.lock().unwrap();
+        if task.is_some() {
+            return;
+        }
+
+        let db = Arc::clone(&self.db);
+        let state = Arc::clone(&self.state);
+        let config_rx = self.config.subscribe();
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stop_rx => break,
+                }
+
+                let config = config_rx.borrow().clone();
+                if !config.enabled {
+                    state.lock().unwrap().reset(&db, now());
+                    continue;
+                }
+
+                let now = now();
+                let idle_ms = get_idle_time().as_millis() as i64;
+                let mut state = state.lock().unwrap();
+                match state.tracker.tick(now, idle_ms, &config) {
+                    Some(BreakEvent::Remind { active_ms }) => {
+                        state.reminder_id = db
+                            .write()
+                            .and_then(|db| {
+                                db.log_break_reminder(now, active_ms)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .map_err(|e| log::error!("Failed to log break reminder: {}", e))
+                            .ok();
+                        crate::notifications::show(
+                            &app_handle,
+                            "Time for a break",
+                            &format!(
+                                "You have been active for {} minutes.",
+                                active_ms / MINUTE_MS
+                            ),
+                        );
+                        let _ = app_handle.emit("break-reminder", state.tracker.status(now));
+                    }
+                    Some(BreakEvent::BreakTaken) => {
+                        resolve(&db, state.reminder_id.take(), BreakOutcome::Taken, now);
+                        let _ = app_handle.emit("break-taken", state.tracker.status(now));
+                    }
+                    None => {}
+                }
+            }
+        });
+
+        *task = Some(BreakTask { stop_tx, handle });
+    }
+
+    /// Stop watching activity
+    pub async fn stop(&self) {
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.stop_tx.send(());
+            if let Err(e) = task.handle.await {
+                log::error!("Break reminder task failed: {}", e);
+            }
+        }
+    }
+
+    /// Snooze the pending reminder
+    pub fn snooze(&self) -> BreakStatus {
+        let config = self.config.borrow().clone();
+        let now = now();
+        let mut state = self.state.lock().unwrap();
+        if state.tracker.reminder_pending {
+            resolve(&self.db, state.reminder_id, BreakOutcome::Snoozed, now);
+        }
+        state.tracker.snooze(now, &config);
+        state.tracker.status(now)
+    }
+
+    /// Dismiss the pending reminder
+    pub fn dismiss(&self) -> BreakStatus {
+        let now = now();
+        let mut state = self.state.lock().unwrap();
+        if state.tracker.reminder_pending {
+            resolve(
+                &self.db,
+                state.reminder_id.take(),
+                BreakOutcome::Dismissed,
+                now,
+            );
+        }
+        state.tracker.dismiss(now);
+        state.tracker.status(now)
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Log the outcome of a reminder, if any
+fn resolve(db: &DatabasePool, id: Option<i64>, outcome: BreakOutcome, now: i64) {
+    let Some(id) = id else {
+        return;
+    };
+    let resolved = db.write().and_then(|db| {
+        db.resolve_break_reminder(id, outcome, now)
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = resolved {
+        log::error!("Failed to log break reminder outcome: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: BreakConfig = BreakConfig {
+        enabled: true,
+        remind_after_minutes: 50,
+        break_minutes: 5,
+        snooze_minutes: 10,
+    };
+
+    #[test]
+    fn test_short_idle_does_not_reset() {
+        let mut tracker = BreakTracker::default();
+        assert_eq!(tracker.tick(0, 0, &CONFIG), None);
+        // Three idle minutes are not a break
+        assert_eq!(tracker.tick(30 * MINUTE_MS, 3 * MINUTE_MS, &CONFIG), None);
+
+        assert_eq!(
+            tracker.tick(50 * MINUTE_MS, 0, &CONFIG),
+            Some(BreakEvent::Remind {
+                active_ms: 50 * MINUTE_MS
+            })
+        );
+        // No second reminder while one is pending
+        assert_eq!(tracker.tick(55 * MINUTE_MS, 0, &CONFIG), None);
+
+        assert_eq!(
+            tracker.tick(60 * MINUTE_MS, 5 * MINUTE_MS, &CONFIG),
+            Some(BreakEvent::BreakTaken)
+        );
+        assert_eq!(tracker.status(60 * MINUTE_MS).active_ms, 0);
+    }
+
+    #[test]
+    fn test_snooze_and_dismiss() {
+        let mut tracker = BreakTracker::default();
+        tracker.tick(0, 0, &CONFIG);
+        assert!(tracker.tick(50 * MINUTE_MS, 0, &CONFIG).is_some());
+
+        tracker.snooze(50 * MINUTE_MS, &CONFIG);
+        assert_eq!(tracker.tick(55 * MINUTE_MS, 0, &CONFIG), None);
+        assert!(tracker.tick(60 * MINUTE_MS, 0, &CONFIG).is_some());
+
+        tracker.dismiss(61 * MINUTE_MS);
+        assert_eq!(tracker.tick(100 * MINUTE_MS, 0, &CONFIG), None);
+        assert!(tracker.tick(111 * MINUTE_MS, 0, &CONFIG).is_some());
+    }
+
+    #[test]
+    fn test_long_idle_without_activity_is_no_break() {
+        let mut tracker = BreakTracker::default();
+        assert_eq!(tracker.tick(0, 30 * MINUTE_MS, &CONFIG), None);
+    }
+
+    /// A state whose tracker shows a logged reminder
+    fn reminded(db: &DatabasePool) -> BreakState {
+        let mut state = BreakState::default();
+        state.tracker.tick(0, 0, &CONFIG);
+        state.tracker.tick(50 * MINUTE_MS, 0, &CONFIG);
+        state.reminder_id = Some(
+            db.write()
+                .unwrap()
+                .log_break_reminder(50 * MINUTE_MS, 50 * MINUTE_MS)
+                .unwrap(),
+        );
+        state
+    }
+
+    #[test]
+    fn test_disabling_dismisses_the_pending_reminder() {
+        let db = DatabasePool::open(":memory:", None).unwrap();
+        let mut state = reminded(&db);
+        state.reset(&db, 51 * MINUTE_MS);
+
+        assert_eq!(state.reminder_id, None);
+        let stats = db.write().unwrap().get_break_stats(0, i64::MAX).unwrap();
+        assert_eq!(stats.dismissed, 1);
+    }
+
+    #[test]
+    fn test_break_during_snooze_is_logged() {
+        let db = Arc::new(DatabasePool::open(":memory:", None).unwrap());
+        let service = BreakService::new(Arc::clone(&db), CONFIG);
+        *service.state.lock().unwrap() = reminded(&db);
+        service.snooze();
+
+        let mut state = service.state.lock().unwrap();
+        assert!(state.reminder_id.is_some());
+        // What the loop does once the user has been idle long enough
+        resolve(&db, state.reminder_id.take(), BreakOutcome::Taken, now());
+        let stats = db.write().unwrap().get_break_stats(0, i64::MAX).unwrap();
+        assert_eq!((stats.taken, stats.snoozed), (1, 0));
+    }
+}
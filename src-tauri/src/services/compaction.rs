@@ -0,0 +1,131 @@
+// Compaction Service Module
+// 时间线压缩服务
+
+use crate::database::{CompactionConfig, CompactionResult, DatabasePool, DayBoundaries};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{oneshot, watch};
+
+/// How often the job checks for finished days (seconds)
+const CHECK_INTERVAL_SECS: u64 = 600;
+
+/// Handle to a running compaction loop
+struct CompactionTask {
+    stop_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Nightly compaction: once a day is over, merges its touching app items
+/// of the same kind and absorbs blips
+pub struct CompactionService {
+    db: Arc<DatabasePool>,
+    config: watch::Sender<CompactionConfig>,
+    task: Mutex<Option<CompactionTask>>,
+}
+
+impl CompactionService {
+    pub fn new(db: Arc<DatabasePool>, config: CompactionConfig) -> Self {
+        Self {
+            db,
+            config: watch::Sender::new(config),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Update configuration
+    pub fn update_config(&self, config: CompactionConfig) {
+        self.config.send_replace(config);
+    }
+
+    /// Start checking for finished days
+    ///
+    /// Nothing is compacted while the nightly job is disabled.
+    pub fn start(&self, app_handle: AppHandle) {
+        let mut task = self.task.lock().unwrap();
+        if task.is_some() {
+            return;
+        }
+
+        let db = Arc::clone(&self.db);
+        let config_rx = self.config.subscribe();
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+
+            'run: loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stop_rx => break,
+                }
+
+                let config = config_rx.borrow().clone();
+                if !config.nightly {
+                    continue;
+                }
+
+                let days = db.read().and_then(|db| {
+                    let settings = db.get_settings().map_err(|e| e.to_string())?;
+                    DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)
+                });
+                let days = match days {
+                    Ok(days) => days,
+                    Err(e) => {
+                        log::error!("Failed to compact timeline: {}", e);
+                        continue;
+                    }
+                };
+                let today = days.day_start(chrono::Utc::now().timestamp_millis());
+
+                // One day per transaction, letting other writers in between
+                let mut total = CompactionResult {
+                    applied: true,
+                    ..Default::default()
+                };
+                loop {
+                    let step = db.write().and_then(|db| {
+                        db.compact_next_day(today, &days, &config)
+                            .map_err(|e| e.to_string())
+                    });
+                    match step {
+                        Ok(Some(result)) => {
+                            total.items_before += result.items_before;
+                            total.items_removed += result.items_removed;
+                            total.blips_absorbed += result.blips_absorbed;
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            log::error!("Failed to compact timeline: {}", e);
+                            break;
+                        }
+                    }
+                    tokio::select! {
+                        biased;
+                        _ = &mut stop_rx => break 'run,
+                        _ = tokio::task::yield_now() => {}
+                    }
+                }
+
+                if total.items_removed > 0 {
+                    log::info!("Compacted {} track items", total.items_removed);
+                    let _ = app_handle.emit("timeline-compacted", &total);
+                }
+            }
+        });
+
+        *task = Some(CompactionTask { stop_tx, handle });
+    }
+
+    /// Stop checking for finished days
+    pub async fn stop(&self) {
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.stop_tx.send(());
+            if let Err(e) = task.handle.await {
+                log::error!("Compaction task failed: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// Exclusions Module
+// 隐私排除规则模块
+
+use crate::services::{domain_matches, WindowInfo};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// App and title recorded for windows in `ExclusionMode::Private`
+pub const PRIVATE_LABEL: &str = "Private";
+
+/// What the tracker does while an excluded window is focused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExclusionMode {
+    /// Record no app item; status time is still recorded
+    Drop,
+    /// Record the time as a generic "Private" item without title or URL
+    Private,
+    /// Record nothing at all, as if tracking were paused
+    Pause,
+}
+
+/// A user-defined rule that keeps matching windows out of the database
+///
+/// Every condition that is set must match. Rules without conditions never
+/// match. Domain conditions only apply when URL tracking is on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExclusionRule {
+    /// App name, compared case-insensitively
+    #[serde(default)]
+    pub app: Option<String>,
+    /// Regular expression matched against the window title
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+    /// Domain; also matches its subdomains
+    #[serde(default)]
+    pub domain: Option<String>,
+    pub mode: ExclusionMode,
+}
+
+impl ExclusionRule {
+    fn app(app: &str, mode: ExclusionMode) -> Self {
+        Self {
+            app: Some(app.to_string()),
+            title_pattern: None,
+            domain: None,
+            mode,
+        }
+    }
+}
+
+/// Exclusions known out of the box
+pub fn default_exclusions() -> Vec<ExclusionRule> {
+    vec![
+        // Lock and login screens
+        ExclusionRule::app("loginwindow", ExclusionMode::Drop),
+        ExclusionRule::app("ScreenSaverEngine", ExclusionMode::Drop),
+        // Password managers
+        ExclusionRule::app("1Password", ExclusionMode::Private),
+        ExclusionRule::app("Bitwarden", ExclusionMode::Private),
+        ExclusionRule::app("KeePassXC", ExclusionMode::Private),
+        ExclusionRule::app("Keychain Access", ExclusionMode::Private),
+    ]
+}
+
+/// An exclusion rule with its title pattern compiled
+struct CompiledExclusion {
+    rule: ExclusionRule,
+    title: Option<Regex>,
+}
+
+impl CompiledExclusion {
+    fn compile(rule: &ExclusionRule) -> Result<Self, String> {
+        let title = rule
+            .title_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| format!("Invalid exclusion pattern: {}", e))?;
+
+        Ok(Self {
+            rule: rule.clone(),
+            title,
+        })
+    }
+
+    fn matches(&self, window: &WindowInfo) -> bool {
+        let rule = &self.rule;
+        if rule.app.is_none() && self.title.is_none() && rule.domain.is_none() {
+            return false;
+        }
+
+        if let Some(app) = &rule.app {
+            if !window.app_name.eq_ignore_ascii_case(app) {
+                return false;
+            }
+        }
+        if let Some(title) = &self.title {
+            if !title.is_match(&window.title) {
+                return false;
+            }
+        }
+        if let Some(domain) = &rule.domain {
+            if !window
+                .domain
+                .as_deref()
+                .is_some_and(|d| domain_matches(d, domain))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Decides which windows must not be recorded as-is
+#[derive(Default)]
+pub struct ExclusionFilter {
+    rules: Vec<CompiledExclusion>,
+}
+
+impl ExclusionFilter {
+    /// Build a filter; rules with invalid patterns are logged and skipped
+    pub fn new(rules: &[ExclusionRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| match CompiledExclusion::compile(rule) {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// Check that every rule's pattern compiles
+    pub fn validate(rules: &[ExclusionRule]) -> Result<(), String> {
+        rules
+            .iter()
+            .try_for_each(|rule| CompiledExclusion::compile(rule).map(|_| ()))
+    }
+
+    /// The mode of the first rule matching a window, if any
+    pub fn check(&self, window: &WindowInfo) -> Option<ExclusionMode> {
+        self.rules
+            .iter()
+            .find(|r| r.matches(window))
+            .map(|r| r.rule.mode)
+    }
+}
+
+/// A copy of a window with everything identifying removed
+pub fn private_window(window: &WindowInfo) -> WindowInfo {
+    WindowInfo::new(
+        PRIVATE_LABEL.to_string(),
+        PRIVATE_LABEL.to_string(),
+        window.process_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app: &str, title: &str, domain: Option<&str>) -> WindowInfo {
+        let mut window = WindowInfo::new(app.to_string(), title.to_string(), 1);
+        window.domain = domain.map(str::to_string);
+        window
+    }
+
+    #[test]
+    fn test_default_exclusions() {
+        let filter = ExclusionFilter::new(&default_exclusions());
+
+        assert_eq!(
+            filter.check(&window("loginwindow", "", None)),
+            Some(ExclusionMode::Drop)
+        );
+        assert_eq!(
+            filter.check(&window("1password", "Vault", None)),
+            Some(ExclusionMode::Private)
+        );
+        assert_eq!(filter.check(&window("Code", "main.rs", None)), None);
+    }
+
+    #[test]
+    fn test_title_and_domain_rules() {
+        let filter = ExclusionFilter::new(&[
+            ExclusionRule {
+                app: None,
+                title_pattern: Some("(?i)payslip".to_string()),
+                domain: None,
+                mode: ExclusionMode::Pause,
+            },
+            ExclusionRule {
+                app: Some("Safari".to_string()),
+                title_pattern: None,
+                domain: Some("mybank.com".to_string()),
+                mode: ExclusionMode::Private,
+            },
+            ExclusionRule {
+                app: None,
+                title_pattern: None,
+                domain: None,
+                mode: ExclusionMode::Drop,
+            },
+        ]);
+
+        assert_eq!(
+            filter.check(&window("Preview", "Payslip March.pdf", None)),
+            Some(ExclusionMode::Pause)
+        );
+        assert_eq!(
+            filter.check(&window("Safari", "Accounts", Some("online.mybank.com"))),
+            Some(ExclusionMode::Private)
+        );
+        assert_eq!(
+            filter.check(&window("Chrome", "Accounts", Some("mybank.com"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let rules = [ExclusionRule {
+            app: None,
+            title_pattern: Some("[".to_string()),
+            domain: None,
+            mode: ExclusionMode::Drop,
+        }];
+
+        assert!(ExclusionFilter::validate(&rules).is_err());
+        assert_eq!(
+            ExclusionFilter::new(&rules).check(&window("App", "[", None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_private_window_drops_details() {
+        let mut original = window("Safari", "Statement", Some("mybank.com"));
+        original.url = Some("https://mybank.com/statement".to_string());
+
+        let private = private_window(&original);
+        assert_eq!(private.app_name, PRIVATE_LABEL);
+        assert_eq!(private.title, PRIVATE_LABEL);
+        assert!(private.url.is_none());
+        assert!(private.domain.is_none());
+    }
+}
@@ -0,0 +1,531 @@
+// Focus Session Module
+// 番茄钟专注模块
+
+use crate::database::{DatabasePool, TrackItem, MIN_ITEM_DURATION};
+use crate::services::{get_active_window, WindowInfo};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{oneshot, watch};
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Focus session configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FocusConfig {
+    /// Length of a focus block in minutes
+    pub work_minutes: u64,
+    /// Length of a short break in minutes
+    pub short_break_minutes: u64,
+    /// Length of a long break in minutes
+    pub long_break_minutes: u64,
+    /// A long break follows every this many focus blocks
+    pub long_break_every: u32,
+    /// Alert when a blocklisted window is focused during a focus block
+    pub distraction_alerts: bool,
+    /// App names, or text in window titles (e.g. "YouTube"), to alert on
+    pub blocklist: Vec<String>,
+}
+
+impl Default for FocusConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+            distraction_alerts: false,
+            blocklist: Vec::new(),
+        }
+    }
+}
+
+impl FocusConfig {
+    /// Whether a window is on the blocklist
+    ///
+    /// Entries match app names exactly and titles as a substring, ignoring
+    /// case.
+    pub fn is_distraction(&self, window: &WindowInfo) -> bool {
+        let title = window.title.to_lowercase();
+        self.blocklist
+            .iter()
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| window.app_name.to_lowercase() == entry || title.contains(&entry))
+    }
+}
+
+/// Phase of a focus session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FocusPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl FocusPhase {
+    fn label(&self) -> &'static str {
+        match self {
+            FocusPhase::Work => "Focus",
+            FocusPhase::ShortBreak => "Short Break",
+            FocusPhase::LongBreak => "Long Break",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            FocusPhase::Work => "#ef4444",       // red
+            FocusPhase::ShortBreak => "#22c55e", // green
+            FocusPhase::LongBreak => "#3b82f6",  // blue
+        }
+    }
+
+    fn length_ms(&self, config: &FocusConfig) -> i64 {
+        let minutes = match self {
+            FocusPhase::Work => config.work_minutes,
+            FocusPhase::ShortBreak => config.short_break_minutes,
+            FocusPhase::LongBreak => config.long_break_minutes,
+        };
+        minutes.max(1) as i64 * MINUTE_MS
+    }
+}
+
+/// Focus session status for frontend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusStatus {
+    /// Current phase; `None` when no session runs
+    pub phase: Option<FocusPhase>,
+    /// Focus blocks finished in this session
+    pub completed_cycles: u32,
+    pub remaining_ms: i64,
+    pub is_paused: bool,
+}
+
+/// The focus session state machine
+///
+/// Phases run back to back until the session is stopped. Time spent
+/// paused is not recorded; each stretch of a phase becomes its own
+/// `FocusTrackItem`.
+#[derive(Debug, Default)]
+pub struct FocusTimer {
+    phase: Option<FocusPhase>,
+    completed_cycles: u32,
+    /// Start of the current unpaused stretch
+    begin_date: i64,
+    ends_at: i64,
+    paused_at: Option<i64>,
+}
+
+impl FocusTimer {
+    pub fn is_running(&self) -> bool {
+        self.phase.is_some()
+    }
+
+    pub fn status(&self, now: i64) -> FocusStatus {
+        let at = self.paused_at.unwrap_or(now);
+        FocusStatus {
+            phase: self.phase,
+            completed_cycles: self.completed_cycles,
+            remaining_ms: if self.is_running() {
+                (self.ends_at - at).max(0)
+            } else {
+                0
+            },
+            is_paused: self.paused_at.is_some(),
+        }
+    }
+
+    /// Start a session with a focus block; returns false if one runs
+    pub fn start(&mut self, now: i64, config: &FocusConfig) -> bool {
+        if self.is_running() {
+            return false;
+        }
+        *self = Self::default();
+        self.enter(FocusPhase::Work, now, config);
+        true
+    }
+
+    /// Pause; returns the stretch recorded so far
+    pub fn pause(&mut self, now: i64) -> Option<TrackItem> {
+        if !self.is_running() || self.paused_at.is_some() {
+            return None;
+        }
+        let item = self.record(now);
+        self.paused_at = Some(now);
+        item
+    }
+
+    /// Resume; the phase ends later by the time spent paused
+    pub fn resume(&mut self, now: i64) -> bool {
+        let Some(paused_at) = self.paused_at.take() else {
+            return false;
+        };
+        self.ends_at += now - paused_at;
+        self.begin_date = now;
+        true
+    }
+
+    /// End the current phase now and move on to the next
+    ///
+    /// Skipped focus blocks count towards the long break.
+    pub fn skip(&mut self, now: i64, config: &FocusConfig) -> Option<TrackItem> {
+        let phase = self.phase?;
+        let item = self.record(now);
+        self.advance(phase, now, config);
+        item
+    }
+
+    /// Move on when the current phase has run out
+    pub fn tick(&mut self, now: i64, config: &FocusConfig) -> Option<TrackItem> {
+        match self.phase {
+            Some(phase) if self.paused_at.is_none() && now >= self.ends_at => {
+                let ends_at = self.ends_at;
+                let item = self.record(ends_at);
+                self.advance(phase, ends_at, config);
+                item
+            }
+            _ => None,
+        }
+    }
+
+    /// End the session; returns the unrecorded rest of the current phase
+    pub fn stop(&mut self, now: i64) -> Option<TrackItem> {
+        let item = self.record(now);
+        *self = Self::default();
+        item
+    }
+
+    fn enter(&mut self, phase: FocusPhase, now: i64, config: &FocusConfig) {
+        self.phase = Some(phase);
+        self.begin_date = now;
+        self.ends_at = now + phase.length_ms(config);
+        self.paused_at = None;
+    }
+
+    fn advance(&mut self, finished: FocusPhase, now: i64, config: &FocusConfig) {
+        let next = match finished {
+            FocusPhase::Work => {
+                self.completed_cycles += 1;
+                if self
+                    .completed_cycles
+                    .is_multiple_of(config.long_break_every.max(1))
+                {
+                    FocusPhase::LongBreak
+                } else {
+                    FocusPhase::ShortBreak
+                }
+            }
+            FocusPhase::ShortBreak | FocusPhase::LongBreak => FocusPhase::Work,
+        };
+        self.enter(next, now, config);
+    }
+
+    /// The current stretch as an item, unless paused or too short
+    fn record(&self, end_date: i64) -> Option<TrackItem> {
+        let phase = self.phase?;
+        if self.paused_at.is_some() || end_date - self.begin_date < MIN_ITEM_DURATION {
+            return None;
+        }
+        // Breaks are numbered after the focus block before them
+        let cycle = match phase {
+            FocusPhase::Work => self.completed_cycles + 1,
+            FocusPhase::ShortBreak | FocusPhase::LongBreak => self.completed_cycles,
+        };
+        Some(TrackItem {
+            id: None,
+            app: phase.label().to_string(),
+            task_name: "FocusTrackItem".to_string(),
+            title: format!("{} #{}", phase.label(), cycle),
+            url: None,
+            domain: None,
+            color: Some(phase.color().to_string()),
+            begin_date: self.begin_date,
+            end_date,
+            productivity: None,
+        })
+    }
+}
+
+/// Handle to a running countdown loop
+struct FocusTask {
+    stop_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Focus session service: runs the countdown, records phases and sends
+/// `focus-changed` events
+pub struct FocusService {
+    db: Arc<DatabasePool>,
+    config: watch::Sender<FocusConfig>,
+    timer: Arc<Mutex<FocusTimer>>,
+    task: Mutex<Option<FocusTask>>,
+}
+
+impl FocusService {
+    pub fn new(db: Arc<DatabasePool>, config: FocusConfig) -> Self {
+        Self {
+            db,
+            config: watch::Sender::new(config),
+            timer: Arc::new(Mutex::new(FocusTimer::default())),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Get the current configuration
+    pub fn config(&self) -> FocusConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Update configuration
+    ///
+    /// New lengths apply from the next phase on.
+    pub fn update_config(&self, config: FocusConfig) {
+        self.config.send_replace(config);
+    }
+
+    /// Get the session status
+    pub fn status(&self) -> FocusStatus {
+        self.timer.lock().unwrap().status(now())
+    }
+
+    /// Start a session and its countdown loop
+    pub fn start(&self, app_handle: AppHandle) -> Result<FocusStatus, String> {
+        let config = self.config();
+        if !self.timer.lock().unwrap().start(now(), &config) {
+            return Err("A focus session is already running".to_string());
+        }
+
+        self.spawn_loop(app_handle.clone());
+        Ok(self.changed(&app_handle, None))
+    }
+
+    /// Stop the countdown loop but keep the session
+    ///
+    /// A phase that ends meanwhile is recorded once `resume_loop` runs.
+    pub async fn suspend_loop(&self) {
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.stop_tx.send(());
+            if let Err(e) = task.handle.await {
+                log::error!("Focus task failed: {}", e);
+            }
+        }
+    }
+
+    /// Restart the countdown loop of a session kept by `suspend_loop`
+    pub fn resume_loop(&self, app_handle: AppHandle) {
+        if self.timer.lock().unwrap().is_running() && self.task.lock().unwrap().is_none() {
+            self.spawn_loop(app_handle);
+        }
+    }
+
+    /// Run the countdown: record finished phases and watch for distractions
+    fn spawn_loop(&self, app: AppHandle) {
+        let db = Arc::clone(&self.db);
+        let timer = Arc::clone(&self.timer);
+        let config_rx = self.config.subscribe();
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            let mut alerted: Option<String> = None;
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stop_rx => break,
+                }
+
+                let config = config_rx.borrow().clone();
+                let (item, status) = {
+                    let mut timer = timer.lock().unwrap();
+                    let now = now();
+                    (timer.tick(now, &config), timer.status(now))
+                };
+                if let Some(item) = item {
+                    save_focus_item(&db, &app, &item);
+                    let _ = app.emit("focus-changed", &status);
+                }
+                crate::tray::show_focus(&app, Some(&status));
+
+                // Alert once per distracting window during focus blocks
+                if config.distraction_alerts
+                    && status.phase == Some(FocusPhase::Work)
+                    && !status.is_paused
+                {
+                    if let Ok(window) = get_active_window() {
+                        let key = format!("{}\u{0}{}", window.app_name, window.title);
+                        if config.is_distraction(&window) {
+                            if alerted.as_deref() != Some(key.as_str()) {
+                                let _ = app.emit("focus-distraction", &window);
+                                alerted = Some(key);
+                            }
+                        } else {
+                            alerted = None;
+                        }
+                    }
+                }
+            }
+        });
+
+        *self.task.lock().unwrap() = Some(FocusTask { stop_tx, handle });
+    }
+
+    /// Pause the countdown
+    pub fn pause(&self, app_handle: &AppHandle) -> FocusStatus {
+        let item = self.timer.lock().unwrap().pause(now());
+        self.changed(app_handle, item)
+    }
+
+    /// Resume the countdown
+    pub fn resume(&self, app_handle: &AppHandle) -> FocusStatus {
+        self.timer.lock().unwrap().resume(now());
+        self.changed(app_handle, None)
+    }
+
+    /// Skip to the next phase
+    pub fn skip(&self, app_handle: &AppHandle) -> FocusStatus {
+        let config = self.config();
+        let item = self.timer.lock().unwrap().skip(now(), &config);
+        self.changed(app_handle, item)
+    }
+
+    /// End the session
+    ///
+    /// Waits for the countdown loop to exit, then records the rest of the
+    /// current phase.
+    pub async fn stop(&self, app_handle: &AppHandle) -> FocusStatus {
+        self.suspend_loop().await;
+
+        let item = self.timer.lock().unwrap().stop(now());
+        let status = self.changed(app_handle, item);
+        crate::tray::show_focus(app_handle, None);
+        status
+    }
+
+    /// Save a finished stretch and send the new status
+    fn changed(&self, app_handle: &AppHandle, item: Option<TrackItem>) -> FocusStatus {
+        if let Some(item) = item {
+            save_focus_item(&self.db, app_handle, &item);
+        }
+        let status = self.status();
+        let _ = app_handle.emit("focus-changed", &status);
+        status
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Save a recorded phase and tell the frontend
+fn save_focus_item(db: &Arc<DatabasePool>, app_handle: &AppHandle, item: &TrackItem) {
+    let saved = db
+        .write()
+        .and_then(|db| db.create_track_item(item).map_err(|e| e.to_string()));
+    match saved {
+        Ok(item) => {
+            let _ = app_handle.emit("track-item-saved", &item);
+        }
+        Err(e) => log::error!("Failed to save focus item: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> FocusConfig {
+        FocusConfig {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_phases_cycle_with_long_breaks() {
+        let config = config();
+        let mut timer = FocusTimer::default();
+        assert!(timer.start(0, &config));
+        assert!(!timer.start(0, &config));
+
+        let mut phases = Vec::new();
+        let mut now = 0;
+        for _ in 0..4 {
+            now = timer.ends_at;
+            let item = timer.tick(now, &config).unwrap();
+            assert_eq!(item.task_name, "FocusTrackItem");
+            phases.push(timer.status(now).phase.unwrap());
+        }
+
+        assert_eq!(
+            phases,
+            vec![
+                FocusPhase::ShortBreak,
+                FocusPhase::Work,
+                FocusPhase::LongBreak,
+                FocusPhase::Work
+            ]
+        );
+        assert_eq!(timer.status(now).completed_cycles, 2);
+        assert!(timer.tick(now + MINUTE_MS, &config).is_none());
+    }
+
+    #[test]
+    fn test_paused_time_is_not_recorded() {
+        let config = config();
+        let mut timer = FocusTimer::default();
+        timer.start(0, &config);
+
+        let first = timer.pause(10 * MINUTE_MS).unwrap();
+        assert_eq!(first.end_date, 10 * MINUTE_MS);
+        assert_eq!(timer.status(40 * MINUTE_MS).remaining_ms, 15 * MINUTE_MS);
+        assert!(timer.tick(40 * MINUTE_MS, &config).is_none());
+
+        assert!(timer.resume(40 * MINUTE_MS));
+        let second = timer.tick(55 * MINUTE_MS, &config).unwrap();
+        assert_eq!(second.begin_date, 40 * MINUTE_MS);
+        assert_eq!(
+            timer.status(55 * MINUTE_MS).phase,
+            Some(FocusPhase::ShortBreak)
+        );
+    }
+
+    #[test]
+    fn test_skip_and_stop() {
+        let config = config();
+        let mut timer = FocusTimer::default();
+        timer.start(0, &config);
+
+        timer.skip(MINUTE_MS, &config).unwrap();
+        let status = timer.status(MINUTE_MS);
+        assert_eq!(status.phase, Some(FocusPhase::ShortBreak));
+        assert_eq!(status.remaining_ms, 5 * MINUTE_MS);
+
+        let item = timer.stop(2 * MINUTE_MS).unwrap();
+        assert_eq!(item.app, "Short Break");
+        assert!(!timer.is_running());
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let config = FocusConfig {
+            blocklist: vec!["youtube".to_string(), "Slack".to_string()],
+            ..Default::default()
+        };
+        let window =
+            |app: &str, title: &str| WindowInfo::new(app.to_string(), title.to_string(), 1);
+
+        assert!(config.is_distraction(&window("Safari", "Cats - YouTube")));
+        assert!(config.is_distraction(&window("slack", "general")));
+        assert!(!config.is_distraction(&window("Code", "main.rs - timlyzer")));
+    }
+}
@@ -0,0 +1,412 @@
+// Goals Module
+// 目标与限制服务
+
+use crate::database::{
+    Database, DatabasePool, DayBoundaries, Goal, GoalHistoryEntry, GoalKind, GoalPeriod,
+    GoalTarget, ItemLabels, TrackItem, MIN_ITEM_DURATION,
+};
+use crate::services::domain_matches;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+/// Where a goal stands in its current period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    pub goal_id: i64,
+    pub name: String,
+    pub kind: GoalKind,
+    pub period_start: i64,
+    pub period_end: i64,
+    /// Time counted so far (milliseconds)
+    pub progress: i64,
+    /// Target time (milliseconds)
+    pub duration: i64,
+    pub met: bool,
+}
+
+/// The current period of a goal: its first local date and `[from, to)`
+///
+/// `None` for daily goals that do not apply on today's weekday.
+pub fn goal_period(goal: &Goal, days: &DayBoundaries, now: i64) -> Option<(NaiveDate, i64, i64)> {
+    let date = days.date_of(now);
+    let (first, last) = match goal.period {
+        GoalPeriod::Day => {
+            let weekday = date.weekday().num_days_from_monday();
+            if !goal.weekdays.is_empty() && !goal.weekdays.contains(&weekday) {
+                return None;
+            }
+            (date, date)
+        }
+        GoalPeriod::Week => {
+            let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+            (monday, monday + Duration::days(6))
+        }
+    };
+    let (from, to) = days.date_range(first, last);
+    Some((first, from, to))
+}
+
+/// Whether an item's time counts toward a target
+pub fn target_matches(target: &GoalTarget, item: &TrackItem, labels: &ItemLabels) -> bool {
+    match target {
+        GoalTarget::App(app) => item.app == *app,
+        GoalTarget::Domain(domain) => item
+            .domain
+            .as_deref()
+            .is_some_and(|d| domain_matches(d, domain)),
+        GoalTarget::Project(id) => labels.project_ids.contains(id),
+        GoalTarget::Tag(id) => labels.tag_ids.contains(id),
+    }
+}
+
+/// A goal active in the current period
+struct TrackedGoal {
+    goal: Goal,
+    date: NaiveDate,
+    from: i64,
+    to: i64,
+    progress: i64,
+    /// Whether the limit has already been reported as exceeded
+    exceeded_sent: bool,
+}
+
+impl TrackedGoal {
+    fn status(&self) -> GoalProgress {
+        GoalProgress {
+            goal_id: self.goal.id.unwrap_or_default(),
+            name: self.goal.name.clone(),
+            kind: self.goal.kind,
+            period_start: self.from,
+            period_end: self.to,
+            progress: self.progress,
+            duration: self.goal.duration,
+            met: self.goal.is_met(self.progress),
+        }
+    }
+
+    fn is_exceeded(&self) -> bool {
+        self.goal.kind == GoalKind::Limit && self.progress > self.goal.duration
+    }
+
+    fn history(&self) -> GoalHistoryEntry {
+        GoalHistoryEntry {
+            goal_id: self.goal.id.unwrap_or_default(),
+            period_start: self.from,
+            date: self.date.format("%Y-%m-%d").to_string(),
+            progress: self.progress,
+            met: self.goal.is_met(self.progress),
+        }
+    }
+}
+
+/// Goals of the current periods, valid until `valid_until`
+struct GoalState {
+    goals: Vec<TrackedGoal>,
+    valid_until: i64,
+}
+
+/// Goal service: keeps the progress of enabled goals up to date as the
+/// tracker saves items, records it in the goal history and reports
+/// exceeded limits
+pub struct GoalService {
+    db: Arc<DatabasePool>,
+    /// `None` until first used
+    state: Mutex<Option<GoalState>>,
+}
+
+impl GoalService {
+    pub fn new(db: Arc<DatabasePool>) -> Self {
+        Self {
+            db,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Recompute progress from the database on next use
+    pub fn invalidate(&self) {
+        if let Some(state) = self.state.lock().unwrap().as_mut() {
+            state.valid_until = i64::MIN;
+        }
+    }
+
+    /// Progress of the goals active right now
+    pub fn progress(&self) -> Result<Vec<GoalProgress>, String> {
+        let mut state = self.state.lock().unwrap();
+        let (state, _) = self.current(&mut state, None)?;
+        Ok(state.goals.iter().map(TrackedGoal::status).collect())
+    }
+
+    /// Count a saved item toward the goals it matches
+    ///
+    /// Emits `goal-progress` for each goal that moved and `limit-exceeded`
+    /// once per period when a limit is passed.
+    pub fn record_item(&self, app_handle: &AppHandle, item: &TrackItem) {
+        if !matches!(item.task_name.as_str(), "AppTrackItem" | "LogTrackItem")
+            || item.end_date - item.begin_date < MIN_ITEM_DURATION
+        {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let state = match self.current(&mut state, Some(app_handle)) {
+            // A fresh load already counted the item
+            Ok((_, true)) => return,
+            Ok((state, false)) => state,
+            Err(e) => {
+                log::error!("Failed to load goals: {}", e);
+                return;
+            }
+        };
+
+        let labels = match item.id {
+            Some(id)
+                if state.goals.iter().any(|g| {
+                    matches!(g.goal.target, GoalTarget::Project(_) | GoalTarget::Tag(_))
+                }) =>
+            {
+                self.db
+                    .read()
+                    .and_then(|db| db.get_item_labels(&[id]).map_err(|e| e.to_string()))
+                    .map(|mut labels| labels.pop())
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to read item labels: {}", e);
+                        None
+                    })
+            }
+            _ => None,
+        }
+        .unwrap_or_default();
+
+        for tracked in &mut state.goals {
+            let counted = item.end_date.min(tracked.to) - item.begin_date.max(tracked.from);
+            if counted <= 0 || !target_matches(&tracked.goal.target, item, &labels) {
+                continue;
+            }
+            tracked.progress += counted;
+            self.save_history(tracked);
+            report(app_handle, tracked);
+        }
+    }
+
+    /// The loaded state, reloaded when stale; also returns whether it was
+    /// reloaded
+    fn current<'a>(
+        &self,
+        state: &'a mut Option<GoalState>,
+        app_handle: Option<&AppHandle>,
+    ) -> Result<(&'a mut GoalState, bool), String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        if state.as_ref().is_some_and(|s| now < s.valid_until) {
+            return Ok((state.as_mut().unwrap(), false));
+        }
+
+        let mut loaded = {
+            let db = self.db.read()?;
+            load(&db, now)?
+        };
+        // Limits are reported once per period; on the first load, ones
+        // exceeded before startup count as reported
+        let previous = state.take().map(|s| s.goals);
+        for tracked in &mut loaded.goals {
+            tracked.exceeded_sent = match &previous {
+                Some(previous) => previous.iter().any(|p| {
+                    p.goal.id == tracked.goal.id && p.from == tracked.from && p.exceeded_sent
+                }),
+                None => tracked.is_exceeded(),
+            };
+            self.save_history(tracked);
+            if let Some(app_handle) = app_handle {
+                report(app_handle, tracked);
+            }
+        }
+
+        Ok((state.insert(loaded), true))
+    }
+
+    fn save_history(&self, tracked: &TrackedGoal) {
+        let saved = self.db.write().and_then(|db| {
+            db.save_goal_history(&tracked.history())
+                .map_err(|e| e.to_string())
+        });
+        if let Err(e) = saved {
+            log::error!("Failed to save goal history: {}", e);
+        }
+    }
+}
+
+/// Compute the progress of every enabled goal active at `now`
+fn load(db: &Database, now: i64) -> Result<GoalState, String> {
+    let settings = db.get_settings().map_err(|e| e.to_string())?;
+    let days = DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)?;
+
+    let mut goals = Vec::new();
+    for goal in db.get_goals().map_err(|e| e.to_string())? {
+        if !goal.enabled {
+            continue;
+        }
+        let Some((date, from, to)) = goal_period(&goal, &days, now) else {
+            continue;
+        };
+        let progress = db
+            .get_goal_progress(&goal.target, from, to)
+            .map_err(|e| e.to_string())?;
+        goals.push(TrackedGoal {
+            goal,
+            date,
+            from,
+            to,
+            progress,
+            exceeded_sent: false,
+        });
+    }
+
+    Ok(GoalState {
+        goals,
+        valid_until: days.next_day(now),
+    })
+}
+
+/// Emit a goal's progress, and report a newly exceeded limit
+fn report(app_handle: &AppHandle, tracked: &mut TrackedGoal) {
+    let status = tracked.status();
+    let _ = app_handle.emit("goal-progress", &status);
+
+    if tracked.is_exceeded() && !tracked.exceeded_sent {
+        tracked.exceeded_sent = true;
+        crate::notifications::show(
+            app_handle,
+            "Limit exceeded",
+            &format!(
+                "{}: {} of {} minutes used.",
+                status.name,
+                status.progress / 60_000,
+                status.duration / 60_000
+            ),
+        );
+        let _ = app_handle.emit("limit-exceeded", &status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    const DAY: i64 = 86_400_000;
+    /// 2024-03-06, a Wednesday, 12:00 UTC
+    const WEDNESDAY_NOON: i64 = 1_709_726_400_000;
+
+    fn goal(period: GoalPeriod, weekdays: Vec<u32>) -> Goal {
+        Goal {
+            id: Some(1),
+            name: "Deep work".to_string(),
+            enabled: true,
+            kind: GoalKind::Goal,
+            period,
+            target: GoalTarget::Tag(1),
+            duration: 4 * 3_600_000,
+            weekdays,
+        }
+    }
+
+    #[test]
+    fn test_daily_goal_applies_on_its_weekdays() {
+        let days = DayBoundaries::new(Some("UTC"), 0).unwrap();
+        let weekdays = goal(GoalPeriod::Day, vec![0, 1, 2, 3, 4]);
+
+        let (date, from, to) = goal_period(&weekdays, &days, WEDNESDAY_NOON).unwrap();
+        assert_eq!(date.to_string(), "2024-03-06");
+        assert_eq!(
+            (from, to),
+            (WEDNESDAY_NOON - DAY / 2, WEDNESDAY_NOON + DAY / 2)
+        );
+
+        // Saturday
+        assert!(goal_period(&weekdays, &days, WEDNESDAY_NOON + 3 * DAY).is_none());
+        assert!(goal_period(
+            &goal(GoalPeriod::Day, vec![]),
+            &days,
+            WEDNESDAY_NOON + 3 * DAY
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_weekly_goal_runs_monday_to_sunday() {
+        let days = DayBoundaries::new(Some("UTC"), 0).unwrap();
+        let weekly = goal(GoalPeriod::Week, vec![]);
+
+        let (date, from, to) = goal_period(&weekly, &days, WEDNESDAY_NOON).unwrap();
+        assert_eq!(date.to_string(), "2024-03-04");
+        assert_eq!(to - from, 7 * DAY);
+        assert_eq!(
+            goal_period(&weekly, &days, from + 7 * DAY - 1).unwrap().1,
+            from
+        );
+    }
+
+    #[test]
+    fn test_target_matches() {
+        let labels = ItemLabels {
+            item_id: 1,
+            project_ids: vec![2],
+            tag_ids: vec![3],
+        };
+        let browser = TestItem::new(0, 1)
+            .id(1)
+            .app("Firefox")
+            .domain("youtube.com")
+            .build();
+
+        assert!(target_matches(
+            &GoalTarget::App("Firefox".to_string()),
+            &browser,
+            &labels
+        ));
+        assert!(target_matches(
+            &GoalTarget::Domain("youtube.com".to_string()),
+            &browser,
+            &labels
+        ));
+        assert!(target_matches(
+            &GoalTarget::Domain("youtube.com".to_string()),
+            &TestItem::new(0, 1)
+                .id(1)
+                .app("Firefox")
+                .domain("www.youtube.com")
+                .build(),
+            &labels
+        ));
+        assert!(!target_matches(
+            &GoalTarget::Domain("youtube.com".to_string()),
+            &TestItem::new(0, 1)
+                .id(1)
+                .app("Firefox")
+                .domain("notyoutube.com")
+                .build(),
+            &labels
+        ));
+        assert!(!target_matches(
+            &GoalTarget::Domain("youtube.com".to_string()),
+            &TestItem::new(0, 1).id(1).build(),
+            &labels
+        ));
+        assert!(target_matches(&GoalTarget::Project(2), &browser, &labels));
+        assert!(!target_matches(&GoalTarget::Tag(2), &browser, &labels));
+    }
+
+    #[test]
+    fn test_limit_is_met_until_exceeded() {
+        let limit = Goal {
+            kind: GoalKind::Limit,
+            duration: 30 * 60_000,
+            ..goal(GoalPeriod::Day, vec![])
+        };
+        assert!(limit.is_met(30 * 60_000));
+        assert!(!limit.is_met(30 * 60_000 + 1));
+        assert!(!goal(GoalPeriod::Day, vec![]).is_met(3_600_000));
+    }
+}
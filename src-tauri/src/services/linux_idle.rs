@@ -0,0 +1,312 @@
+// Linux Idle Detection Module
+// Linux 空闲检测模块
+//
+// Linux has no single API for "time since last input", so several backends
+// are probed at runtime and the first one that answers is kept:
+//
+// 1. XScreenSaver extension (X11 sessions only; under XWayland it only sees
+//    input sent to X clients, so it is skipped on Wayland)
+// 2. `org.gnome.Mutter.IdleMonitor` (GNOME, X11 and Wayland)
+// 3. `org.freedesktop.ScreenSaver` (KDE Plasma and others)
+// 4. systemd-logind session `IdleHint` (coarse fallback)
+
+use super::IdleBackend;
+use std::os::raw::c_int;
+use std::ptr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use x11_dl::xlib::{Display, Xlib};
+use x11_dl::xss::{XScreenSaverInfo, Xss};
+use zbus::blocking::Connection;
+use zbus::zvariant::{DynamicDeserialize, OwnedValue};
+
+/// Selected idle source (probed lazily on first use)
+static SOURCE: Mutex<Probe> = Mutex::new(Probe::Pending);
+
+/// How long to wait before probing again when nothing answered, e.g. when
+/// the app started before the display or session bus was up
+const REPROBE_AFTER: Duration = Duration::from_secs(60);
+
+enum Probe {
+    /// Not probed yet, or the last source stopped answering
+    Pending,
+    /// A working source was found
+    Active(Source),
+    /// Nothing answered when probed at the given time; the user is
+    /// considered active until a later probe finds a source
+    Unavailable(Instant),
+}
+
+impl Probe {
+    /// Probe again if nothing was tried yet or the last failure is old
+    fn refresh(&mut self) {
+        let due = match self {
+            Probe::Pending => true,
+            Probe::Active(_) => false,
+            Probe::Unavailable(at) => at.elapsed() >= REPROBE_AFTER,
+        };
+        if due {
+            *self = detect();
+        }
+    }
+}
+
+enum Source {
+    XScreenSaver(Box<XScreenSaver>),
+    Mutter(Connection),
+    ScreenSaver(Connection),
+    Logind(Connection),
+}
+
+impl Source {
+    fn backend(&self) -> IdleBackend {
+        match self {
+            Source::XScreenSaver(_) => IdleBackend::XScreenSaver,
+            Source::Mutter(_) => IdleBackend::MutterIdleMonitor,
+            Source::ScreenSaver(_) => IdleBackend::FreedesktopScreenSaver,
+            Source::Logind(_) => IdleBackend::LogindIdleHint,
+        }
+    }
+
+    fn idle_time(&self) -> Result<Duration, String> {
+        match self {
+            Source::XScreenSaver(xss) => xss.idle_time(),
+            Source::Mutter(conn) => call::<u64>(
+                conn,
+                "org.gnome.Mutter.IdleMonitor",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "org.gnome.Mutter.IdleMonitor",
+                "GetIdletime",
+            )
+            .map(Duration::from_millis),
+            // KDE reports milliseconds here despite the method name
+            Source::ScreenSaver(conn) => call::<u32>(
+                conn,
+                "org.freedesktop.ScreenSaver",
+                "/org/freedesktop/ScreenSaver",
+                "org.freedesktop.ScreenSaver",
+                "GetSessionIdleTime",
+            )
+            .map(|ms| Duration::from_millis(ms as u64)),
+            Source::Logind(conn) => logind_idle_time(conn),
+        }
+    }
+
+    fn open_x_screensaver() -> Result<Self, String> {
+        XScreenSaver::open().map(|xss| Source::XScreenSaver(Box::new(xss)))
+    }
+
+    fn open_mutter() -> Result<Self, String> {
+        Connection::session()
+            .map(Source::Mutter)
+            .map_err(|e| e.to_string())
+    }
+
+    fn open_screensaver() -> Result<Self, String> {
+        Connection::session()
+            .map(Source::ScreenSaver)
+            .map_err(|e| e.to_string())
+    }
+
+    fn open_logind() -> Result<Self, String> {
+        Connection::system()
+            .map(Source::Logind)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Get system idle time from the active backend
+pub fn get_idle_time() -> Duration {
+    let mut probe = SOURCE.lock().unwrap();
+    probe.refresh();
+
+    match &*probe {
+        Probe::Active(source) => match source.idle_time() {
+            Ok(idle) => idle,
+            Err(e) => {
+                log::warn!(
+                    "Idle backend {:?} stopped answering, re-probing: {}",
+                    source.backend(),
+                    e
+                );
+                *probe = Probe::Pending;
+                Duration::ZERO
+            }
+        },
+        _ => Duration::ZERO,
+    }
+}
+
+/// Get the idle backend currently in use
+pub fn get_idle_backend() -> IdleBackend {
+    let mut probe = SOURCE.lock().unwrap();
+    probe.refresh();
+
+    match &*probe {
+        Probe::Active(source) => source.backend(),
+        _ => IdleBackend::Unavailable,
+    }
+}
+
+/// Probe the available backends in order of preference
+fn detect() -> Probe {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+    let x11 = std::env::var_os("DISPLAY").is_some();
+
+    let mut candidates: Vec<fn() -> Result<Source, String>> = Vec::new();
+    if x11 && !wayland {
+        candidates.push(Source::open_x_screensaver);
+    }
+    candidates.push(Source::open_mutter);
+    candidates.push(Source::open_screensaver);
+    candidates.push(Source::open_logind);
+
+    for open in candidates {
+        // A backend only counts once it has answered a real query
+        match open().and_then(|source| source.idle_time().map(|_| source)) {
+            Ok(source) => {
+                log::info!("Using {:?} for idle detection", source.backend());
+                return Probe::Active(source);
+            }
+            Err(e) => log::debug!("Idle backend unavailable: {}", e),
+        }
+    }
+
+    log::warn!(
+        "No idle detection backend available; retrying in {} seconds",
+        REPROBE_AFTER.as_secs()
+    );
+    Probe::Unavailable(Instant::now())
+}
+
+// ============================================================================
+// XScreenSaver
+// ============================================================================
+
+/// Handle to the X11 MIT-SCREEN-SAVER extension
+///
+/// libX11/libXss are loaded at runtime so Wayland-only systems without them
+/// still start.
+struct XScreenSaver {
+    xlib: Xlib,
+    xss: Xss,
+    display: *mut Display,
+    info: *mut XScreenSaverInfo,
+}
+
+// The display is only ever used behind the `SOURCE` mutex
+unsafe impl Send for XScreenSaver {}
+
+impl XScreenSaver {
+    fn open() -> Result<Self, String> {
+        let xlib = Xlib::open().map_err(|e| e.to_string())?;
+        let xss = Xss::open().map_err(|e| e.to_string())?;
+
+        unsafe {
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            if display.is_null() {
+                return Err("Failed to open X display".to_string());
+            }
+
+            let mut event_base: c_int = 0;
+            let mut error_base: c_int = 0;
+            if (xss.XScreenSaverQueryExtension)(display, &mut event_base, &mut error_base) == 0 {
+                (xlib.XCloseDisplay)(display);
+                return Err("X server lacks the MIT-SCREEN-SAVER extension".to_string());
+            }
+
+            let info = (xss.XScreenSaverAllocInfo)();
+            if info.is_null() {
+                (xlib.XCloseDisplay)(display);
+                return Err("Failed to allocate XScreenSaverInfo".to_string());
+            }
+
+            Ok(Self {
+                xlib,
+                xss,
+                display,
+                info,
+            })
+        }
+    }
+
+    fn idle_time(&self) -> Result<Duration, String> {
+        unsafe {
+            let root = (self.xlib.XDefaultRootWindow)(self.display);
+            if (self.xss.XScreenSaverQueryInfo)(self.display, root, self.info) == 0 {
+                return Err("XScreenSaverQueryInfo failed".to_string());
+            }
+            // `c_ulong` is not 64-bit on every target
+            #[allow(clippy::unnecessary_cast)]
+            Ok(Duration::from_millis((*self.info).idle as u64))
+        }
+    }
+}
+
+impl Drop for XScreenSaver {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xlib.XFree)(self.info as *mut _);
+            (self.xlib.XCloseDisplay)(self.display);
+        }
+    }
+}
+
+// ============================================================================
+// D-Bus
+// ============================================================================
+
+/// Call a no-argument D-Bus method and decode its single return value
+fn call<T>(
+    conn: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    method: &str,
+) -> Result<T, String>
+where
+    T: for<'d> DynamicDeserialize<'d>,
+{
+    let reply = conn
+        .call_method(Some(destination), path, Some(interface), method, &())
+        .map_err(|e| e.to_string())?;
+    reply.body().deserialize::<T>().map_err(|e| e.to_string())
+}
+
+/// Read a property of the caller's logind session
+///
+/// Goes through `org.freedesktop.DBus.Properties` directly so every poll
+/// sees a fresh value instead of a proxy's cache.
+fn logind_property<T>(conn: &Connection, name: &str) -> Result<T, String>
+where
+    T: TryFrom<OwnedValue>,
+    T::Error: std::fmt::Display,
+{
+    let reply = conn
+        .call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1/session/auto",
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &("org.freedesktop.login1.Session", name),
+        )
+        .map_err(|e| e.to_string())?;
+    let value: OwnedValue = reply.body().deserialize().map_err(|e| e.to_string())?;
+    T::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Idle time derived from logind's `IdleHint`/`IdleSinceHint`
+///
+/// Only as precise as the desktop environment's own idle reporting.
+fn logind_idle_time(conn: &Connection) -> Result<Duration, String> {
+    let idle: bool = logind_property(conn, "IdleHint")?;
+    if !idle {
+        return Ok(Duration::ZERO);
+    }
+
+    // Microseconds since the epoch (CLOCK_REALTIME)
+    let since: u64 = logind_property(conn, "IdleSinceHint")?;
+    let now = chrono::Utc::now().timestamp_micros().max(0) as u64;
+    Ok(Duration::from_micros(now.saturating_sub(since)))
+}
@@ -2,9 +2,29 @@
 // 核心业务服务
 
 pub mod active_window;
+pub mod breaks;
+pub mod compaction;
+pub mod exclusions;
+pub mod focus;
+pub mod goals;
+#[cfg(target_os = "linux")]
+mod linux_idle;
+pub mod redaction;
+pub mod rollups;
+pub mod rule_engine;
+pub mod sleep_monitor;
 pub mod state_monitor;
 pub mod tracker;
 
 pub use active_window::*;
+pub use breaks::*;
+pub use compaction::*;
+pub use exclusions::*;
+pub use focus::*;
+pub use goals::*;
+pub use redaction::*;
+pub use rollups::*;
+pub use rule_engine::*;
+pub use sleep_monitor::*;
 pub use state_monitor::*;
 pub use tracker::*;
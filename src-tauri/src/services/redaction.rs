@@ -0,0 +1,329 @@
+// Redaction Module
+// 标题与 URL 脱敏模块
+
+use crate::database::{Database, TrackItem};
+use crate::services::WindowInfo;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+/// Replacement for masked email addresses
+const EMAIL_MASK: &str = "[email]";
+/// Replacement for masked tokens
+const TOKEN_MASK: &str = "[token]";
+/// Shortest run of token characters that is masked
+const MIN_TOKEN_LENGTH: usize = 32;
+
+/// A user-defined regex replacement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionPattern {
+    pub pattern: String,
+    /// Replacement text; may refer to groups as `$1`
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// Which scrubbers run on titles and URLs before they are stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionConfig {
+    /// Remove query strings and fragments from URLs
+    pub strip_url_query: bool,
+    /// Replace email addresses
+    pub mask_emails: bool,
+    /// Replace long hex/base64 runs that look like tokens
+    pub mask_tokens: bool,
+    /// Cut titles to this many characters
+    pub max_title_length: Option<usize>,
+    /// User replacements, applied before the built-in scrubbers
+    pub patterns: Vec<RedactionPattern>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            strip_url_query: true,
+            mask_emails: true,
+            mask_tokens: true,
+            max_title_length: Some(256),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Applies a `RedactionConfig` to text
+pub struct Redactor {
+    config: RedactionConfig,
+    patterns: Vec<(Regex, String)>,
+    email: Regex,
+    token: Regex,
+    url_query: Regex,
+}
+
+impl Redactor {
+    /// Build a redactor; user patterns that fail to compile are logged
+    /// and skipped
+    pub fn new(config: &RedactionConfig) -> Self {
+        let patterns = config
+            .patterns
+            .iter()
+            .filter_map(|p| match Regex::new(&p.pattern) {
+                Ok(re) => Some((re, p.replacement.clone())),
+                Err(e) => {
+                    log::warn!("Invalid redaction pattern: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            config: config.clone(),
+            patterns,
+            email: Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap(),
+            token: Regex::new(&format!(r"[A-Za-z0-9+_=-]{{{},}}", MIN_TOKEN_LENGTH)).unwrap(),
+            url_query: Regex::new(r"(https?://[^\s?#]*)[?#]\S*").unwrap(),
+        }
+    }
+
+    /// Check that every user pattern compiles
+    pub fn validate(config: &RedactionConfig) -> Result<(), String> {
+        config.patterns.iter().try_for_each(|p| {
+            Regex::new(&p.pattern)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid redaction pattern: {}", e))
+        })
+    }
+
+    /// Scrub free text
+    fn scrub(&self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        for (re, replacement) in &self.patterns {
+            text = re.replace_all(&text, replacement.as_str()).into_owned();
+        }
+        if self.config.strip_url_query {
+            text = self.url_query.replace_all(&text, "$1").into_owned();
+        }
+        if self.config.mask_emails {
+            text = self.email.replace_all(&text, EMAIL_MASK).into_owned();
+        }
+        if self.config.mask_tokens {
+            text = self
+                .token
+                .replace_all(&text, |caps: &Captures| {
+                    let run = &caps[0];
+                    // Long words and identifiers are not tokens
+                    let looks_random = run.chars().any(|c| c.is_ascii_digit())
+                        && run.chars().any(|c| c.is_ascii_alphabetic());
+                    if looks_random {
+                        TOKEN_MASK.to_string()
+                    } else {
+                        run.to_string()
+                    }
+                })
+                .into_owned();
+        }
+
+        text
+    }
+
+    /// Redact a window title
+    pub fn redact_title(&self, title: &str) -> String {
+        let title = self.scrub(title);
+
+        match self.config.max_title_length {
+            Some(max) if title.chars().count() > max => {
+                let mut cut: String = title.chars().take(max).collect();
+                cut.push('…');
+                cut
+            }
+            _ => title,
+        }
+    }
+
+    /// Redact a URL
+    pub fn redact_url(&self, url: &str) -> String {
+        let url = if self.config.strip_url_query {
+            url.split(['?', '#']).next().unwrap_or(url)
+        } else {
+            url
+        };
+        self.scrub(url)
+    }
+
+    /// Redact the title and URL of a window in place
+    pub fn redact_window(&self, window: &mut WindowInfo) {
+        window.title = self.redact_title(&window.title);
+        window.url = window.url.as_deref().map(|u| self.redact_url(u));
+    }
+
+    /// Redact a stored item in place; returns whether anything changed
+    pub fn redact_item(&self, item: &mut TrackItem) -> bool {
+        let title = self.redact_title(&item.title);
+        let url = item.url.as_deref().map(|u| self.redact_url(u));
+        let changed = title != item.title || url != item.url;
+
+        item.title = title;
+        item.url = url;
+        changed
+    }
+}
+
+/// Item types whose titles and URLs are scrubbed; titles of any of them
+/// can be edited by hand
+const REDACTED_TASK_NAMES: &[&str] = &[
+    "AppTrackItem",
+    "LogTrackItem",
+    "StatusTrackItem",
+    "FocusTrackItem",
+];
+
+/// Re-run redaction over the stored items in a time range
+///
+/// Changes are written in one transaction. Returns the number of items
+/// that changed.
+pub fn redact_history(
+    db: &Database,
+    redactor: &Redactor,
+    from: i64,
+    to: i64,
+) -> rusqlite::Result<i64> {
+    let mut changed = Vec::new();
+
+    for task_name in REDACTED_TASK_NAMES {
+        for mut item in db.find_all_day_items(from, to, task_name)? {
+            if redactor.redact_item(&mut item) {
+                changed.push(item);
+            }
+        }
+    }
+    db.update_track_items(&changed)?;
+
+    Ok(changed.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestItem;
+
+    #[test]
+    fn test_builtin_scrubbers() {
+        let redactor = Redactor::new(&RedactionConfig::default());
+
+        assert_eq!(
+            redactor.redact_title("Re: offer - jane.doe@example.com - Mail"),
+            "Re: offer - [email] - Mail"
+        );
+        assert_eq!(
+            redactor.redact_title(
+                "reset 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+            ),
+            "reset [token]"
+        );
+        assert_eq!(
+            redactor.redact_title("/Users/jane/Documents/Projects/timlyzer/src-tauri"),
+            "/Users/jane/Documents/Projects/timlyzer/src-tauri"
+        );
+        assert_eq!(
+            redactor.redact_title("See https://example.com/a?token=abc#x now"),
+            "See https://example.com/a now"
+        );
+        assert_eq!(
+            redactor.redact_url("https://example.com/login?session=abc#top"),
+            "https://example.com/login"
+        );
+    }
+
+    #[test]
+    fn test_truncate_and_custom_patterns() {
+        let redactor = Redactor::new(&RedactionConfig {
+            max_title_length: Some(12),
+            patterns: vec![RedactionPattern {
+                pattern: r"Invoice #\d+".to_string(),
+                replacement: "Invoice".to_string(),
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(redactor.redact_title("Invoice #4411"), "Invoice");
+        assert_eq!(
+            redactor.redact_title("A rather long title"),
+            "A rather lon…"
+        );
+    }
+
+    #[test]
+    fn test_disabled_scrubbers_keep_text() {
+        let redactor = Redactor::new(&RedactionConfig {
+            strip_url_query: false,
+            mask_emails: false,
+            mask_tokens: false,
+            max_title_length: None,
+            patterns: Vec::new(),
+        });
+
+        assert_eq!(
+            redactor.redact_title("jane@example.com"),
+            "jane@example.com"
+        );
+        assert_eq!(
+            redactor.redact_url("https://x.org/?q=1"),
+            "https://x.org/?q=1"
+        );
+    }
+
+    #[test]
+    fn test_redact_item_reports_changes() {
+        let redactor = Redactor::new(&RedactionConfig::default());
+        let mut item = TestItem::new(0, 1_000)
+            .id(1)
+            .app("Safari")
+            .title("Inbox")
+            .domain("mail.example.com")
+            .build();
+        item.url = Some("https://mail.example.com/?q=1".to_string());
+
+        assert!(redactor.redact_item(&mut item));
+        assert_eq!(item.url.as_deref(), Some("https://mail.example.com/"));
+        assert!(!redactor.redact_item(&mut item));
+    }
+
+    #[test]
+    fn test_redact_history() {
+        let db = Database::new(":memory:").unwrap();
+        TestItem::new(0, 1_000)
+            .app("Mail")
+            .title("From boss@example.com")
+            .create(&db);
+        TestItem::new(0, 1_000)
+            .app("Mail")
+            .title("Calendar")
+            .create(&db);
+        TestItem::new(0, 1_000)
+            .app("Mail")
+            .task_name("LogTrackItem")
+            .title("Call with boss@example.com")
+            .create(&db);
+
+        let redactor = Redactor::new(&RedactionConfig::default());
+        assert_eq!(redact_history(&db, &redactor, 0, 10_000).unwrap(), 2);
+
+        let (_, total, _) = db
+            .search_items(0, 10_000, None, Some("example"), 50, 0)
+            .unwrap();
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let config = RedactionConfig {
+            patterns: vec![RedactionPattern {
+                pattern: "(".to_string(),
+                replacement: String::new(),
+            }],
+            ..Default::default()
+        };
+        assert!(Redactor::validate(&config).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+// Rollup Service Module
+// 每日汇总刷新服务
+
+use crate::database::{DatabasePool, DayBoundaries};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::oneshot;
+
+/// How often changed days are rebuilt (seconds)
+const REFRESH_INTERVAL_SECS: u64 = 60;
+
+/// Handle to a running refresh loop
+struct RollupTask {
+    stop_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Keeps the daily rollups up to date in the background, so statistics
+/// only ever read
+pub struct RollupService {
+    db: Arc<DatabasePool>,
+    task: Mutex<Option<RollupTask>>,
+}
+
+impl RollupService {
+    pub fn new(db: Arc<DatabasePool>) -> Self {
+        Self {
+            db,
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Start rebuilding changed days
+    pub fn start(&self) {
+        let mut task = self.task.lock().unwrap();
+        if task.is_some() {
+            return;
+        }
+
+        let db = Arc::clone(&self.db);
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(REFRESH_INTERVAL_SECS));
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stop_rx => break,
+                }
+
+                let refreshed = db.write().and_then(|db| {
+                    let settings = db.get_settings().map_err(|e| e.to_string())?;
+                    let days =
+                        DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)?;
+                    db.refresh_rollups(&days).map_err(|e| e.to_string())
+                });
+                match refreshed {
+                    Ok(0) => {}
+                    Ok(days) => log::debug!("Rebuilt rollups of {} days", days),
+                    Err(e) => log::error!("Failed to refresh rollups: {}", e),
+                }
+            }
+        });
+
+        *task = Some(RollupTask { stop_tx, handle });
+    }
+
+    /// Stop rebuilding changed days
+    pub async fn stop(&self) {
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.stop_tx.send(());
+            if let Err(e) = task.handle.await {
+                log::error!("Rollup task failed: {}", e);
+            }
+        }
+    }
+}
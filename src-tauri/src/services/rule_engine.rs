@@ -0,0 +1,523 @@
+// Rule Engine Module
+// 自动分类规则引擎
+
+use crate::database::{Database, DayBoundaries, Rule, RuleActions, RuleConditions, TrackItem};
+use crate::services::domain_matches;
+use chrono::Timelike;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A rule with its patterns compiled
+struct CompiledRule {
+    conditions: RuleConditions,
+    title: Option<Regex>,
+    url: Option<Regex>,
+    actions: RuleActions,
+}
+
+impl CompiledRule {
+    fn compile(rule: &Rule) -> Result<Self, String> {
+        let compile = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| format!("Invalid pattern in rule '{}': {}", rule.name, e))
+        };
+
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            title: compile(&rule.conditions.title_pattern)?,
+            url: compile(&rule.conditions.url_pattern)?,
+            actions: rule.actions.clone(),
+        })
+    }
+
+    fn matches(&self, item: &TrackItem, days: &DayBoundaries) -> bool {
+        let c = &self.conditions;
+
+        if let Some(app) = &c.app {
+            if !item.app.eq_ignore_ascii_case(app) {
+                return false;
+            }
+        }
+        if let Some(title) = &self.title {
+            if !title.is_match(&item.title) {
+                return false;
+            }
+        }
+        if let Some(domain) = &c.domain {
+            if !item
+                .domain
+                .as_deref()
+                .is_some_and(|d| domain_matches(d, domain))
+            {
+                return false;
+            }
+        }
+        if let Some(url) = &self.url {
+            if !item.url.as_deref().is_some_and(|u| url.is_match(u)) {
+                return false;
+            }
+        }
+        if c.start_minute.is_some() || c.end_minute.is_some() {
+            let time = days.local_time(item.begin_date);
+            let minute = time.hour() * 60 + time.minute();
+            let start = c.start_minute.unwrap_or(0);
+            let end = c.end_minute.unwrap_or(24 * 60);
+            let inside = if start <= end {
+                start <= minute && minute < end
+            } else {
+                minute >= start || minute < end
+            };
+            if !inside {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Evaluates the user's classification rules against track items
+#[derive(Default)]
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>,
+    /// Time zone that time-of-day conditions are read in
+    days: DayBoundaries,
+}
+
+impl RuleEngine {
+    /// Build an engine from stored rules
+    ///
+    /// Disabled rules are left out, as are rules with invalid patterns
+    /// (those are logged).
+    pub fn new(rules: &[Rule], days: DayBoundaries) -> Self {
+        let mut rules: Vec<&Rule> = rules.iter().filter(|r| r.enabled).collect();
+        rules.sort_by_key(|r| r.position);
+
+        let rules = rules
+            .into_iter()
+            .filter_map(|rule| match CompiledRule::compile(rule) {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Self { rules, days }
+    }
+
+    /// Check that a rule's patterns compile
+    pub fn validate(rule: &Rule) -> Result<(), String> {
+        CompiledRule::compile(rule).map(|_| ())
+    }
+
+    /// Work out what the rules do to an item
+    ///
+    /// For each action the first matching rule that sets it wins; tags from
+    /// all matching rules are combined.
+    pub fn classify(&self, item: &TrackItem) -> RuleActions {
+        let mut result = RuleActions::default();
+
+        for rule in self.rules.iter().filter(|r| r.matches(item, &self.days)) {
+            let actions = &rule.actions;
+            result.project_id = result.project_id.or(actions.project_id);
+            result.color = result.color.take().or_else(|| actions.color.clone());
+            result.productivity = result.productivity.or(actions.productivity);
+            for tag_id in &actions.tag_ids {
+                if !result.tag_ids.contains(tag_id) {
+                    result.tag_ids.push(*tag_id);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Whether the engine has any active rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Outcome of re-applying rules to stored history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleApplyResult {
+    /// App items in the range matched by at least one rule
+    pub items_matched: i64,
+    /// Items whose classification differs from the rules, including ones
+    /// left with anything set by rules that no longer match
+    pub items_changed: i64,
+    /// Whether changes were written
+    pub applied: bool,
+}
+
+/// Re-classify the app items in a time range
+///
+/// Projects, tags, colors and productivity set by rules are replaced, so
+/// edited rules take effect; what was set by hand is kept. All changes are written in one
+/// transaction. With `dry_run` nothing is written; the result says how
+/// many items would change.
+pub fn reapply_rules(
+    db: &Database,
+    engine: &RuleEngine,
+    from: i64,
+    to: i64,
+    dry_run: bool,
+) -> rusqlite::Result<RuleApplyResult> {
+    let mut result = RuleApplyResult {
+        applied: !dry_run,
+        ..Default::default()
+    };
+    let mut changes = Vec::new();
+
+    for item in db.find_all_day_items(from, to, "AppTrackItem")? {
+        let Some(id) = item.id else {
+            continue;
+        };
+        let actions = engine.classify(&item);
+        if !actions.is_empty() {
+            result.items_matched += 1;
+        }
+
+        let labels = db.get_item_labels(&[id])?.pop().unwrap_or_default();
+        let by_rule = db.get_rule_marks(id)?;
+
+        let stale = by_rule
+            .project_ids
+            .iter()
+            .any(|&p| actions.project_id != Some(p))
+            || by_rule.tag_ids.iter().any(|t| !actions.tag_ids.contains(t))
+            || (by_rule.color && actions.color.is_none())
+            || (by_rule.productivity && actions.productivity.is_none());
+        let changed = stale
+            || actions
+                .color
+                .as_ref()
+                .is_some_and(|c| item.color.as_ref() != Some(c))
+            || actions
+                .productivity
+                .is_some_and(|p| item.productivity != Some(p))
+            || actions
+                .project_id
+                .is_some_and(|p| !labels.project_ids.contains(&p))
+            || actions.tag_ids.iter().any(|t| !labels.tag_ids.contains(t));
+        if !changed {
+            continue;
+        }
+        result.items_changed += 1;
+        changes.push((id, actions));
+    }
+
+    if !dry_run {
+        db.replace_rule_actions(&changes)?;
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Productivity, TestItem};
+
+    fn rule(position: i64, conditions: RuleConditions, actions: RuleActions) -> Rule {
+        Rule {
+            id: None,
+            name: format!("rule {}", position),
+            enabled: true,
+            position,
+            conditions,
+            actions,
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let engine = RuleEngine::new(
+            &[
+                rule(
+                    2,
+                    RuleConditions::default(),
+                    RuleActions {
+                        productivity: Some(Productivity::Neutral),
+                        tag_ids: vec![2],
+                        ..Default::default()
+                    },
+                ),
+                rule(
+                    1,
+                    RuleConditions {
+                        domain: Some("github.com".to_string()),
+                        ..Default::default()
+                    },
+                    RuleActions {
+                        productivity: Some(Productivity::Productive),
+                        project_id: Some(7),
+                        tag_ids: vec![1],
+                        ..Default::default()
+                    },
+                ),
+            ],
+            DayBoundaries::default(),
+        );
+
+        let gist = TestItem::new(0, 60_000)
+            .app("Safari")
+            .title("PR")
+            .domain("gist.github.com")
+            .build();
+        let actions = engine.classify(&gist);
+        assert_eq!(actions.productivity, Some(Productivity::Productive));
+        assert_eq!(actions.project_id, Some(7));
+        assert_eq!(actions.tag_ids, vec![1, 2]);
+
+        let news = TestItem::new(0, 60_000)
+            .app("Safari")
+            .title("News")
+            .domain("example.com")
+            .build();
+        let actions = engine.classify(&news);
+        assert_eq!(actions.productivity, Some(Productivity::Neutral));
+        assert_eq!(actions.project_id, None);
+    }
+
+    #[test]
+    fn test_conditions_must_all_match() {
+        let engine = RuleEngine::new(
+            &[rule(
+                0,
+                RuleConditions {
+                    app: Some("code".to_string()),
+                    title_pattern: Some(r"\.rs\b".to_string()),
+                    ..Default::default()
+                },
+                RuleActions {
+                    color: Some("#ff0000".to_string()),
+                    ..Default::default()
+                },
+            )],
+            DayBoundaries::default(),
+        );
+
+        assert!(!engine
+            .classify(&TestItem::new(0, 60_000).build())
+            .is_empty());
+        assert!(engine
+            .classify(&TestItem::new(0, 60_000).title("main.py").build())
+            .is_empty());
+        assert!(engine
+            .classify(&TestItem::new(0, 60_000).app("Xcode").build())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_and_disabled_rules_are_skipped() {
+        let mut invalid = rule(
+            0,
+            RuleConditions {
+                title_pattern: Some("(".to_string()),
+                ..Default::default()
+            },
+            RuleActions {
+                project_id: Some(1),
+                ..Default::default()
+            },
+        );
+        assert!(RuleEngine::validate(&invalid).is_err());
+        assert!(
+            RuleEngine::new(std::slice::from_ref(&invalid), DayBoundaries::default()).is_empty()
+        );
+
+        invalid.conditions.title_pattern = None;
+        invalid.enabled = false;
+        assert!(RuleEngine::new(&[invalid], DayBoundaries::default()).is_empty());
+    }
+
+    #[test]
+    fn test_time_of_day_uses_configured_zone() {
+        let engine = RuleEngine::new(
+            &[rule(
+                0,
+                RuleConditions {
+                    start_minute: Some(9 * 60),
+                    end_minute: Some(17 * 60),
+                    ..Default::default()
+                },
+                RuleActions {
+                    tag_ids: vec![1],
+                    ..Default::default()
+                },
+            )],
+            DayBoundaries::new(Some("Asia/Tokyo"), 0).unwrap(),
+        );
+
+        // 01:00 UTC is 10:00 in Tokyo
+        let mut working = TestItem::new(0, 60_000).build();
+        working.begin_date = 3_600_000;
+        assert_eq!(engine.classify(&working).tag_ids, vec![1]);
+
+        // 12:00 UTC is 21:00 in Tokyo
+        working.begin_date = 12 * 3_600_000;
+        assert!(engine.classify(&working).is_empty());
+    }
+
+    #[test]
+    fn test_reapply_replaces_rule_labels() {
+        let db = Database::new(":memory:").unwrap();
+        let old = db.create_project("Old", None).unwrap();
+        let new = db.create_project("New", None).unwrap();
+        let manual = db.create_tag("manual", None).unwrap();
+        let id = TestItem::new(0, 60_000).create(&db);
+
+        let code = RuleConditions {
+            app: Some("Code".to_string()),
+            ..Default::default()
+        };
+        let first = RuleEngine::new(
+            &[rule(
+                0,
+                code.clone(),
+                RuleActions {
+                    project_id: Some(old.id),
+                    ..Default::default()
+                },
+            )],
+            DayBoundaries::default(),
+        );
+        reapply_rules(&db, &first, 0, 120_000, false).unwrap();
+        db.tag_items(manual.id, &[id]).unwrap();
+
+        // The rule now files the item under another project
+        let edited = RuleEngine::new(
+            &[rule(
+                0,
+                code,
+                RuleActions {
+                    project_id: Some(new.id),
+                    ..Default::default()
+                },
+            )],
+            DayBoundaries::default(),
+        );
+        let result = reapply_rules(&db, &edited, 0, 120_000, false).unwrap();
+        assert_eq!(result.items_changed, 1);
+
+        let labels = db.get_item_labels(&[id]).unwrap();
+        assert_eq!(labels[0].project_ids, vec![new.id]);
+        assert_eq!(labels[0].tag_ids, vec![manual.id]);
+
+        // Without any rule, only the manual tag is left
+        let none = RuleEngine::new(&[], DayBoundaries::default());
+        reapply_rules(&db, &none, 0, 120_000, false).unwrap();
+        let labels = db.get_item_labels(&[id]).unwrap();
+        assert!(labels[0].project_ids.is_empty());
+        assert_eq!(labels[0].tag_ids, vec![manual.id]);
+    }
+
+    #[test]
+    fn test_reapply_resets_rule_color_and_productivity() {
+        let db = Database::new(":memory:").unwrap();
+        db.update_track_item_color("Code", "#0000ff").unwrap();
+        let ids: Vec<i64> = ["main.rs", "lib.rs"]
+            .iter()
+            .map(|title| {
+                TestItem::new(0, 60_000)
+                    .title(title)
+                    .color("#0000ff")
+                    .create(&db)
+            })
+            .collect();
+
+        let distracting = RuleEngine::new(
+            &[rule(
+                0,
+                RuleConditions {
+                    app: Some("Code".to_string()),
+                    ..Default::default()
+                },
+                RuleActions {
+                    color: Some("#ff0000".to_string()),
+                    productivity: Some(Productivity::Distracting),
+                    ..Default::default()
+                },
+            )],
+            DayBoundaries::default(),
+        );
+        reapply_rules(&db, &distracting, 0, 120_000, false).unwrap();
+
+        // Set by hand on the second item
+        let stored = |id: i64| {
+            db.find_all_day_items(0, i64::MAX, "AppTrackItem")
+                .unwrap()
+                .into_iter()
+                .find(|i| i.id == Some(id))
+                .unwrap()
+        };
+        let mut edited = stored(ids[1]);
+        edited.productivity = Some(Productivity::Productive);
+        db.update_track_item(&edited).unwrap();
+
+        let none = RuleEngine::new(&[], DayBoundaries::default());
+        let result = reapply_rules(&db, &none, 0, 120_000, false).unwrap();
+        assert_eq!(result.items_changed, 2);
+
+        let first = stored(ids[0]);
+        assert_eq!(first.color.as_deref(), Some("#0000ff"));
+        assert_eq!(first.productivity, None);
+        let second = stored(ids[1]);
+        assert_eq!(second.color.as_deref(), Some("#0000ff"));
+        assert_eq!(second.productivity, Some(Productivity::Productive));
+
+        assert_eq!(
+            reapply_rules(&db, &none, 0, 120_000, false)
+                .unwrap()
+                .items_changed,
+            0
+        );
+    }
+
+    #[test]
+    fn test_reapply_rules_dry_run() {
+        let db = Database::new(":memory:").unwrap();
+        let project = db.create_project("Acme", None).unwrap();
+        TestItem::new(0, 60_000).create(&db);
+        TestItem::new(0, 60_000)
+            .app("Slack")
+            .title("general")
+            .create(&db);
+
+        let engine = RuleEngine::new(
+            &[rule(
+                0,
+                RuleConditions {
+                    app: Some("Code".to_string()),
+                    ..Default::default()
+                },
+                RuleActions {
+                    project_id: Some(project.id),
+                    productivity: Some(Productivity::Productive),
+                    ..Default::default()
+                },
+            )],
+            DayBoundaries::default(),
+        );
+
+        let preview = reapply_rules(&db, &engine, 0, 120_000, true).unwrap();
+        assert_eq!(preview.items_matched, 1);
+        assert_eq!(preview.items_changed, 1);
+        assert!(!preview.applied);
+
+        reapply_rules(&db, &engine, 0, 120_000, false).unwrap();
+        let again = reapply_rules(&db, &engine, 0, 120_000, true).unwrap();
+        assert_eq!(again.items_matched, 1);
+        assert_eq!(again.items_changed, 0);
+
+        let items = db.find_all_day_items(0, 120_000, "AppTrackItem").unwrap();
+        assert_eq!(items[0].productivity, Some(Productivity::Productive));
+    }
+}
@@ -0,0 +1,165 @@
+// Sleep Monitor Module
+// 系统休眠检测模块
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// A gap is treated as sleep when the wall clock advanced this many polling
+/// intervals between two ticks...
+const SLEEP_GAP_INTERVALS: i64 = 5;
+/// ...and by at least this much (milliseconds)
+const SLEEP_GAP_MIN_MS: i64 = 30_000;
+
+/// Detects system sleep/resume
+///
+/// Sleep is detected from wall-clock gaps between tracker ticks (the tokio
+/// interval runs on a monotonic clock that stops while suspended). On Linux,
+/// logind's `PrepareForSleep` signal is used as well, which also catches
+/// sleeps too short to show up as a gap.
+pub struct SleepMonitor {
+    /// When the system announced it is going to sleep (0 = not pending)
+    suspended_at: AtomicI64,
+    /// When the system announced it has resumed (0 = not resumed yet)
+    resumed_at: AtomicI64,
+}
+
+impl SleepMonitor {
+    /// Create a sleep monitor and start listening for OS sleep signals
+    pub fn start() -> Arc<Self> {
+        let monitor = Arc::new(Self {
+            suspended_at: AtomicI64::new(0),
+            resumed_at: AtomicI64::new(0),
+        });
+
+        #[cfg(target_os = "linux")]
+        logind::watch(Arc::clone(&monitor));
+
+        monitor
+    }
+
+    /// Record that the system is about to sleep
+    pub fn mark_suspended(&self, at: i64) {
+        self.resumed_at.store(0, Ordering::SeqCst);
+        self.suspended_at.store(at, Ordering::SeqCst);
+    }
+
+    /// Record that the system has resumed
+    pub fn mark_resumed(&self, at: i64) {
+        self.resumed_at.store(at, Ordering::SeqCst);
+    }
+
+    /// Check whether the system slept since the previous tick
+    ///
+    /// Returns when the sleep started, or `None` if there was no sleep.
+    pub fn check(&self, last_tick: i64, now: i64, polling_interval_secs: u64) -> Option<i64> {
+        if self.resumed_at.load(Ordering::SeqCst) != 0 {
+            let suspended_at = self.suspended_at.swap(0, Ordering::SeqCst);
+            self.resumed_at.store(0, Ordering::SeqCst);
+            if suspended_at != 0 {
+                return Some(suspended_at.max(last_tick).min(now));
+            }
+        }
+
+        if is_sleep_gap(last_tick, now, polling_interval_secs) {
+            Some(last_tick)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the time between two ticks is too long to be scheduling jitter
+pub fn is_sleep_gap(last_tick: i64, now: i64, polling_interval_secs: u64) -> bool {
+    let threshold =
+        (polling_interval_secs as i64 * 1000 * SLEEP_GAP_INTERVALS).max(SLEEP_GAP_MIN_MS);
+    now - last_tick > threshold
+}
+
+#[cfg(target_os = "linux")]
+mod logind {
+    use super::SleepMonitor;
+    use std::sync::Arc;
+    use zbus::blocking::{Connection, Proxy};
+
+    /// Listen for `org.freedesktop.login1.Manager.PrepareForSleep`
+    pub fn watch(monitor: Arc<SleepMonitor>) {
+        let spawned = std::thread::Builder::new()
+            .name("sleep-monitor".to_string())
+            .spawn(move || {
+                if let Err(e) = listen(&monitor) {
+                    log::warn!("logind sleep signals unavailable: {}", e);
+                }
+            });
+
+        if let Err(e) = spawned {
+            log::warn!("Failed to start sleep monitor: {}", e);
+        }
+    }
+
+    fn listen(monitor: &SleepMonitor) -> zbus::Result<()> {
+        let conn = Connection::system()?;
+        let proxy = Proxy::new(
+            &conn,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+        )?;
+
+        for signal in proxy.receive_signal("PrepareForSleep")? {
+            let going_to_sleep: bool = signal.body().deserialize()?;
+            let now = chrono::Utc::now().timestamp_millis();
+            if going_to_sleep {
+                log::info!("System is going to sleep");
+                monitor.mark_suspended(now);
+            } else {
+                log::info!("System resumed from sleep");
+                monitor.mark_resumed(now);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> SleepMonitor {
+        SleepMonitor {
+            suspended_at: AtomicI64::new(0),
+            resumed_at: AtomicI64::new(0),
+        }
+    }
+
+    #[test]
+    fn test_regular_ticks_are_not_sleep() {
+        let monitor = monitor();
+        assert_eq!(monitor.check(0, 3_000, 3), None);
+        assert_eq!(monitor.check(0, 10_000, 3), None);
+    }
+
+    #[test]
+    fn test_wall_clock_gap_is_sleep() {
+        let monitor = monitor();
+        assert_eq!(monitor.check(1_000, 3_600_000, 3), Some(1_000));
+    }
+
+    #[test]
+    fn test_gap_threshold_scales_with_polling_interval() {
+        assert!(!is_sleep_gap(0, 60_000, 30));
+        assert!(is_sleep_gap(0, 151_000, 30));
+    }
+
+    #[test]
+    fn test_signalled_sleep_is_reported_once() {
+        let monitor = monitor();
+        monitor.mark_suspended(2_000);
+        // Still pending until the resume signal arrives
+        assert_eq!(monitor.check(1_000, 4_000, 3), None);
+
+        monitor.mark_resumed(9_000);
+        assert_eq!(monitor.check(4_000, 10_000, 3), Some(4_000));
+        assert_eq!(monitor.check(10_000, 13_000, 3), None);
+    }
+}
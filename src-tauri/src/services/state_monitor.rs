@@ -28,6 +28,26 @@ impl std::fmt::Display for SystemState {
     }
 }
 
+/// Source used to measure idle time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdleBackend {
+    /// macOS IOKit `HIDIdleTime`
+    IoKit,
+    /// Windows `GetLastInputInfo`
+    Win32,
+    /// X11 MIT-SCREEN-SAVER extension
+    XScreenSaver,
+    /// GNOME `org.gnome.Mutter.IdleMonitor` over D-Bus
+    MutterIdleMonitor,
+    /// `org.freedesktop.ScreenSaver` over D-Bus (KDE and others)
+    FreedesktopScreenSaver,
+    /// systemd-logind session `IdleHint`
+    LogindIdleHint,
+    /// No idle source; the user is always considered active
+    Unavailable,
+}
+
 /// State monitor for tracking system idle state
 pub struct StateMonitor {
     /// Current system state
@@ -235,12 +255,7 @@ pub fn get_idle_time() -> Duration {
 
 #[cfg(target_os = "linux")]
 pub fn get_idle_time() -> Duration {
-    // For Linux, we would use X11's XScreenSaver extension
-    // This is a simplified implementation
-    // In production, use the `x11` crate
-
-    // Try to read from /proc or use XScreenSaver
-    Duration::ZERO
+    super::linux_idle::get_idle_time()
 }
 
 #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
@@ -248,6 +263,27 @@ pub fn get_idle_time() -> Duration {
     Duration::ZERO
 }
 
+/// Get the platform backend used by `get_idle_time`
+#[cfg(target_os = "macos")]
+pub fn get_idle_backend() -> IdleBackend {
+    IdleBackend::IoKit
+}
+
+#[cfg(target_os = "windows")]
+pub fn get_idle_backend() -> IdleBackend {
+    IdleBackend::Win32
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_idle_backend() -> IdleBackend {
+    super::linux_idle::get_idle_backend()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn get_idle_backend() -> IdleBackend {
+    IdleBackend::Unavailable
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,12 +1,25 @@
 // Tracker Service Module
 // 核心追踪服务模块
 
-use crate::database::{Database, TrackItem};
-use crate::services::{get_active_window, get_idle_time, StateMonitor, SystemState, WindowInfo};
+use crate::commands::AppSettings;
+use crate::database::{
+    DatabasePool, DayBoundaries, Rule, RuleActions, TrackItem, MIN_ITEM_DURATION,
+};
+use crate::services::{
+    capture_browser_url, default_browsers, default_exclusions, get_active_window, get_idle_time,
+    private_window, BrowserConfig, ExclusionFilter, ExclusionMode, ExclusionRule, GoalService,
+    IdleBackend, RedactionConfig, Redactor, RuleEngine, SleepMonitor, StateMonitor, SystemState,
+    WindowInfo,
+};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
 use tauri::{AppHandle, Emitter};
+use tokio::sync::{oneshot, watch};
+
+/// How often open items are written back to the database (milliseconds)
+const HEARTBEAT_INTERVAL_MS: i64 = 15_000;
 
 /// Tracker configuration
 #[derive(Debug, Clone)]
@@ -17,6 +30,12 @@ pub struct TrackerConfig {
     pub idle_threshold: u64,
     /// Whether to track URLs (for browsers)
     pub track_urls: bool,
+    /// Browsers URLs can be read from
+    pub browsers: Vec<BrowserConfig>,
+    /// Windows that must not be recorded as-is
+    pub exclusions: Vec<ExclusionRule>,
+    /// Scrubbing applied to titles and URLs before they are stored
+    pub redaction: RedactionConfig,
 }
 
 impl Default for TrackerConfig {
@@ -25,38 +44,87 @@ impl Default for TrackerConfig {
             polling_interval: 3,
             idle_threshold: 300,
             track_urls: false,
+            browsers: default_browsers(),
+            exclusions: default_exclusions(),
+            redaction: RedactionConfig::default(),
         }
     }
 }
 
+impl From<&AppSettings> for TrackerConfig {
+    fn from(settings: &AppSettings) -> Self {
+        Self {
+            polling_interval: settings.polling_interval,
+            idle_threshold: settings.idle_threshold,
+            track_urls: settings.track_urls,
+            browsers: settings.browsers.clone(),
+            exclusions: settings.exclusions.clone(),
+            redaction: settings.redaction.clone(),
+        }
+    }
+}
+
+/// Handle to a running tracking loop
+struct TrackerTask {
+    /// Tells the loop to flush open items and exit
+    stop_tx: oneshot::Sender<()>,
+    /// Resolves once the loop has exited
+    handle: JoinHandle<()>,
+}
+
 /// Tracker service for automatic time tracking
 pub struct TrackerService {
     /// Reference to database
-    db: Arc<Mutex<Database>>,
+    db: Arc<DatabasePool>,
     /// State monitor for idle detection
     state_monitor: Arc<StateMonitor>,
-    /// Tracker configuration
-    config: RwLock<TrackerConfig>,
+    /// Sleep/resume detection
+    sleep_monitor: Arc<SleepMonitor>,
+    /// Tracker configuration (the running loop subscribes to changes)
+    config: watch::Sender<TrackerConfig>,
+    /// Classification rules applied to new app items
+    rules: Arc<RwLock<RuleEngine>>,
+    /// Goals that saved app items count toward
+    goals: Arc<GoalService>,
     /// Whether tracking is running
     is_running: Arc<AtomicBool>,
     /// Whether tracking is paused
     is_paused: Arc<AtomicBool>,
+    /// The running tracking loop, if any
+    task: Mutex<Option<TrackerTask>>,
 }
 
 impl TrackerService {
     /// Create a new tracker service
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
-        let config = TrackerConfig::default();
+    pub fn new(db: Arc<DatabasePool>, goals: Arc<GoalService>, config: TrackerConfig) -> Self {
         let state_monitor = Arc::new(StateMonitor::with_threshold(Duration::from_secs(
             config.idle_threshold,
         )));
 
+        let rules = db
+            .read()
+            .and_then(|db| {
+                let rules = db.get_rules().map_err(|e| e.to_string())?;
+                let settings = db.get_settings().map_err(|e| e.to_string())?;
+                let days =
+                    DayBoundaries::new(settings.timezone.as_deref(), settings.day_start_hour)?;
+                Ok(RuleEngine::new(&rules, days))
+            })
+            .unwrap_or_else(|e| {
+                log::error!("Failed to load rules: {}", e);
+                RuleEngine::default()
+            });
+
         Self {
             db,
             state_monitor,
-            config: RwLock::new(config),
+            sleep_monitor: SleepMonitor::start(),
+            config: watch::Sender::new(config),
+            rules: Arc::new(RwLock::new(rules)),
+            goals,
             is_running: Arc::new(AtomicBool::new(false)),
             is_paused: Arc::new(AtomicBool::new(false)),
+            task: Mutex::new(None),
         }
     }
 
@@ -75,13 +143,29 @@ impl TrackerService {
         self.state_monitor.get_state()
     }
 
+    /// Get the current configuration
+    pub fn config(&self) -> TrackerConfig {
+        self.config.borrow().clone()
+    }
+
     /// Update configuration
+    ///
+    /// Takes effect on the running loop immediately.
     pub fn update_config(&self, config: TrackerConfig) {
         self.state_monitor.set_idle_threshold(config.idle_threshold);
-        *self.config.write().unwrap() = config;
+        self.config.send_replace(config);
         log::info!("Tracker config updated");
     }
 
+    /// Replace the classification rules
+    ///
+    /// Applies to app items opened from now on. Time-of-day conditions
+    /// are read in the zone of `days`.
+    pub fn set_rules(&self, rules: &[Rule], days: DayBoundaries) {
+        *self.rules.write().unwrap() = RuleEngine::new(rules, days);
+        log::info!("Tracker rules updated");
+    }
+
     /// Start the tracking loop
     pub fn start(&self, app_handle: AppHandle) {
         if self.is_running.swap(true, Ordering::SeqCst) {
@@ -93,44 +177,73 @@ impl TrackerService {
 
         let db = Arc::clone(&self.db);
         let state_monitor = Arc::clone(&self.state_monitor);
-        let config = self.config.read().unwrap().clone();
+        let sleep_monitor = Arc::clone(&self.sleep_monitor);
+        let rules = Arc::clone(&self.rules);
+        let goals = Arc::clone(&self.goals);
+        let mut config_rx = self.config.subscribe();
+        let mut config = config_rx.borrow_and_update().clone();
 
         // Clone flags for the async task
-        let is_running = Arc::clone(&self.is_running);
         let is_paused = Arc::clone(&self.is_paused);
 
-        // Store references for stopping
-        // Note: In a real implementation, we'd need a way to signal the task to stop
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
 
-        tauri::async_runtime::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(config.polling_interval));
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut interval = polling_interval(&config);
 
             // Initialize tracking state
             let mut current_window: Option<WindowInfo> = None;
             let mut current_app_item: Option<TrackItem> = None;
             let mut current_status_item: Option<TrackItem> = None;
+            let mut exclusions = ExclusionFilter::new(&config.exclusions);
+            let mut redactor = Redactor::new(&config.redaction);
+            let mut current_exclusion: Option<ExclusionMode> = None;
+            let mut focus_paused = false;
             let mut last_state = SystemState::Online;
+            let mut last_tick = chrono::Utc::now().timestamp_millis();
+            let mut last_heartbeat = last_tick;
 
             loop {
-                interval.tick().await;
-
-                // Check if we should stop
-                if !is_running.load(Ordering::SeqCst) {
-                    break;
+                // Wait for the next tick, or stop (also when the sender is dropped)
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stop_rx => break,
+                    Ok(()) = config_rx.changed() => {
+                        let new_config = config_rx.borrow_and_update().clone();
+                        if new_config.polling_interval != config.polling_interval {
+                            log::info!(
+                                "Polling interval changed to {} seconds",
+                                new_config.polling_interval
+                            );
+                            interval = polling_interval(&new_config);
+                        }
+                        if new_config.exclusions != config.exclusions {
+                            exclusions = ExclusionFilter::new(&new_config.exclusions);
+                            // Re-check the focused window against the new rules
+                            current_window = None;
+                        }
+                        if new_config.redaction != config.redaction {
+                            redactor = Redactor::new(&new_config.redaction);
+                        }
+                        config = new_config;
+                        continue;
+                    }
                 }
 
+                let now = chrono::Utc::now().timestamp_millis();
+                let slept_at = sleep_monitor.check(last_tick, now, config.polling_interval);
+                last_tick = now;
+
                 // Check if paused
                 if is_paused.load(Ordering::SeqCst) {
                     // If we were tracking, save and clear state
                     if let Some(mut item) = current_app_item.take() {
-                        let now = chrono::Utc::now().timestamp_millis();
                         item.end_date = now;
-                        if let Err(e) = save_track_item(&db, &item) {
+                        if let Err(e) = save_app_item(&db, &goals, &app_handle, &item) {
                             log::error!("Failed to save app item directly before pause: {}", e);
                         }
                     }
                     if let Some(mut item) = current_status_item.take() {
-                        let now = chrono::Utc::now().timestamp_millis();
                         item.end_date = now;
                         if let Err(e) = save_track_item(&db, &item) {
                             log::error!("Failed to save status item before pause: {}", e);
@@ -142,15 +255,50 @@ impl TrackerService {
                     continue;
                 }
 
-                let now = chrono::Utc::now().timestamp_millis();
+                // Handle system sleep: close open items where the machine went
+                // to sleep and record the gap as OFFLINE
+                if let Some(slept_at) = slept_at {
+                    log::info!("System was asleep for {} seconds", (now - slept_at) / 1000);
+
+                    if let Some(mut item) = current_app_item.take() {
+                        item.end_date = slept_at.max(item.begin_date);
+                        if let Err(e) = save_app_item(&db, &goals, &app_handle, &item) {
+                            log::error!("Failed to save app item before sleep: {}", e);
+                        }
+                        let _ = app_handle.emit("track-item-saved", &item);
+                    }
+                    if let Some(mut item) = current_status_item.take() {
+                        item.end_date = slept_at.max(item.begin_date);
+                        if let Err(e) = save_track_item(&db, &item) {
+                            log::error!("Failed to save status item before sleep: {}", e);
+                        }
+                    }
+
+                    let offline_item = status_item(SystemState::Offline, slept_at, now);
+                    if let Err(e) = save_track_item(&db, &offline_item) {
+                        log::error!("Failed to save offline item: {}", e);
+                    }
+                    let _ = app_handle.emit("track-item-saved", &offline_item);
+
+                    // The state check below moves us back to ONLINE/IDLE and
+                    // opens a fresh status item
+                    state_monitor.set_offline();
+                    last_state = SystemState::Offline;
+                    current_window = None;
+                }
 
                 // Check idle state
                 let idle_time = get_idle_time();
                 let state_changed = state_monitor.update(idle_time);
                 let current_state = state_monitor.get_state();
 
-                // Handle state changes
-                if state_changed || last_state != current_state {
+                // Handle state changes; also reopen the status item once a
+                // paused window loses focus
+                if !focus_paused
+                    && (state_changed
+                        || last_state != current_state
+                        || current_status_item.is_none())
+                {
                     // Save current status item if exists
                     if let Some(mut item) = current_status_item.take() {
                         item.end_date = now;
@@ -160,17 +308,8 @@ impl TrackerService {
                     }
 
                     // Create new status item
-                    current_status_item = Some(TrackItem {
-                        id: None,
-                        app: current_state.to_string(),
-                        task_name: "StatusTrackItem".to_string(),
-                        title: current_state.to_string(),
-                        url: None,
-                        domain: None,
-                        color: Some(state_to_color(current_state)),
-                        begin_date: now,
-                        end_date: now,
-                    });
+                    current_status_item =
+                        Some(open_track_item(&db, status_item(current_state, now, now)));
 
                     // Emit event to frontend
                     let _ = app_handle.emit("state-changed", current_state);
@@ -183,64 +322,87 @@ impl TrackerService {
                     item.end_date = now;
                 }
 
+                // Periodically persist open items so a crash loses little time
+                if now - last_heartbeat >= HEARTBEAT_INTERVAL_MS {
+                    for item in [&current_app_item, &current_status_item]
+                        .into_iter()
+                        .flatten()
+                    {
+                        if let Err(e) = heartbeat_track_item(&db, item) {
+                            log::error!("Failed to write heartbeat: {}", e);
+                        }
+                    }
+                    last_heartbeat = now;
+                }
+
                 // Only track app when not idle
                 if current_state == SystemState::Online {
                     match get_active_window() {
-                        Ok(window) => {
-                            // Filter out system windows like loginwindow
-                            if window.app_name == "loginwindow"
-                                || window.app_name == "ScreenSaverEngine"
-                            {
-                                // If we entered login screen, close current app item
-                                if let Some(mut item) = current_app_item.take() {
-                                    item.end_date = now;
-                                    if let Err(e) = save_track_item(&db, &item) {
-                                        log::error!(
-                                            "Failed to save app item on system window: {}",
-                                            e
-                                        );
-                                    }
-                                    let _ = app_handle.emit("track-item-saved", &item);
-                                }
-                                current_window = None;
-                                continue;
-                            }
-
+                        Ok(mut window) => {
                             let window_changed = current_window
                                 .as_ref()
                                 .map(|w| w.app_name != window.app_name || w.title != window.title)
                                 .unwrap_or(true);
 
                             if window_changed {
+                                // Only ask the browser for its URL when allowed
+                                if config.track_urls {
+                                    capture_browser_url(&mut window, &config.browsers);
+                                }
+
                                 // Save current app item if exists
                                 if let Some(mut item) = current_app_item.take() {
                                     item.end_date = now;
-                                    if let Err(e) = save_track_item(&db, &item) {
+                                    if let Err(e) = save_app_item(&db, &goals, &app_handle, &item) {
                                         log::error!("Failed to save app item: {}", e);
                                     }
                                     // Emit event to frontend
                                     let _ = app_handle.emit("track-item-saved", &item);
                                 }
 
-                                // Get color for this app
-                                let color = get_app_color(&db, &window.app_name);
-
-                                // Create new app item
-                                current_app_item = Some(TrackItem {
-                                    id: None,
-                                    app: window.app_name.clone(),
-                                    task_name: "AppTrackItem".to_string(),
-                                    title: window.title.clone(),
-                                    url: window.url.clone(),
-                                    domain: window.domain.clone(),
-                                    color,
-                                    begin_date: now,
-                                    end_date: now,
-                                });
-
-                                // Emit window change event
-                                let _ = app_handle.emit("window-changed", &window);
+                                current_exclusion = exclusions.check(&window);
+                                let recorded = match current_exclusion {
+                                    Some(ExclusionMode::Drop) | Some(ExclusionMode::Pause) => None,
+                                    Some(ExclusionMode::Private) => Some(private_window(&window)),
+                                    None => Some(window.clone()),
+                                };
+
+                                if let Some(mut recorded) = recorded {
+                                    redactor.redact_window(&mut recorded);
+
+                                    // Get color for this app
+                                    let color = get_app_color(&db, &recorded.app_name);
+
+                                    let mut item = TrackItem {
+                                        id: None,
+                                        app: recorded.app_name.clone(),
+                                        task_name: "AppTrackItem".to_string(),
+                                        title: recorded.title.clone(),
+                                        url: recorded.url.clone(),
+                                        domain: recorded.domain.clone(),
+                                        color,
+                                        begin_date: now,
+                                        end_date: now,
+                                        productivity: None,
+                                    };
+
+                                    // Classify with the user's rules
+                                    let actions = rules.read().unwrap().classify(&item);
+                                    if actions.color.is_some() {
+                                        item.color = actions.color.clone();
+                                    }
+                                    item.productivity = actions.productivity;
+
+                                    // Create new app item
+                                    let item = open_track_item(&db, item);
+                                    link_rule_actions(&db, &item, &actions);
+                                    current_app_item = Some(item);
+
+                                    // Emit window change event
+                                    let _ = app_handle.emit("window-changed", &recorded);
+                                }
 
+                                // Keep the real window to detect the next change
                                 current_window = Some(window);
                             } else {
                                 // Update end time
@@ -248,6 +410,20 @@ impl TrackerService {
                                     item.end_date = now;
                                 }
                             }
+
+                            // Record no status time while a paused window is focused
+                            focus_paused = current_exclusion == Some(ExclusionMode::Pause);
+                            if focus_paused {
+                                if let Some(mut item) = current_status_item.take() {
+                                    item.end_date = now;
+                                    if let Err(e) = save_track_item(&db, &item) {
+                                        log::error!(
+                                            "Failed to save status item on exclusion: {}",
+                                            e
+                                        );
+                                    }
+                                }
+                            }
                         }
                         Err(e) => {
                             log::debug!("Could not get active window: {}", e);
@@ -257,14 +433,32 @@ impl TrackerService {
                     // System is idle, save current app item
                     if let Some(mut item) = current_app_item.take() {
                         item.end_date = now;
-                        if let Err(e) = save_track_item(&db, &item) {
+                        if let Err(e) = save_app_item(&db, &goals, &app_handle, &item) {
                             log::error!("Failed to save app item on idle: {}", e);
                         }
                     }
                     current_window = None;
+                    focus_paused = false;
+                }
+            }
+
+            // Flush whatever is still open so no time is lost on exit
+            let now = chrono::Utc::now().timestamp_millis();
+            if let Some(mut item) = current_app_item.take() {
+                item.end_date = now;
+                if let Err(e) = save_app_item(&db, &goals, &app_handle, &item) {
+                    log::error!("Failed to save app item on stop: {}", e);
+                }
+            }
+            if let Some(mut item) = current_status_item.take() {
+                item.end_date = now;
+                if let Err(e) = save_track_item(&db, &item) {
+                    log::error!("Failed to save status item on stop: {}", e);
                 }
             }
         });
+
+        *self.task.lock().unwrap() = Some(TrackerTask { stop_tx, handle });
     }
 
     /// Pause tracking
@@ -280,32 +474,135 @@ impl TrackerService {
     }
 
     /// Stop tracking
-    pub fn stop(&self) {
+    ///
+    /// Waits until the loop has saved its open items.
+    pub async fn stop(&self) {
+        let task = self.task.lock().unwrap().take();
+
+        if let Some(task) = task {
+            let _ = task.stop_tx.send(());
+            if let Err(e) = task.handle.await {
+                log::error!("Tracker task failed: {}", e);
+            }
+            log::info!("Tracker stopped");
+        }
+
         self.is_running.store(false, Ordering::SeqCst);
-        log::info!("Tracker stopped");
     }
 }
 
+/// Build the polling timer for a configuration
+fn polling_interval(config: &TrackerConfig) -> tokio::time::Interval {
+    tokio::time::interval(Duration::from_secs(config.polling_interval.max(1)))
+}
+
 /// Get color for an app from the database
-fn get_app_color(db: &Arc<Mutex<Database>>, app_name: &str) -> Option<String> {
-    db.lock()
+fn get_app_color(db: &Arc<DatabasePool>, app_name: &str) -> Option<String> {
+    db.read()
         .ok()
         .and_then(|db| db.get_app_color(app_name).ok().flatten())
 }
 
+/// Write a newly started item to the database right away
+///
+/// If the insert fails the item is kept in memory only and saved when closed.
+fn open_track_item(db: &Arc<DatabasePool>, item: TrackItem) -> TrackItem {
+    let opened = db
+        .write()
+        .and_then(|db| db.open_track_item(&item).map_err(|e| e.to_string()));
+
+    match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            log::error!("Failed to open track item: {}", e);
+            item
+        }
+    }
+}
+
+/// Link an opened item to the classification chosen by the rules, marked
+/// as set by them
+///
+/// Items that could not be opened have no id yet and stay unlinked.
+fn link_rule_actions(db: &Arc<DatabasePool>, item: &TrackItem, actions: &RuleActions) {
+    let Some(id) = item.id else {
+        return;
+    };
+    if actions.is_empty() {
+        return;
+    }
+
+    let linked = db.write().and_then(|db| {
+        db.apply_rule_actions(id, actions)
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = linked {
+        log::error!("Failed to apply rules to track item: {}", e);
+    }
+}
+
+/// Persist the current end date of an open item
+fn heartbeat_track_item(db: &Arc<DatabasePool>, item: &TrackItem) -> Result<(), String> {
+    let Some(id) = item.id else {
+        return Ok(());
+    };
+
+    let db = db.write()?;
+    db.heartbeat_track_item(id, item.end_date)
+        .map_err(|e| e.to_string())
+}
+
 /// Save a track item to the database
-fn save_track_item(db: &Arc<Mutex<Database>>, item: &TrackItem) -> Result<(), String> {
+///
+/// Items opened with `open_track_item` are closed in place; others are
+/// inserted.
+fn save_track_item(db: &Arc<DatabasePool>, item: &TrackItem) -> Result<(), String> {
+    let db = db.write()?;
+
+    if let Some(id) = item.id {
+        return db
+            .close_track_item(id, item.end_date)
+            .map_err(|e| e.to_string());
+    }
+
     // Only save if duration is meaningful (> 1 second)
-    if item.end_date - item.begin_date < 1000 {
+    if item.end_date - item.begin_date < MIN_ITEM_DURATION {
         return Ok(());
     }
 
-    let db = db.lock().map_err(|e| e.to_string())?;
     db.create_track_item(item)
         .map(|_| ())
         .map_err(|e| e.to_string())
 }
 
+/// Save a closed app item and count it toward the goals
+fn save_app_item(
+    db: &Arc<DatabasePool>,
+    goals: &GoalService,
+    app_handle: &AppHandle,
+    item: &TrackItem,
+) -> Result<(), String> {
+    save_track_item(db, item)?;
+    goals.record_item(app_handle, item);
+    Ok(())
+}
+
+/// Build a status item for a system state
+fn status_item(state: SystemState, begin_date: i64, end_date: i64) -> TrackItem {
+    TrackItem {
+        id: None,
+        app: state.to_string(),
+        task_name: "StatusTrackItem".to_string(),
+        title: state.to_string(),
+        url: None,
+        domain: None,
+        color: Some(state_to_color(state)),
+        begin_date,
+        end_date,
+        productivity: None,
+    }
+}
+
 /// Get color for system state
 fn state_to_color(state: SystemState) -> String {
     match state {
@@ -322,6 +619,7 @@ pub struct TrackingStatus {
     pub is_running: bool,
     pub is_paused: bool,
     pub current_state: SystemState,
+    pub idle_backend: IdleBackend,
     pub current_app: Option<String>,
     pub current_title: Option<String>,
 }
@@ -1,8 +1,11 @@
 // System Tray Module
 // 系统托盘模块
 
+use crate::database::{DayBoundaries, LogTimer};
+use crate::services::{FocusPhase, FocusStatus};
 use crate::AppState;
 use serde::Deserialize;
+use std::sync::{Arc, Mutex};
 use tauri::{
     menu::{CheckMenuItem, Menu, MenuItem},
     tray::{TrayIcon, TrayIconBuilder},
@@ -101,7 +104,13 @@ pub fn setup_tray(app: &tauri::App) -> Result<TrayIcon, tauri::Error> {
                 }
             }
             "quit" => {
-                app.exit(0);
+                // Save the open items before exiting
+                let app = app.clone();
+                let tracker = Arc::clone(&app.state::<AppState>().tracker);
+                tauri::async_runtime::spawn(async move {
+                    tracker.stop().await;
+                    app.exit(0);
+                });
             }
             _ => {}
         })
@@ -123,3 +132,73 @@ pub fn update_tray_menu(app: &AppHandle, trans: TrayTranslations) -> Result<(),
         Err("Tray icon not found".to_string())
     }
 }
+
+/// What the tray shows next to its icon, by source
+struct TrayStatus {
+    /// Title and tooltip text of the running log timer
+    log_timer: Option<(String, String)>,
+    /// Title and tooltip text of the focus countdown
+    focus: Option<(String, String)>,
+}
+
+static TRAY_STATUS: Mutex<TrayStatus> = Mutex::new(TrayStatus {
+    log_timer: None,
+    focus: None,
+});
+
+fn refresh_tray_status(app: &AppHandle, status: &TrayStatus) {
+    if let Some(tray) = app.tray_by_id("main") {
+        let parts: Vec<&(String, String)> = [&status.focus, &status.log_timer]
+            .into_iter()
+            .flatten()
+            .collect();
+        let title = parts
+            .iter()
+            .map(|(title, _)| title.as_str())
+            .collect::<Vec<_>>()
+            .join("  ");
+        let tooltip = if parts.is_empty() {
+            "Timlyzer - Time Tracking".to_string()
+        } else {
+            let details: Vec<&str> = parts.iter().map(|(_, tooltip)| tooltip.as_str()).collect();
+            format!("Timlyzer - {}", details.join("; "))
+        };
+
+        let _ = tray.set_title(Some(title).filter(|t| !t.is_empty()));
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+}
+
+/// Show the running log timer next to the tray icon, with its start time
+/// in the configured time zone
+pub fn show_log_timer(app: &AppHandle, timer: Option<&LogTimer>, days: &DayBoundaries) {
+    let mut status = TRAY_STATUS.lock().unwrap();
+    status.log_timer = timer.map(|timer| {
+        let since = days.local_time(timer.begin_date).format("%H:%M");
+        (
+            format!("⏱ {}", timer.title),
+            format!("{} (since {})", timer.title, since),
+        )
+    });
+    refresh_tray_status(app, &status);
+}
+
+/// Show the focus session countdown next to the tray icon
+pub fn show_focus(app: &AppHandle, focus: Option<&FocusStatus>) {
+    let mut status = TRAY_STATUS.lock().unwrap();
+    status.focus = focus.and_then(|focus| {
+        let phase = match focus.phase? {
+            FocusPhase::Work => "Focus",
+            FocusPhase::ShortBreak => "Short break",
+            FocusPhase::LongBreak => "Long break",
+        };
+        let seconds = (focus.remaining_ms + 999) / 1000;
+        let remaining = format!("{:02}:{:02}", seconds / 60, seconds % 60);
+        let paused = if focus.is_paused { " (paused)" } else { "" };
+        Some((
+            format!("🍅 {}{}", remaining, paused),
+            format!("{} {} left{}", phase, remaining, paused),
+        ))
+    });
+    refresh_tray_status(app, &status);
+}
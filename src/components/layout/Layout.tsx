@@ -1,7 +1,10 @@
+import { useEffect, useState } from "react";
 import { NavLink, Outlet } from "react-router-dom";
 import { BarChart3, Clock, Search, Settings } from "lucide-react";
 import { cn } from "@/lib/utils";
 import { useTranslation } from "react-i18next";
+import { settingsApi } from "@/services/tauri-api";
+import { UnlockPrompt } from "./UnlockPrompt";
 
 const navItems = [
   { to: "/", icon: Clock, label: "timeline" },
@@ -12,6 +15,12 @@ const navItems = [
 
 export function Layout() {
   const { t } = useTranslation("sidebar");
+  // Pages only load once an encrypted database is unlocked
+  const [locked, setLocked] = useState<boolean | null>(null);
+
+  useEffect(() => {
+    settingsApi.getDatabaseInfo().then((info) => setLocked(!!info?.locked));
+  }, []);
 
   return (
     <div className="flex h-screen bg-slate-50 dark:bg-slate-900">
@@ -61,9 +70,11 @@ export function Layout() {
 
       {/* Main Content */}
       <main className="flex-1 overflow-auto">
-        <div className="p-6">
-          <Outlet />
-        </div>
+        {locked ? (
+          <UnlockPrompt onUnlocked={() => setLocked(false)} />
+        ) : (
+          <div className="p-6">{locked === false && <Outlet />}</div>
+        )}
       </main>
     </div>
   );
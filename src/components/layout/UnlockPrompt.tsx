@@ -0,0 +1,99 @@
+import { useState } from "react";
+import { Lock } from "lucide-react";
+import { useTranslation } from "react-i18next";
+import {
+  Button,
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui";
+import { encryptionApi } from "@/services/tauri-api";
+
+interface UnlockPromptProps {
+  /** Called once the database accepted the passphrase */
+  onUnlocked: () => void;
+}
+
+/**
+ * Asks for the passphrase of an encrypted database, or retries the system
+ * keychain when it was not available at launch
+ */
+export function UnlockPrompt({ onUnlocked }: UnlockPromptProps) {
+  const { t } = useTranslation("common");
+  const [passphrase, setPassphrase] = useState("");
+  const [unlocking, setUnlocking] = useState(false);
+  const [failed, setFailed] = useState<"passphrase" | "keyring" | null>(null);
+
+  // Without a passphrase the system keychain is tried again
+  const unlock = async (typed?: string) => {
+    setUnlocking(true);
+    const unlocked = await encryptionApi.unlockDatabase(typed);
+    setUnlocking(false);
+
+    if (unlocked) {
+      onUnlocked();
+    } else {
+      setFailed(typed ? "passphrase" : "keyring");
+      setPassphrase("");
+    }
+  };
+
+  const handleSubmit = (e: React.FormEvent) => {
+    e.preventDefault();
+    if (passphrase) unlock(passphrase);
+  };
+
+  return (
+    <div className="flex h-full items-center justify-center">
+      <Card className="w-full max-w-sm">
+        <CardHeader>
+          <div className="flex items-center gap-2">
+            <Lock className="w-5 h-5 text-slate-500" />
+            <CardTitle>{t("unlock.title")}</CardTitle>
+          </div>
+          <CardDescription>{t("unlock.desc")}</CardDescription>
+        </CardHeader>
+        <CardContent>
+          <form onSubmit={handleSubmit} className="space-y-4">
+            <input
+              type="password"
+              autoFocus
+              placeholder={t("unlock.passphrase")}
+              value={passphrase}
+              onChange={(e) => {
+                setPassphrase(e.target.value);
+                setFailed(null);
+              }}
+              className="w-full rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 px-3 py-2 text-sm text-slate-900 dark:text-slate-100 placeholder:text-slate-400 focus:outline-none focus:ring-2 focus:ring-slate-500"
+            />
+            {failed && (
+              <p className="text-sm text-red-500">
+                {failed === "keyring"
+                  ? t("unlock.keyringFailed")
+                  : t("unlock.wrong")}
+              </p>
+            )}
+            <Button
+              type="submit"
+              className="w-full"
+              disabled={unlocking || !passphrase}
+            >
+              {unlocking ? t("status.loading") : t("unlock.submit")}
+            </Button>
+            <Button
+              type="button"
+              variant="outline"
+              className="w-full"
+              disabled={unlocking}
+              onClick={() => unlock()}
+            >
+              {t("unlock.retryKeyring")}
+            </Button>
+          </form>
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
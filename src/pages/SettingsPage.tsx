@@ -33,7 +33,7 @@ import {
   type AppSettings,
 } from "@/services/tauri-api";
 import { appApi } from "@/services/tauri-api";
-import { format, subDays, startOfDay, endOfDay } from "date-fns";
+import { format, subDays, startOfDay } from "date-fns";
 import { save } from "@tauri-apps/plugin-dialog";
 import { revealItemInDir, openUrl } from "@tauri-apps/plugin-opener";
 
@@ -104,8 +104,8 @@ export function SettingsPage() {
     setIsExporting(true);
     try {
       const now = new Date();
-      const from = startOfDay(subDays(now, 30)).getTime();
-      const to = endOfDay(now).getTime();
+      const fromDate = format(subDays(now, 30), "yyyy-MM-dd");
+      const toDate = format(now, "yyyy-MM-dd");
 
       const defaultName = `timlyzer_export_${format(now, "yyyy-MM-dd")}.${exportFormat}`;
 
@@ -125,8 +125,8 @@ export function SettingsPage() {
       }
 
       const result = exportFormat === "csv"
-        ? await exportApi.exportToCsv(from, to, filePath)
-        : await exportApi.exportToJson(from, to, filePath);
+        ? await exportApi.exportToCsv(fromDate, toDate, filePath)
+        : await exportApi.exportToJson(fromDate, toDate, filePath);
 
       if (result) {
         setExportMessage(t("data.export.success", { count: result.itemsExported, format: exportFormat.toUpperCase() }));
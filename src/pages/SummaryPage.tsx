@@ -27,8 +27,6 @@ import {
   Legend,
 } from "recharts";
 import {
-  startOfDay,
-  endOfDay,
   startOfWeek,
   endOfWeek,
   startOfMonth,
@@ -45,6 +43,9 @@ import { formatDuration, stringToColor } from "@/lib/utils";
 
 type TimeRangeType = "day" | "week" | "month";
 
+// Local dates sent to the stats commands
+const DATE_FORMAT = "yyyy-MM-dd";
+
 // Known browser app names that support domain tracking
 const KNOWN_BROWSERS = [
   "Google Chrome",
@@ -123,20 +124,20 @@ export function SummaryPage() {
     switch (rangeType) {
       case "day":
         return {
-          start: startOfDay(baseDate).getTime(),
-          end: endOfDay(baseDate).getTime(),
+          start: format(baseDate, DATE_FORMAT),
+          end: format(baseDate, DATE_FORMAT),
           label: format(baseDate, "PPPP", { locale }),
         };
       case "week":
         return {
-          start: startOfWeek(baseDate, { weekStartsOn: 1 }).getTime(),
-          end: endOfWeek(baseDate, { weekStartsOn: 1 }).getTime(),
+          start: format(startOfWeek(baseDate, { weekStartsOn: 1 }), DATE_FORMAT),
+          end: format(endOfWeek(baseDate, { weekStartsOn: 1 }), DATE_FORMAT),
           label: `${t("range.week")} ${format(startOfWeek(baseDate, { weekStartsOn: 1 }), "PP", { locale })} - ${format(endOfWeek(baseDate, { weekStartsOn: 1 }), "PP", { locale })}`,
         };
       case "month":
         return {
-          start: startOfMonth(baseDate).getTime(),
-          end: endOfMonth(baseDate).getTime(),
+          start: format(startOfMonth(baseDate), DATE_FORMAT),
+          end: format(endOfMonth(baseDate), DATE_FORMAT),
           label: format(baseDate, "MMMM yyyy", { locale }),
         };
     }
@@ -158,9 +159,8 @@ export function SummaryPage() {
 
           const dailyData: DailyStats[] = await Promise.all(
             days.map(async (day) => {
-              const dayStart = startOfDay(day).getTime();
-              const dayEnd = endOfDay(day).getTime();
-              const dayStats = await statsApi.getAppUsageStats(dayStart, dayEnd);
+              const date = format(day, DATE_FORMAT);
+              const dayStats = await statsApi.getAppUsageStats(date, date);
 
               const apps: Record<string, number> = {};
               dayStats.forEach(s => {
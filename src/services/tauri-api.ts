@@ -2,6 +2,7 @@ import { invoke } from "@tauri-apps/api/core";
 import type {
   TrackItem,
   TrackItemType,
+  Productivity,
   SearchParams,
   SearchResult,
 } from "@/types";
@@ -50,178 +51,967 @@ export interface AppUsageStats {
 // Track Item API - Communication with Tauri backend
 // ============================================================================
 
+/**
+ * What to change on the app items in a range; omitted fields are kept
+ */
+export interface RangeChanges {
+  app?: string;
+  title?: string;
+  /** Replaces every project of the changed items */
+  projectId?: number;
+}
+
 export const trackItemApi = {
   /**
-   * Find all items for a day
+   * Find all items for a day
+   * With `clip`, items are cut to the range and split at local midnight
+   */
+  findAllDayItems: async (
+    from: number,
+    to: number,
+    taskName: TrackItemType,
+    clip?: boolean
+  ): Promise<TrackItem[]> => {
+    try {
+      return await invoke<TrackItem[]>("find_all_day_items", {
+        from,
+        to,
+        taskName,
+        clip,
+      });
+    } catch (error) {
+      console.error("findAllDayItems error:", error);
+      return [];
+    }
+  },
+
+  /**
+   * Create a new track item
+   */
+  createTrackItem: async (trackItem: TrackItem): Promise<TrackItem | null> => {
+    try {
+      return await invoke<TrackItem>("create_track_item", { trackItem });
+    } catch (error) {
+      console.error("createTrackItem error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Update an existing track item
+   */
+  updateTrackItem: async (trackItem: TrackItem): Promise<TrackItem | null> => {
+    try {
+      return await invoke<TrackItem>("update_track_item", { trackItem });
+    } catch (error) {
+      console.error("updateTrackItem error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Delete track items by IDs
+   */
+  deleteByIds: async (ids: number[]): Promise<void> => {
+    try {
+      await invoke("delete_by_ids", { ids });
+    } catch (error) {
+      console.error("deleteByIds error:", error);
+    }
+  },
+
+  /**
+   * Split a track item in two at a timestamp
+   */
+  splitTrackItem: async (
+    id: number,
+    at: number
+  ): Promise<[TrackItem, TrackItem] | null> => {
+    try {
+      return await invoke<[TrackItem, TrackItem]>("split_track_item", {
+        id,
+        at,
+      });
+    } catch (error) {
+      console.error("splitTrackItem error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Merge track items of one type into the earliest of them
+   */
+  mergeTrackItems: async (ids: number[]): Promise<TrackItem | null> => {
+    try {
+      return await invoke<TrackItem>("merge_track_items", { ids });
+    } catch (error) {
+      console.error("mergeTrackItems error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Move track items by an offset in milliseconds
+   */
+  shiftTrackItems: async (
+    ids: number[],
+    offset: number
+  ): Promise<TrackItem[]> => {
+    try {
+      return await invoke<TrackItem[]>("shift_track_items", { ids, offset });
+    } catch (error) {
+      console.error("shiftTrackItems error:", error);
+      return [];
+    }
+  },
+
+  /**
+   * Cut [from, to) out of the timeline, optionally for one item type;
+   * returns the number of items changed
+   */
+  trimTrackItems: async (
+    from: number,
+    to: number,
+    taskName?: TrackItemType
+  ): Promise<number> => {
+    try {
+      return await invoke<number>("trim_track_items", { from, to, taskName });
+    } catch (error) {
+      console.error("trimTrackItems error:", error);
+      return 0;
+    }
+  },
+
+  /**
+   * Change the app, title or project of the app items in [from, to)
+   */
+  reassignRange: async (
+    from: number,
+    to: number,
+    changes: RangeChanges
+  ): Promise<TrackItem[]> => {
+    try {
+      return await invoke<TrackItem[]>("reassign_range", {
+        from,
+        to,
+        changes,
+      });
+    } catch (error) {
+      console.error("reassignRange error:", error);
+      return [];
+    }
+  },
+
+  /**
+   * Search track items
+   */
+  searchItems: async (params: SearchParams): Promise<SearchResult> => {
+    try {
+      return await invoke<SearchResult>("search_items", {
+        from: params.from,
+        to: params.to,
+        taskName: params.taskName,
+        searchStr: params.searchStr,
+        limit: params.limit,
+        offset: params.offset,
+      });
+    } catch (error) {
+      console.error("searchItems error:", error);
+      return { data: [], total: 0 };
+    }
+  },
+
+  /**
+   * Update track item color
+   */
+  updateTrackItemColor: async (
+    appName: string,
+    color: string
+  ): Promise<void> => {
+    try {
+      await invoke("update_track_item_color", { appName, color });
+    } catch (error) {
+      console.error("updateTrackItemColor error:", error);
+    }
+  },
+};
+
+// ============================================================================
+// Tracking API - Controls for time tracking
+// ============================================================================
+
+export const trackingApi = {
+  /**
+   * Get current active window info
+   */
+  getCurrentWindow: async (): Promise<WindowInfo | null> => {
+    try {
+      return await invoke<WindowInfo>("get_current_window");
+    } catch (error) {
+      console.error("getCurrentWindow error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Get tracking status
+   */
+  getStatus: async (): Promise<TrackingStatus | null> => {
+    try {
+      return await invoke<TrackingStatus>("get_tracking_status");
+    } catch (error) {
+      console.error("getStatus error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Start tracking
+   */
+  start: async (): Promise<void> => {
+    try {
+      await invoke("start_tracking");
+    } catch (error) {
+      console.error("startTracking error:", error);
+    }
+  },
+
+  /**
+   * Stop tracking
+   */
+  stop: async (): Promise<void> => {
+    try {
+      await invoke("stop_tracking");
+    } catch (error) {
+      console.error("stopTracking error:", error);
+    }
+  },
+
+  /**
+   * Pause tracking
+   */
+  pause: async (): Promise<void> => {
+    try {
+      await invoke("pause_tracking");
+    } catch (error) {
+      console.error("pauseTracking error:", error);
+    }
+  },
+
+  /**
+   * Resume tracking
+   */
+  resume: async (): Promise<void> => {
+    try {
+      await invoke("resume_tracking");
+    } catch (error) {
+      console.error("resumeTracking error:", error);
+    }
+  },
+
+  /**
+   * Get current idle time in seconds
+   */
+  getIdleTime: async (): Promise<number> => {
+    try {
+      return await invoke<number>("get_idle_time_command");
+    } catch (error) {
+      console.error("getIdleTime error:", error);
+      return 0;
+    }
+  },
+};
+
+// ============================================================================
+// Log Timer API - Manual timers for time no window tracker sees
+// ============================================================================
+
+/**
+ * A running manual timer; emitted as "log-timer-changed" (null when stopped)
+ */
+export interface LogTimer {
+  title: string;
+  projectId?: number;
+  beginDate: number;
+}
+
+export const logTimerApi = {
+  /**
+   * Start a timer; a running one is stopped and logged first
+   */
+  start: async (title: string, projectId?: number): Promise<LogTimer | null> => {
+    try {
+      return await invoke<LogTimer>("start_log_timer", { title, projectId });
+    } catch (error) {
+      console.error("startLogTimer error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Stop the running timer and save it as a LogTrackItem
+   */
+  stop: async (): Promise<TrackItem | null> => {
+    try {
+      return await invoke<TrackItem | null>("stop_log_timer");
+    } catch (error) {
+      console.error("stopLogTimer error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Get the running timer, if any
+   */
+  getRunning: async (): Promise<LogTimer | null> => {
+    try {
+      return await invoke<LogTimer | null>("get_running_log_timer");
+    } catch (error) {
+      console.error("getRunningLogTimer error:", error);
+      return null;
+    }
+  },
+};
+
+// ============================================================================
+// Focus API - Pomodoro focus sessions
+// ============================================================================
+
+export type FocusPhase = "work" | "shortBreak" | "longBreak";
+
+/**
+ * Focus session status; emitted as "focus-changed"
+ */
+export interface FocusStatus {
+  phase?: FocusPhase;
+  completedCycles: number;
+  remainingMs: number;
+  isPaused: boolean;
+}
+
+export const focusApi = {
+  /**
+   * Start a session with a focus block
+   */
+  start: async (): Promise<FocusStatus | null> => {
+    try {
+      return await invoke<FocusStatus>("start_focus_session");
+    } catch (error) {
+      console.error("startFocusSession error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Pause the countdown
+   */
+  pause: async (): Promise<FocusStatus | null> => {
+    try {
+      return await invoke<FocusStatus>("pause_focus_session");
+    } catch (error) {
+      console.error("pauseFocusSession error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Resume the countdown
+   */
+  resume: async (): Promise<FocusStatus | null> => {
+    try {
+      return await invoke<FocusStatus>("resume_focus_session");
+    } catch (error) {
+      console.error("resumeFocusSession error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * End the current phase and move on to the next
+   */
+  skip: async (): Promise<FocusStatus | null> => {
+    try {
+      return await invoke<FocusStatus>("skip_focus_phase");
+    } catch (error) {
+      console.error("skipFocusPhase error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * End the session
+   */
+  stop: async (): Promise<FocusStatus | null> => {
+    try {
+      return await invoke<FocusStatus>("stop_focus_session");
+    } catch (error) {
+      console.error("stopFocusSession error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Get the session status
+   */
+  getStatus: async (): Promise<FocusStatus | null> => {
+    try {
+      return await invoke<FocusStatus>("get_focus_status");
+    } catch (error) {
+      console.error("getFocusStatus error:", error);
+      return null;
+    }
+  },
+};
+
+// ============================================================================
+// Break API - Reminders after long stretches of activity
+// ============================================================================
+
+/**
+ * Break reminder status; emitted as "break-reminder" and "break-taken"
+ */
+export interface BreakStatus {
+  activeMs: number;
+  reminderPending: boolean;
+  snoozedUntil?: number;
+}
+
+/**
+ * How break reminders in a date range went
+ */
+export interface BreakStats {
+  shown: number;
+  taken: number;
+  snoozed: number;
+  dismissed: number;
+  averageActiveDuration: number;
+}
+
+export const breakApi = {
+  /**
+   * Get continuous active time and whether a reminder is pending
+   */
+  getStatus: async (): Promise<BreakStatus | null> => {
+    try {
+      return await invoke<BreakStatus>("get_break_status");
+    } catch (error) {
+      console.error("getBreakStatus error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Snooze the pending reminder
+   */
+  snooze: async (): Promise<BreakStatus | null> => {
+    try {
+      return await invoke<BreakStatus>("snooze_break_reminder");
+    } catch (error) {
+      console.error("snoozeBreakReminder error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Dismiss the pending reminder
+   */
+  dismiss: async (): Promise<BreakStatus | null> => {
+    try {
+      return await invoke<BreakStatus>("dismiss_break_reminder");
+    } catch (error) {
+      console.error("dismissBreakReminder error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Get reminder outcomes for local dates (yyyy-MM-dd, inclusive)
+   */
+  getStats: async (
+    fromDate: string,
+    toDate: string
+  ): Promise<BreakStats | null> => {
+    try {
+      return await invoke<BreakStats>("get_break_stats", { fromDate, toDate });
+    } catch (error) {
+      console.error("getBreakStats error:", error);
+      return null;
+    }
+  },
+};
+
+// ============================================================================
+// Goals API - Daily/weekly goals and limits
+// ============================================================================
+
+/**
+ * Which time counts toward a goal
+ */
+export type GoalTarget =
+  | { type: "app"; value: string }
+  | { type: "domain"; value: string }
+  | { type: "project"; value: number }
+  | { type: "tag"; value: number };
+
+/**
+ * A goal (at least) or limit (at most) per day or week
+ */
+export interface Goal {
+  id?: number;
+  name: string;
+  enabled: boolean;
+  kind: "goal" | "limit";
+  period: "day" | "week";
+  target: GoalTarget;
+  /** Target time per period (milliseconds) */
+  duration: number;
+  /** Weekdays a daily goal applies on (0 = Monday); empty for every day */
+  weekdays: number[];
+}
+
+/**
+ * Progress in the current period; emitted as "goal-progress" and
+ * "limit-exceeded"
+ */
+export interface GoalProgress {
+  goalId: number;
+  name: string;
+  kind: "goal" | "limit";
+  periodStart: number;
+  periodEnd: number;
+  progress: number;
+  duration: number;
+  met: boolean;
+}
+
+/**
+ * How a goal went in one period
+ */
+export interface GoalHistoryEntry {
+  goalId: number;
+  periodStart: number;
+  date: string;
+  progress: number;
+  met: boolean;
+}
+
+export const goalsApi = {
+  /**
+   * List goals and limits
+   */
+  getGoals: async (): Promise<Goal[]> => {
+    try {
+      return await invoke<Goal[]>("get_goals");
+    } catch (error) {
+      console.error("getGoals error:", error);
+      return [];
+    }
+  },
+
+  /**
+   * Create or update a goal
+   */
+  saveGoal: async (goal: Goal): Promise<Goal | null> => {
+    try {
+      return await invoke<Goal>("save_goal", { goal });
+    } catch (error) {
+      console.error("saveGoal error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Delete a goal and its history
+   */
+  deleteGoal: async (id: number): Promise<void> => {
+    try {
+      await invoke("delete_goal", { id });
+    } catch (error) {
+      console.error("deleteGoal error:", error);
+    }
+  },
+
+  /**
+   * Get the progress of the goals that apply right now
+   */
+  getProgress: async (): Promise<GoalProgress[]> => {
+    try {
+      return await invoke<GoalProgress[]>("get_goal_progress");
+    } catch (error) {
+      console.error("getGoalProgress error:", error);
+      return [];
+    }
+  },
+
+  /**
+   * Get a goal's history for local dates (yyyy-MM-dd, inclusive)
+   */
+  getHistory: async (
+    goalId: number,
+    fromDate: string,
+    toDate: string
+  ): Promise<GoalHistoryEntry[]> => {
+    try {
+      return await invoke<GoalHistoryEntry[]>("get_goal_history", {
+        goalId,
+        fromDate,
+        toDate,
+      });
+    } catch (error) {
+      console.error("getGoalHistory error:", error);
+      return [];
+    }
+  },
+};
+
+// ============================================================================
+// Projects API - Projects and tags for track items
+// ============================================================================
+
+export interface Project {
+  id: number;
+  name: string;
+  color?: string;
+  archived: boolean;
+  createdAt: number;
+}
+
+export interface Tag {
+  id: number;
+  name: string;
+  color?: string;
+}
+
+/**
+ * Projects and tags linked to one track item
+ */
+export interface ItemLabels {
+  itemId: number;
+  projectIds: number[];
+  tagIds: number[];
+}
+
+export const projectsApi = {
+  /**
+   * List projects, optionally including archived ones
+   */
+  getProjects: async (includeArchived = false): Promise<Project[]> => {
+    try {
+      return await invoke<Project[]>("get_projects", { includeArchived });
+    } catch (error) {
+      console.error("getProjects error:", error);
+      return [];
+    }
+  },
+
+  /**
+   * Create a project
+   */
+  createProject: async (
+    name: string,
+    color?: string
+  ): Promise<Project | null> => {
+    try {
+      return await invoke<Project>("create_project", { name, color });
+    } catch (error) {
+      console.error("createProject error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Rename a project
+   */
+  renameProject: async (id: number, name: string): Promise<void> => {
+    try {
+      await invoke("rename_project", { id, name });
+    } catch (error) {
+      console.error("renameProject error:", error);
+    }
+  },
+
+  /**
+   * Archive or unarchive a project
+   */
+  archiveProject: async (id: number, archived: boolean): Promise<void> => {
+    try {
+      await invoke("archive_project", { id, archived });
+    } catch (error) {
+      console.error("archiveProject error:", error);
+    }
+  },
+
+  /**
+   * Move the items, rules and goals of the source projects to the target
+   * and delete the sources
+   */
+  mergeProjects: async (
+    sourceIds: number[],
+    targetId: number
+  ): Promise<void> => {
+    try {
+      await invoke("merge_projects", { sourceIds, targetId });
+    } catch (error) {
+      console.error("mergeProjects error:", error);
+    }
+  },
+
+  /**
+   * Assign a project to track items
+   */
+  assignProject: async (
+    projectId: number,
+    itemIds: number[]
+  ): Promise<void> => {
+    try {
+      await invoke("assign_project", { projectId, itemIds });
+    } catch (error) {
+      console.error("assignProject error:", error);
+    }
+  },
+
+  /**
+   * Remove a project from track items
+   */
+  unassignProject: async (
+    projectId: number,
+    itemIds: number[]
+  ): Promise<void> => {
+    try {
+      await invoke("unassign_project", { projectId, itemIds });
+    } catch (error) {
+      console.error("unassignProject error:", error);
+    }
+  },
+
+  /**
+   * List tags
    */
-  findAllDayItems: async (
-    from: number,
-    to: number,
-    taskName: TrackItemType
-  ): Promise<TrackItem[]> => {
+  getTags: async (): Promise<Tag[]> => {
     try {
-      return await invoke<TrackItem[]>("find_all_day_items", {
-        from,
-        to,
-        taskName,
-      });
+      return await invoke<Tag[]>("get_tags");
     } catch (error) {
-      console.error("findAllDayItems error:", error);
+      console.error("getTags error:", error);
       return [];
     }
   },
 
   /**
-   * Create a new track item
+   * Create a tag
    */
-  createTrackItem: async (trackItem: TrackItem): Promise<TrackItem | null> => {
+  createTag: async (name: string, color?: string): Promise<Tag | null> => {
     try {
-      return await invoke<TrackItem>("create_track_item", { trackItem });
+      return await invoke<Tag>("create_tag", { name, color });
     } catch (error) {
-      console.error("createTrackItem error:", error);
+      console.error("createTag error:", error);
       return null;
     }
   },
 
   /**
-   * Update an existing track item
+   * Delete a tag
    */
-  updateTrackItem: async (trackItem: TrackItem): Promise<TrackItem | null> => {
+  deleteTag: async (id: number): Promise<void> => {
     try {
-      return await invoke<TrackItem>("update_track_item", { trackItem });
+      await invoke("delete_tag", { id });
     } catch (error) {
-      console.error("updateTrackItem error:", error);
-      return null;
+      console.error("deleteTag error:", error);
     }
   },
 
   /**
-   * Delete track items by IDs
+   * Add a tag to track items
    */
-  deleteByIds: async (ids: number[]): Promise<void> => {
+  tagItems: async (tagId: number, itemIds: number[]): Promise<void> => {
     try {
-      await invoke("delete_by_ids", { ids });
+      await invoke("tag_items", { tagId, itemIds });
     } catch (error) {
-      console.error("deleteByIds error:", error);
+      console.error("tagItems error:", error);
     }
   },
 
   /**
-   * Search track items
+   * Remove a tag from track items
    */
-  searchItems: async (params: SearchParams): Promise<SearchResult> => {
+  untagItems: async (tagId: number, itemIds: number[]): Promise<void> => {
     try {
-      return await invoke<SearchResult>("search_items", {
-        from: params.from,
-        to: params.to,
-        taskName: params.taskName,
-        searchStr: params.searchStr,
-        limit: params.limit,
-        offset: params.offset,
-      });
+      await invoke("untag_items", { tagId, itemIds });
     } catch (error) {
-      console.error("searchItems error:", error);
-      return { data: [], total: 0 };
+      console.error("untagItems error:", error);
     }
   },
 
   /**
-   * Update track item color
+   * Get the projects and tags of track items
    */
-  updateTrackItemColor: async (
-    appName: string,
-    color: string
-  ): Promise<void> => {
+  getItemLabels: async (itemIds: number[]): Promise<ItemLabels[]> => {
     try {
-      await invoke("update_track_item_color", { appName, color });
+      return await invoke<ItemLabels[]>("get_item_labels", { itemIds });
     } catch (error) {
-      console.error("updateTrackItemColor error:", error);
+      console.error("getItemLabels error:", error);
+      return [];
     }
   },
 };
 
 // ============================================================================
-// Tracking API - Controls for time tracking
+// Rules API - Classifying app activity automatically
 // ============================================================================
 
-export const trackingApi = {
+/**
+ * What an item must look like for a rule to apply; every condition that
+ * is set must match
+ */
+export interface RuleConditions {
+  /** App name, compared case-insensitively */
+  app?: string;
+  /** Regular expression matched against the window title */
+  titlePattern?: string;
+  /** Domain; also matches its subdomains */
+  domain?: string;
+  /** Regular expression matched against the URL */
+  urlPattern?: string;
+  /** Start of the time-of-day window (minutes after local midnight) */
+  startMinute?: number;
+  /** End of the time-of-day window (exclusive); may wrap past midnight */
+  endMinute?: number;
+}
+
+/**
+ * What a matching rule does to an item
+ */
+export interface RuleActions {
+  projectId?: number;
+  tagIds: number[];
+  color?: string;
+  productivity?: Productivity;
+}
+
+/**
+ * An ordered classification rule; rules are evaluated in ascending
+ * position
+ */
+export interface Rule {
+  id?: number;
+  name: string;
+  enabled: boolean;
+  position: number;
+  conditions: RuleConditions;
+  actions: RuleActions;
+}
+
+/**
+ * Outcome of re-applying rules to stored history
+ */
+export interface RuleApplyResult {
+  itemsMatched: number;
+  itemsChanged: number;
+  applied: boolean;
+}
+
+export const rulesApi = {
   /**
-   * Get current active window info
+   * List rules in evaluation order
    */
-  getCurrentWindow: async (): Promise<WindowInfo | null> => {
+  getRules: async (): Promise<Rule[]> => {
     try {
-      return await invoke<WindowInfo>("get_current_window");
+      return await invoke<Rule[]>("get_rules");
     } catch (error) {
-      console.error("getCurrentWindow error:", error);
-      return null;
+      console.error("getRules error:", error);
+      return [];
     }
   },
 
   /**
-   * Get tracking status
+   * Create or update a rule; rejected when a pattern does not compile
    */
-  getStatus: async (): Promise<TrackingStatus | null> => {
+  saveRule: async (rule: Rule): Promise<Rule | null> => {
     try {
-      return await invoke<TrackingStatus>("get_tracking_status");
+      return await invoke<Rule>("save_rule", { rule });
     } catch (error) {
-      console.error("getStatus error:", error);
+      console.error("saveRule error:", error);
       return null;
     }
   },
 
   /**
-   * Start tracking
+   * Delete a rule
    */
-  start: async (): Promise<void> => {
+  deleteRule: async (id: number): Promise<void> => {
     try {
-      await invoke("start_tracking");
+      await invoke("delete_rule", { id });
     } catch (error) {
-      console.error("startTracking error:", error);
+      console.error("deleteRule error:", error);
     }
   },
 
   /**
-   * Stop tracking
+   * Set rule positions to the order of the given ids
    */
-  stop: async (): Promise<void> => {
+  reorderRules: async (ids: number[]): Promise<void> => {
     try {
-      await invoke("stop_tracking");
+      await invoke("reorder_rules", { ids });
     } catch (error) {
-      console.error("stopTracking error:", error);
+      console.error("reorderRules error:", error);
     }
   },
 
   /**
-   * Pause tracking
+   * Re-classify the app items in [from, to); with dryRun only preview how
+   * many would change
    */
-  pause: async (): Promise<void> => {
+  applyToRange: async (
+    from: number,
+    to: number,
+    dryRun: boolean
+  ): Promise<RuleApplyResult | null> => {
     try {
-      await invoke("pause_tracking");
+      return await invoke<RuleApplyResult>("apply_rules_to_range", {
+        from,
+        to,
+        dryRun,
+      });
     } catch (error) {
-      console.error("pauseTracking error:", error);
+      console.error("applyRulesToRange error:", error);
+      return null;
     }
   },
+};
 
-  /**
-   * Resume tracking
-   */
-  resume: async (): Promise<void> => {
-    try {
-      await invoke("resume_tracking");
-    } catch (error) {
-      console.error("resumeTracking error:", error);
-    }
-  },
+// ============================================================================
+// Compaction API - Merging adjacent and tiny items
+// ============================================================================
+
+/**
+ * What compacting a range did; emitted as "timeline-compacted" by the
+ * nightly job
+ */
+export interface CompactionResult {
+  itemsBefore: number;
+  itemsRemoved: number;
+  blipsAbsorbed: number;
+  applied: boolean;
+}
 
+export const compactionApi = {
   /**
-   * Get current idle time in seconds
+   * Merge adjacent app items of the same kind in [from, to); with dryRun
+   * only preview the result
    */
-  getIdleTime: async (): Promise<number> => {
+  compact: async (
+    from: number,
+    to: number,
+    dryRun: boolean
+  ): Promise<CompactionResult | null> => {
     try {
-      return await invoke<number>("get_idle_time_command");
+      return await invoke<CompactionResult>("compact_timeline", {
+        from,
+        to,
+        dryRun,
+      });
     } catch (error) {
-      console.error("getIdleTime error:", error);
-      return 0;
+      console.error("compactTimeline error:", error);
+      return null;
     }
   },
 };
@@ -240,16 +1030,104 @@ export interface DomainUsageStats {
   pageCount: number;
 }
 
+export interface DateRange {
+  from: number;
+  to: number;
+}
+
+/**
+ * App time on one local date, with trailing averages
+ */
+export interface DayTotal {
+  date: string;
+  duration: number;
+  average7: number;
+  average30: number;
+}
+
+/**
+ * What a usage delta is about
+ */
+export type UsageKey =
+  | { type: "app"; value: string }
+  | { type: "domain"; value: string }
+  | { type: "project"; value: number };
+
+/**
+ * Time in the latest period and the one before it
+ */
+export interface UsageDelta {
+  key: UsageKey;
+  current: number;
+  previous: number;
+}
+
+/**
+ * Uninterrupted stretches of one app or project
+ */
+export interface StretchStats {
+  key: UsageKey;
+  count: number;
+  median: number;
+  longest: number;
+}
+
+/**
+ * Attention metrics of one local date
+ */
+export interface DayAttention {
+  date: string;
+  activeDuration: number;
+  switches: number;
+  switchesPerHour: number;
+  /** 0 for one long stretch, approaching 1 for many short ones */
+  fragmentation: number;
+  apps: StretchStats[];
+  projects: StretchStats[];
+  deepWork: { beginDate: number; endDate: number }[];
+  deepWorkDuration: number;
+}
+
+/**
+ * Calendar heatmap and trend statistics
+ */
+export interface TrendStats {
+  days: DayTotal[];
+  /** Time per weekday (0 = Monday) and hour */
+  hourly: number[][];
+  weekDeltas: UsageDelta[];
+  monthDeltas: UsageDelta[];
+}
+
 export const statsApi = {
   /**
-   * Get app usage statistics for a time range
+   * Get the [from, to) range of local dates (yyyy-MM-dd, inclusive),
+   * using the timezone and start-of-day settings
+   */
+  getDateRange: async (
+    fromDate: string,
+    toDate: string
+  ): Promise<DateRange | null> => {
+    try {
+      return await invoke<DateRange>("get_date_range", { fromDate, toDate });
+    } catch (error) {
+      console.error("getDateRange error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Get app usage statistics for local dates (yyyy-MM-dd, inclusive)
    */
   getAppUsageStats: async (
-    from: number,
-    to: number
+    fromDate: string,
+    toDate: string
   ): Promise<AppUsageStats[]> => {
     try {
-      return await invoke<AppUsageStats[]>("get_app_usage_stats", { from, to });
+      return await invoke<AppUsageStats[]>("get_app_usage_stats", {
+        fromDate,
+        toDate,
+      });
     } catch (error) {
       console.error("getAppUsageStats error:", error);
       return [];
@@ -257,17 +1135,18 @@ export const statsApi = {
   },
 
   /**
-   * Get domain usage statistics for a specific browser app
+   * Get domain usage statistics for a specific browser app for local
+   * dates (yyyy-MM-dd, inclusive)
    */
   getDomainUsageStats: async (
-    from: number,
-    to: number,
+    fromDate: string,
+    toDate: string,
     appName: string
   ): Promise<DomainUsageStats[]> => {
     try {
       return await invoke<DomainUsageStats[]>("get_domain_usage_stats", {
-        from,
-        to,
+        fromDate,
+        toDate,
         appName,
       });
     } catch (error) {
@@ -275,12 +1154,104 @@ export const statsApi = {
       return [];
     }
   },
+
+  /**
+   * Get daily totals, the weekday/hour distribution and week-over-week and
+   * month-over-month deltas for local dates (yyyy-MM-dd, inclusive)
+   */
+  getTrendStats: async (
+    fromDate: string,
+    toDate: string
+  ): Promise<TrendStats | null> => {
+    try {
+      return await invoke<TrendStats>("get_trend_stats", { fromDate, toDate });
+    } catch (error) {
+      console.error("getTrendStats error:", error);
+      return null;
+    }
+  },
+
+  /**
+   * Get app switches, stretch lengths, deep work blocks and fragmentation
+   * per local date (yyyy-MM-dd, inclusive)
+   */
+  getAttentionStats: async (
+    fromDate: string,
+    toDate: string
+  ): Promise<DayAttention[]> => {
+    try {
+      return await invoke<DayAttention[]>("get_attention_stats", {
+        fromDate,
+        toDate,
+      });
+    } catch (error) {
+      console.error("getAttentionStats error:", error);
+      return [];
+    }
+  },
 };
 
 // ============================================================================
 // Settings API
 // ============================================================================
 
+export type BrowserKind = "chromium" | "safari" | "firefox";
+
+export interface BrowserConfig {
+  appName: string;
+  kind: BrowserKind;
+  captureUrl: boolean;
+}
+
+export type ExclusionMode = "drop" | "private" | "pause";
+
+export interface ExclusionRule {
+  app?: string;
+  titlePattern?: string;
+  domain?: string;
+  mode: ExclusionMode;
+}
+
+export interface RedactionPattern {
+  pattern: string;
+  replacement: string;
+}
+
+export interface RedactionConfig {
+  stripUrlQuery: boolean;
+  maskEmails: boolean;
+  maskTokens: boolean;
+  maxTitleLength?: number;
+  patterns: RedactionPattern[];
+}
+
+export interface FocusConfig {
+  workMinutes: number;
+  shortBreakMinutes: number;
+  longBreakMinutes: number;
+  longBreakEvery: number;
+  distractionAlerts: boolean;
+  blocklist: string[]; // app names or window title text
+}
+
+export interface BreakConfig {
+  enabled: boolean;
+  remindAfterMinutes: number;
+  breakMinutes: number; // idle this long counts as a break
+  snoozeMinutes: number;
+}
+
+export interface AttentionConfig {
+  flickerSeconds: number; // shorter windows fold into the surrounding stretch
+  deepWorkMinutes: number;
+}
+
+export interface CompactionConfig {
+  nightly: boolean; // compact finished days in the background
+  ignoreTitles: boolean;
+  blipSeconds: number; // shorter items are absorbed into a neighbor
+}
+
 export interface AppSettings {
   theme: string;
   autoStart: boolean;
@@ -288,7 +1259,16 @@ export interface AppSettings {
   pollingInterval: number;
   idleThreshold: number;
   trackUrls: boolean;
+  browsers: BrowserConfig[];
+  exclusions: ExclusionRule[];
+  redaction: RedactionConfig;
   hideDock: boolean;
+  timezone?: string; // IANA name; unset for the system zone
+  dayStartHour: number; // 0-23
+  focus: FocusConfig;
+  breaks: BreakConfig;
+  attention: AttentionConfig;
+  compaction: CompactionConfig;
 }
 
 export interface TrackedApp {
@@ -300,12 +1280,18 @@ export interface TrackedApp {
 export interface DatabaseInfo {
   path: string;
   sizeBytes: number;
+  encrypted: boolean;
+  locked: boolean;
 }
 
 export interface ClearResult {
   itemsDeleted: number;
 }
 
+export interface RedactionResult {
+  itemsUpdated: number;
+}
+
 export interface ExportResult {
   success: boolean;
   itemsExported: number;
@@ -328,7 +1314,40 @@ export const settingsApi = {
         pollingInterval: 3,
         idleThreshold: 300,
         trackUrls: false,
+        browsers: [],
+        exclusions: [],
+        redaction: {
+          stripUrlQuery: true,
+          maskEmails: true,
+          maskTokens: true,
+          maxTitleLength: 256,
+          patterns: [],
+        },
         hideDock: false,
+        dayStartHour: 0,
+        focus: {
+          workMinutes: 25,
+          shortBreakMinutes: 5,
+          longBreakMinutes: 15,
+          longBreakEvery: 4,
+          distractionAlerts: false,
+          blocklist: [],
+        },
+        breaks: {
+          enabled: false,
+          remindAfterMinutes: 50,
+          breakMinutes: 5,
+          snoozeMinutes: 10,
+        },
+        attention: {
+          flickerSeconds: 10,
+          deepWorkMinutes: 25,
+        },
+        compaction: {
+          nightly: false,
+          ignoreTitles: false,
+          blipSeconds: 5,
+        },
       };
     }
   },
@@ -379,6 +1398,81 @@ export const settingsApi = {
       return null;
     }
   },
+
+  /**
+   * Re-run redaction with the current settings over items in [from, to)
+   */
+  redactStoredItems: async (
+    from: number,
+    to: number
+  ): Promise<RedactionResult | null> => {
+    try {
+      return await invoke<RedactionResult>("redact_stored_items", { from, to });
+    } catch (error) {
+      console.error("redactStoredItems error:", error);
+      return null;
+    }
+  },
+};
+
+// ============================================================================
+// Encryption API
+// ============================================================================
+
+export const encryptionApi = {
+  /**
+   * Encrypt the database; without a passphrase the key is kept in the
+   * system keychain
+   */
+  enableEncryption: async (passphrase?: string): Promise<boolean> => {
+    try {
+      await invoke("enable_encryption", { passphrase });
+      return true;
+    } catch (error) {
+      console.error("enableEncryption error:", error);
+      return false;
+    }
+  },
+
+  /**
+   * Re-encrypt the database with a new passphrase or keychain key
+   */
+  changeEncryptionKey: async (passphrase?: string): Promise<boolean> => {
+    try {
+      await invoke("change_encryption_key", { passphrase });
+      return true;
+    } catch (error) {
+      console.error("changeEncryptionKey error:", error);
+      return false;
+    }
+  },
+
+  /**
+   * Write an unencrypted copy of the database to a file
+   */
+  exportDecryptedCopy: async (filePath: string): Promise<boolean> => {
+    try {
+      await invoke("export_decrypted_copy", { filePath });
+      return true;
+    } catch (error) {
+      console.error("exportDecryptedCopy error:", error);
+      return false;
+    }
+  },
+
+  /**
+   * Unlock an encrypted database; without a passphrase the system keychain
+   * is read again. False if no key fits
+   */
+  unlockDatabase: async (passphrase?: string): Promise<boolean> => {
+    try {
+      await invoke("unlock_database", { passphrase });
+      return true;
+    } catch (error) {
+      console.error("unlockDatabase error:", error);
+      return false;
+    }
+  },
 };
 
 // ============================================================================
@@ -387,18 +1481,18 @@ export const settingsApi = {
 
 export const exportApi = {
   /**
-   * Export data to CSV
+   * Export data for local dates (yyyy-MM-dd, inclusive) to CSV
    */
   exportToCsv: async (
-    from: number,
-    to: number,
+    fromDate: string,
+    toDate: string,
     filePath: string,
     taskName?: string
   ): Promise<ExportResult | null> => {
     try {
       return await invoke<ExportResult>("export_to_csv", {
-        from,
-        to,
+        fromDate,
+        toDate,
         taskName,
         filePath,
       });
@@ -409,18 +1503,18 @@ export const exportApi = {
   },
 
   /**
-   * Export data to JSON
+   * Export data for local dates (yyyy-MM-dd, inclusive) to JSON
    */
   exportToJson: async (
-    from: number,
-    to: number,
+    fromDate: string,
+    toDate: string,
     filePath: string,
     taskName?: string
   ): Promise<ExportResult | null> => {
     try {
       return await invoke<ExportResult>("export_to_json", {
-        from,
-        to,
+        fromDate,
+        toDate,
         taskName,
         filePath,
       });
@@ -1,7 +1,11 @@
 /**
  * Track item types
  */
-export type TrackItemType = "AppTrackItem" | "StatusTrackItem" | "LogTrackItem";
+export type TrackItemType =
+  | "AppTrackItem"
+  | "StatusTrackItem"
+  | "LogTrackItem"
+  | "FocusTrackItem";
 
 /**
  * System state types
@@ -21,8 +25,11 @@ export interface TrackItem {
   color?: string;
   beginDate: number; // timestamp in ms
   endDate: number; // timestamp in ms
+  productivity?: Productivity;
 }
 
+export type Productivity = "productive" | "neutral" | "distracting";
+
 /**
  * App track item - automatically tracked active window
  */
@@ -45,6 +52,13 @@ export interface LogTrackItem extends TrackItem {
   taskName: "LogTrackItem";
 }
 
+/**
+ * Focus track item - a focus block or break of a focus session
+ */
+export interface FocusTrackItem extends TrackItem {
+  taskName: "FocusTrackItem";
+}
+
 /**
  * Timeline state containing all track items
  */